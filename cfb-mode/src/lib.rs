@@ -40,6 +40,33 @@
 //! assert_eq!(data, &ciphertext[..]);
 //! ```
 //!
+//! [`Cfb`] uses segments of the full block size. CFB with smaller segments,
+//! e.g. the CFB1 and CFB8 modes of NIST SP 800-38A, is provided by [`CfbN`]:
+//!
+//! ```
+//! use aes::Aes128;
+//! use cfb_mode::Cfb1;
+//! use cfb_mode::cipher::{NewCipher, AsyncStreamCipher};
+//! use hex_literal::hex;
+//!
+//! let key = hex!("2b7e151628aed2a6abf7158809cf4f3c");
+//! let iv = hex!("000102030405060708090a0b0c0d0e0f");
+//!
+//! // SP 800-38A F.3.1: the plaintext bits are 0110 1011 1100 0001
+//! let mut data = hex!("6bc1");
+//! Cfb1::<Aes128>::new(&key.into(), &iv.into()).encrypt(&mut data);
+//! assert_eq!(data, hex!("68b3"));
+//! ```
+//!
+//! The `state` feature provides `to_bytes` and `from_bytes` methods which
+//! save and restore the shift register and position of a cipher.
+//!
+//! The `io` feature re-exports the `EncryptReader`, `DecryptReader`,
+//! `EncryptWriter` and `DecryptWriter` adapters of the
+//! [`cipher-io`](https://docs.rs/cipher-io) crate, which encrypt or decrypt
+//! the data read from a `std::io::Read` or written to a `std::io::Write`
+//! implementation.
+//!
 //! [1]: https://en.wikipedia.org/wiki/Block_cipher_mode_of_operation#CFB
 //! [2]: https://en.wikipedia.org/wiki/Stream_cipher#Self-synchronizing_stream_ciphers
 
@@ -48,15 +75,42 @@
     html_logo_url = "https://raw.githubusercontent.com/RustCrypto/meta/master/logo.svg",
     html_favicon_url = "https://raw.githubusercontent.com/RustCrypto/meta/master/logo.svg"
 )]
+#![forbid(unsafe_code)]
 #![warn(missing_docs, rust_2018_idioms)]
 
 pub use cipher;
 
 use cipher::{
-    generic_array::{typenum::Unsigned, GenericArray},
+    generic_array::{
+        typenum::{Unsigned, U1, U128, U64, U8},
+        GenericArray,
+    },
     AsyncStreamCipher, BlockCipher, BlockEncrypt, FromBlockCipher, ParBlocks,
 };
-use core::slice;
+
+#[cfg(feature = "io")]
+pub use cipher_io::{DecryptReader, DecryptWriter, EncryptReader, EncryptWriter};
+
+#[cfg(feature = "state")]
+pub use cipher_state::StateError;
+
+#[cfg(feature = "state")]
+use cipher::{BlockCipherKey, NewBlockCipher};
+#[cfg(feature = "state")]
+use cipher_state::State;
+
+mod segment;
+
+pub use segment::{CfbN, SegmentSize};
+
+/// CFB mode with 1-bit segments (CFB1).
+pub type Cfb1<C> = CfbN<C, U1>;
+/// CFB mode with 8-bit segments (CFB8).
+pub type Cfb8<C> = CfbN<C, U8>;
+/// CFB mode with 64-bit segments (CFB64).
+pub type Cfb64<C> = CfbN<C, U64>;
+/// CFB mode with 128-bit segments (CFB128).
+pub type Cfb128<C> = CfbN<C, U128>;
 
 /// CFB self-synchronizing stream cipher instance.
 pub struct Cfb<C: BlockCipher + BlockEncrypt> {
@@ -76,6 +130,61 @@ impl<C: BlockCipher + BlockEncrypt> FromBlockCipher for Cfb<C> {
     }
 }
 
+#[cfg(feature = "state")]
+impl<C: BlockCipher + BlockEncrypt + NewBlockCipher> Cfb<C> {
+    /// Length of the state serialized by [`Cfb::to_bytes`], with or without
+    /// the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(C::KeySize::USIZE, C::BlockSize::USIZE + 1, with_key)
+    }
+
+    /// Serialize the feedback block and position into `buf`, returning the
+    /// written prefix of it.
+    ///
+    /// The block cipher does not expose its key, so it's included only if
+    /// passed as `key`, which must be the key the block cipher was
+    /// initialized with. Without the key the state still contains the
+    /// keystream of the current block, which decrypts the rest of that
+    /// block.
+    ///
+    /// The format is a version byte, a flags byte, the key if included, the
+    /// feedback block, then the byte position within it.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&BlockCipherKey<C>>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let bs = C::BlockSize::USIZE;
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write(buf, key, 0, bs + 1, |body| {
+            body[..bs].copy_from_slice(&self.iv);
+            body[bs] = self.pos as u8;
+            Ok(())
+        })
+    }
+
+    /// Restore an instance from a state serialized by [`Cfb::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    pub fn from_bytes(bytes: &[u8], key: Option<&BlockCipherKey<C>>) -> Result<Self, StateError> {
+        let bs = C::BlockSize::USIZE;
+        let state = State::read(bytes, C::KeySize::USIZE, bs + 1, 0)?;
+        let body = state.body();
+
+        let pos = body[bs] as usize;
+        if pos >= bs {
+            return Err(StateError);
+        }
+        let cipher =
+            C::new_from_slice(state.key(key.map(|k| k.as_slice()))?).map_err(|_| StateError)?;
+        Ok(Self {
+            cipher,
+            iv: GenericArray::clone_from_slice(&body[..bs]),
+            pos,
+        })
+    }
+}
+
 impl<C: BlockCipher + BlockEncrypt> AsyncStreamCipher for Cfb<C> {
     fn encrypt(&mut self, mut data: &mut [u8]) {
         let bs = C::BlockSize::USIZE;
@@ -121,39 +230,27 @@ impl<C: BlockCipher + BlockEncrypt> AsyncStreamCipher for Cfb<C> {
         xor_set2(left, &mut iv[self.pos..]);
         self.cipher.encrypt_block(&mut iv);
 
-        let bss = bs * pb;
-        if pb != 1 && data.len() >= bss {
-            let mut iv_blocks: ParBlocks<C> =
-                unsafe { (&*(data.as_ptr() as *const ParBlocks<C>)).clone() };
-            self.cipher.encrypt_blocks(&mut iv_blocks);
-            let (block, r) = { data }.split_at_mut(bs);
-            data = r;
-            xor(block, iv.as_slice());
-
-            while data.len() >= 2 * bss - bs {
-                let (blocks, r) = { data }.split_at_mut(bss);
-                data = r;
-                let mut next_iv_blocks: ParBlocks<C> = unsafe {
-                    let ptr = data.as_ptr().offset(-(bs as isize));
-                    (&*(ptr as *const ParBlocks<C>)).clone()
-                };
-                self.cipher.encrypt_blocks(&mut next_iv_blocks);
-
-                xor(blocks, unsafe {
-                    let ptr = iv_blocks.as_mut_ptr() as *mut u8;
-                    slice::from_raw_parts(ptr, bss)
-                });
-                iv_blocks = next_iv_blocks;
-            }
+        // The keystream for each block is the encryption of the previous
+        // ciphertext block, so for `pb` blocks of ciphertext the keystream of
+        // the first one is already in `iv`, and encrypting all of them in
+        // parallel yields the keystream of the rest and the next `iv`
+        if pb != 1 {
+            let mut blocks = ParBlocks::<C>::default();
+            let mut chunks = data.chunks_exact_mut(bs * pb);
+            for chunk in &mut chunks {
+                for (block, c) in blocks.iter_mut().zip(chunk.chunks_exact(bs)) {
+                    block.copy_from_slice(c);
+                }
+                self.cipher.encrypt_par_blocks(&mut blocks);
 
-            let n = pb - 1;
-            let (blocks, r) = { data }.split_at_mut(n * bs);
-            data = r;
-            let chunks = blocks.chunks_mut(bs);
-            for (iv, block) in iv_blocks[..n].iter().zip(chunks) {
-                xor(block, iv.as_slice())
+                let mut ct_blocks = chunk.chunks_exact_mut(bs);
+                xor(ct_blocks.next().unwrap(), iv.as_slice());
+                for (c, block) in ct_blocks.zip(blocks.iter()) {
+                    xor(c, block.as_slice());
+                }
+                iv = blocks[pb - 1].clone();
             }
-            iv = iv_blocks[n].clone();
+            data = chunks.into_remainder();
         }
 
         let mut chunks = data.chunks_exact_mut(bs);
@@ -0,0 +1,222 @@
+//! CFB mode with an arbitrary segment size.
+
+use cipher::{
+    generic_array::{
+        typenum::{IsLessOrEqual, NonZero, Prod, True, Unsigned, U8},
+        GenericArray,
+    },
+    AsyncStreamCipher, Block, BlockCipher, BlockEncrypt, FromBlockCipher,
+};
+use core::{marker::PhantomData, ops::Mul};
+
+#[cfg(feature = "state")]
+use crate::StateError;
+#[cfg(feature = "state")]
+use cipher::{BlockCipherKey, NewBlockCipher};
+#[cfg(feature = "state")]
+use cipher_state::State;
+#[cfg(feature = "state")]
+use core::convert::TryInto;
+
+/// CFB mode with a segment size of `S` bits, as defined in [NIST SP 800-38A]
+/// section 6.3.
+///
+/// Segment sizes which are a multiple of 8 bits are processed a byte at a
+/// time, others a bit at a time, with the bits of each byte of data processed
+/// starting from the most significant one. `S` must be at least 1 and not
+/// larger than the block size of `C`, which is checked at compile time:
+///
+/// ```compile_fail
+/// use aes::Aes128;
+/// use cfb_mode::CfbN;
+/// use cfb_mode::cipher::{consts::U136, NewCipher};
+///
+/// let cipher = CfbN::<Aes128, U136>::new(&Default::default(), &Default::default());
+/// ```
+///
+/// With `S` equal to the block size the output is the same as
+/// [`Cfb`][crate::Cfb], which is considerably faster.
+///
+/// [NIST SP 800-38A]: https://csrc.nist.gov/publications/detail/sp/800-38a/final
+pub struct CfbN<C: BlockCipher + BlockEncrypt, S: SegmentSize<C::BlockSize>> {
+    cipher: C,
+    /// Input block of the cipher, into which the ciphertext is shifted
+    register: Block<C>,
+    /// Encryption of `register` at the start of the current segment
+    keystream: Block<C>,
+    /// Number of bits of the current segment processed so far
+    pos: usize,
+    segment: PhantomData<S>,
+}
+
+/// Segment size in bits of [`CfbN`] for a block cipher with blocks of
+/// `BlockSize` bytes: between 1 bit and the block size.
+///
+/// Implemented for all `typenum` unsigned integers in that range.
+pub trait SegmentSize<BlockSize>: Unsigned {}
+
+impl<BlockSize, S> SegmentSize<BlockSize> for S
+where
+    BlockSize: Mul<U8>,
+    S: Unsigned + NonZero + IsLessOrEqual<Prod<BlockSize, U8>, Output = True>,
+{
+}
+
+impl<C, S> CfbN<C, S>
+where
+    C: BlockCipher + BlockEncrypt,
+    S: SegmentSize<C::BlockSize>,
+{
+    #[inline]
+    fn process(&mut self, data: &mut [u8], decrypt: bool) {
+        if S::USIZE % 8 == 0 {
+            for b in data.iter_mut() {
+                let input = *b;
+                *b ^= self.keystream[self.pos / 8];
+                let c = if decrypt { input } else { *b };
+                self.shift_byte(c);
+                self.next_bits(8);
+            }
+        } else {
+            for b in data.iter_mut() {
+                let mut out = 0u8;
+                for i in (0..8).rev() {
+                    let input = (*b >> i) & 1;
+                    let k = (self.keystream[self.pos / 8] >> (7 - self.pos % 8)) & 1;
+                    out |= (input ^ k) << i;
+                    let c = if decrypt { input } else { input ^ k };
+                    self.shift_bit(c);
+                    self.next_bits(1);
+                }
+                *b = out;
+            }
+        }
+    }
+
+    /// Shift the register left by one byte and append `c`
+    #[inline(always)]
+    fn shift_byte(&mut self, c: u8) {
+        let n = self.register.len();
+        self.register.copy_within(1.., 0);
+        self.register[n - 1] = c;
+    }
+
+    /// Shift the register left by one bit and append `c`
+    #[inline(always)]
+    fn shift_bit(&mut self, c: u8) {
+        let mut carry = c;
+        for b in self.register.iter_mut().rev() {
+            let next = *b >> 7;
+            *b = (*b << 1) | carry;
+            carry = next;
+        }
+    }
+
+    /// Advance the position in the segment by `n` bits, starting a new
+    /// segment when the current one is complete
+    #[inline(always)]
+    fn next_bits(&mut self, n: usize) {
+        self.pos += n;
+        if self.pos == S::USIZE {
+            self.keystream = self.register.clone();
+            self.cipher.encrypt_block(&mut self.keystream);
+            self.pos = 0;
+        }
+    }
+}
+
+#[cfg(feature = "state")]
+impl<C, S> CfbN<C, S>
+where
+    C: BlockCipher + BlockEncrypt + NewBlockCipher,
+    S: SegmentSize<C::BlockSize>,
+{
+    /// Length of the state serialized by [`CfbN::to_bytes`], with or without
+    /// the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(C::KeySize::USIZE, 2 * C::BlockSize::USIZE + 2, with_key)
+    }
+
+    /// Serialize the shift register, the keystream of the current segment
+    /// and the position within it into `buf`, returning the written prefix
+    /// of it.
+    ///
+    /// The block cipher does not expose its key, so it's included only if
+    /// passed as `key`, which must be the key the block cipher was
+    /// initialized with. Without the key the state still contains the
+    /// keystream of the current segment.
+    ///
+    /// The format is a version byte, a flags byte, the key if included, the
+    /// shift register, the keystream block, then the number of bits of the
+    /// current segment processed so far as a little endian `u16`.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&BlockCipherKey<C>>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let bs = C::BlockSize::USIZE;
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write(buf, key, 0, 2 * bs + 2, |body| {
+            body[..bs].copy_from_slice(&self.register);
+            body[bs..2 * bs].copy_from_slice(&self.keystream);
+            body[2 * bs..].copy_from_slice(&(self.pos as u16).to_le_bytes());
+            Ok(())
+        })
+    }
+
+    /// Restore an instance from a state serialized by [`CfbN::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    pub fn from_bytes(bytes: &[u8], key: Option<&BlockCipherKey<C>>) -> Result<Self, StateError> {
+        let bs = C::BlockSize::USIZE;
+        let state = State::read(bytes, C::KeySize::USIZE, 2 * bs + 2, 0)?;
+        let body = state.body();
+
+        // segments which are a multiple of 8 bits are processed a byte at a time
+        let pos = u16::from_le_bytes(body[2 * bs..].try_into().unwrap()) as usize;
+        if pos >= S::USIZE || (S::USIZE % 8 == 0 && pos & 7 != 0) {
+            return Err(StateError);
+        }
+        let cipher =
+            C::new_from_slice(state.key(key.map(|k| k.as_slice()))?).map_err(|_| StateError)?;
+        let mut res = Self::from_block_cipher(cipher, GenericArray::from_slice(&body[..bs]));
+        res.keystream.copy_from_slice(&body[bs..2 * bs]);
+        res.pos = pos;
+        Ok(res)
+    }
+}
+
+impl<C, S> FromBlockCipher for CfbN<C, S>
+where
+    C: BlockCipher + BlockEncrypt,
+    S: SegmentSize<C::BlockSize>,
+{
+    type BlockCipher = C;
+    type NonceSize = C::BlockSize;
+
+    fn from_block_cipher(cipher: C, iv: &GenericArray<u8, Self::NonceSize>) -> Self {
+        let mut keystream = iv.clone();
+        cipher.encrypt_block(&mut keystream);
+        Self {
+            cipher,
+            register: iv.clone(),
+            keystream,
+            pos: 0,
+            segment: PhantomData,
+        }
+    }
+}
+
+impl<C, S> AsyncStreamCipher for CfbN<C, S>
+where
+    C: BlockCipher + BlockEncrypt,
+    S: SegmentSize<C::BlockSize>,
+{
+    fn encrypt(&mut self, data: &mut [u8]) {
+        self.process(data, false);
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) {
+        self.process(data, true);
+    }
+}
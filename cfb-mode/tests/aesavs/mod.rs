@@ -0,0 +1,127 @@
+//! NIST AESAVS known answer tests for CFB1 and CFB8.
+//!
+//! The tests read the `CFB1*.rsp` and `CFB8*.rsp` files of the AESAVS KAT
+//! archive (`KAT_AES.zip`, published by the NIST Cryptographic Algorithm
+//! Validation Program) from `tests/data/aesavs`. The files are not part of
+//! the repository yet, so the tests are ignored by default. To run them,
+//! extract the unmodified files into that directory and run
+//! `cargo test -- --ignored aesavs`, as the CI does.
+
+use aes::{Aes128, Aes192, Aes256};
+use cfb_mode::{Cfb1, Cfb8};
+use cipher::{AsyncStreamCipher, NewCipher};
+use std::fs;
+
+struct Vector {
+    encrypt: bool,
+    key: Vec<u8>,
+    iv: Vec<u8>,
+    plaintext: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+fn decode(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// Parse the vectors of an `.rsp` file. Values of CFB1 files are single bits,
+/// which are returned in the most significant bit of a byte.
+fn parse(data: &str, bits: bool) -> Vec<Vector> {
+    let value = |v: &str| -> Vec<u8> {
+        if bits {
+            vec![v.parse::<u8>().unwrap() << 7]
+        } else {
+            decode(v)
+        }
+    };
+
+    let mut vectors = Vec::new();
+    let mut encrypt = true;
+    let mut fields = Vec::new();
+    for line in data.lines().map(str::trim) {
+        match line {
+            "[ENCRYPT]" => encrypt = true,
+            "[DECRYPT]" => encrypt = false,
+            _ if line.starts_with('#') || line.is_empty() => {}
+            _ => {
+                let mut kv = line.splitn(2, " = ");
+                let (k, v) = (kv.next().unwrap(), kv.next().unwrap());
+                fields.push((k.to_string(), v.to_string()));
+                if fields.len() == 5 {
+                    let get = |name: &str| &fields.iter().find(|(k, _)| k == name).unwrap().1;
+                    vectors.push(Vector {
+                        encrypt,
+                        key: decode(get("KEY")),
+                        iv: decode(get("IV")),
+                        plaintext: value(get("PLAINTEXT")),
+                        ciphertext: value(get("CIPHERTEXT")),
+                    });
+                    fields.clear();
+                }
+            }
+        }
+    }
+    vectors
+}
+
+macro_rules! aesavs_test {
+    ($name:ident, $cipher:ty, $bits:expr, $file:expr) => {
+        #[test]
+        #[ignore]
+        fn $name() {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/aesavs/", $file);
+            let data = fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+            let vectors = parse(&data, $bits);
+            assert!(!vectors.is_empty());
+            // mask of the bits which the vector covers
+            let mask = if $bits { 0x80 } else { 0xff };
+
+            for (i, v) in vectors.iter().enumerate() {
+                let mut cipher = <$cipher>::new_from_slices(&v.key, &v.iv).unwrap();
+                let (input, expected) = if v.encrypt {
+                    (&v.plaintext, &v.ciphertext)
+                } else {
+                    (&v.ciphertext, &v.plaintext)
+                };
+                let mut buf = input.clone();
+                if v.encrypt {
+                    cipher.encrypt(&mut buf);
+                } else {
+                    cipher.decrypt(&mut buf);
+                }
+                for (a, b) in buf.iter().zip(expected.iter()) {
+                    assert_eq!(a & mask, *b, "{} vector {}", $file, i);
+                }
+            }
+        }
+    };
+}
+
+aesavs_test!(cfb1_gfsbox128, Cfb1<Aes128>, true, "CFB1GFSbox128.rsp");
+aesavs_test!(cfb1_gfsbox192, Cfb1<Aes192>, true, "CFB1GFSbox192.rsp");
+aesavs_test!(cfb1_gfsbox256, Cfb1<Aes256>, true, "CFB1GFSbox256.rsp");
+aesavs_test!(cfb1_keysbox128, Cfb1<Aes128>, true, "CFB1KeySbox128.rsp");
+aesavs_test!(cfb1_keysbox192, Cfb1<Aes192>, true, "CFB1KeySbox192.rsp");
+aesavs_test!(cfb1_keysbox256, Cfb1<Aes256>, true, "CFB1KeySbox256.rsp");
+aesavs_test!(cfb1_varkey128, Cfb1<Aes128>, true, "CFB1VarKey128.rsp");
+aesavs_test!(cfb1_varkey192, Cfb1<Aes192>, true, "CFB1VarKey192.rsp");
+aesavs_test!(cfb1_varkey256, Cfb1<Aes256>, true, "CFB1VarKey256.rsp");
+aesavs_test!(cfb1_vartxt128, Cfb1<Aes128>, true, "CFB1VarTxt128.rsp");
+aesavs_test!(cfb1_vartxt192, Cfb1<Aes192>, true, "CFB1VarTxt192.rsp");
+aesavs_test!(cfb1_vartxt256, Cfb1<Aes256>, true, "CFB1VarTxt256.rsp");
+
+aesavs_test!(cfb8_gfsbox128, Cfb8<Aes128>, false, "CFB8GFSbox128.rsp");
+aesavs_test!(cfb8_gfsbox192, Cfb8<Aes192>, false, "CFB8GFSbox192.rsp");
+aesavs_test!(cfb8_gfsbox256, Cfb8<Aes256>, false, "CFB8GFSbox256.rsp");
+aesavs_test!(cfb8_keysbox128, Cfb8<Aes128>, false, "CFB8KeySbox128.rsp");
+aesavs_test!(cfb8_keysbox192, Cfb8<Aes192>, false, "CFB8KeySbox192.rsp");
+aesavs_test!(cfb8_keysbox256, Cfb8<Aes256>, false, "CFB8KeySbox256.rsp");
+aesavs_test!(cfb8_varkey128, Cfb8<Aes128>, false, "CFB8VarKey128.rsp");
+aesavs_test!(cfb8_varkey192, Cfb8<Aes192>, false, "CFB8VarKey192.rsp");
+aesavs_test!(cfb8_varkey256, Cfb8<Aes256>, false, "CFB8VarKey256.rsp");
+aesavs_test!(cfb8_vartxt128, Cfb8<Aes128>, false, "CFB8VarTxt128.rsp");
+aesavs_test!(cfb8_vartxt192, Cfb8<Aes192>, false, "CFB8VarTxt192.rsp");
+aesavs_test!(cfb8_vartxt256, Cfb8<Aes256>, false, "CFB8VarTxt256.rsp");
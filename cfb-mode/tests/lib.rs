@@ -1,5 +1,11 @@
 use cfb_mode::Cfb;
 
+mod aesavs;
+mod parallel;
+mod segment;
+#[cfg(feature = "state")]
+mod state;
+
 // tests vectors are from:
 // https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38a.pdf
 cipher::stream_cipher_async_test!(cfb_aes128, "aes128", Cfb<aes::Aes128>);
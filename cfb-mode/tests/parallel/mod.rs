@@ -0,0 +1,95 @@
+//! Differential tests of the parallel decryption path against the serial one
+//! for different numbers of blocks processed in parallel
+
+use aes::{Aes128, BlockCipher, BlockEncrypt, NewBlockCipher};
+use cfb_mode::Cfb;
+use cipher::{
+    consts::{U1, U16, U2, U3, U32, U4, U5, U6, U7, U8, U9},
+    generic_array::{ArrayLength, GenericArray},
+    AsyncStreamCipher, FromBlockCipher,
+};
+use core::marker::PhantomData;
+
+/// AES-128 which claims to process `P` blocks in parallel
+///
+/// The same fixture is in the `cfb8` tests. Integration tests can only use
+/// their own crate's dev-dependencies, and a module shared by path from
+/// outside the package would be missing from the published crate, so each
+/// crate keeps its own copy.
+#[derive(Clone)]
+struct Par<P>(Aes128, PhantomData<P>);
+
+impl<P: ArrayLength<GenericArray<u8, U16>>> BlockCipher for Par<P> {
+    type BlockSize = U16;
+    type ParBlocks = P;
+}
+
+impl<P: ArrayLength<GenericArray<u8, U16>>> BlockEncrypt for Par<P> {
+    fn encrypt_block(&self, block: &mut GenericArray<u8, U16>) {
+        self.0.encrypt_block(block);
+    }
+}
+
+const KEY: [u8; 16] = *b"very secret key.";
+const IV: [u8; 16] = *b"unique init vect";
+const LEN: usize = 16 * 37 + 5;
+
+fn new<P: ArrayLength<GenericArray<u8, U16>>>() -> Cfb<Par<P>> {
+    Cfb::from_block_cipher(Par(Aes128::new(&KEY.into()), PhantomData), &IV.into())
+}
+
+fn check<P: ArrayLength<GenericArray<u8, U16>>>() {
+    let mut ct = [0u8; LEN];
+    for (i, b) in ct.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let pt = ct;
+    new::<U1>().encrypt(&mut ct);
+
+    // serial decryption, one block at a time
+    let mut expected = ct;
+    new::<U1>().decrypt(&mut expected);
+    assert_eq!(expected[..], pt[..]);
+
+    for split in 0..=LEN {
+        let mut buf = ct;
+        let mut cipher = new::<P>();
+        let (l, r) = buf.split_at_mut(split);
+        cipher.decrypt(l);
+        cipher.decrypt(r);
+        assert_eq!(buf[..], expected[..], "split at {}", split);
+    }
+
+    // three pieces, so that the parallel path starts at any offset
+    for split in 0..LEN - 40 {
+        let mut buf = ct;
+        let mut cipher = new::<P>();
+        let (l, r) = buf.split_at_mut(split);
+        let (m, r) = r.split_at_mut(40);
+        cipher.decrypt(l);
+        cipher.decrypt(m);
+        cipher.decrypt(r);
+        assert_eq!(buf[..], expected[..], "split at {}", split);
+    }
+}
+
+macro_rules! par_test {
+    ($name:ident, $p:ty) => {
+        #[test]
+        fn $name() {
+            check::<$p>();
+        }
+    };
+}
+
+par_test!(par_blocks_1, U1);
+par_test!(par_blocks_2, U2);
+par_test!(par_blocks_3, U3);
+par_test!(par_blocks_4, U4);
+par_test!(par_blocks_5, U5);
+par_test!(par_blocks_6, U6);
+par_test!(par_blocks_7, U7);
+par_test!(par_blocks_8, U8);
+par_test!(par_blocks_9, U9);
+par_test!(par_blocks_16, U16);
+par_test!(par_blocks_32, U32);
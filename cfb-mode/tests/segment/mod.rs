@@ -0,0 +1,137 @@
+//! Segment-size-generic CFB
+
+use aes::{Aes128, Aes192, Aes256, BlockEncrypt, NewBlockCipher};
+use cfb_mode::{Cfb, Cfb1, Cfb128, Cfb64, Cfb8, CfbN};
+use cipher::{consts::U24, generic_array::GenericArray, AsyncStreamCipher, NewCipher};
+use hex_literal::hex;
+
+const KEY: [u8; 16] = hex!("2b7e151628aed2a6abf7158809cf4f3c");
+const KEY192: [u8; 24] = hex!("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b");
+const KEY256: [u8; 32] = hex!("603deb1015ca71be2b73aef0857d7781 1f352c073b6108d72d9810a30914dff4");
+const IV: [u8; 16] = hex!("000102030405060708090a0b0c0d0e0f");
+const PT: [u8; 64] = hex!(
+    "6bc1bee22e409f96e93d7e117393172a ae2d8a571e03ac9c9eb76fac45af8e51
+     30c81c46a35ce411e5fbc1191a0a52ef f69f2445df4f9b17ad2b417be66c3710"
+);
+
+/// Encrypt and decrypt `pt`, checking the result for every split point
+macro_rules! check {
+    ($cipher:ty, $pt:expr, $ct:expr) => {
+        check!($cipher, KEY, $pt, $ct)
+    };
+    ($cipher:ty, $key:expr, $pt:expr, $ct:expr) => {{
+        let (key, pt, ct) = ($key, $pt, $ct);
+        for split in 0..=pt.len() {
+            let mut cipher = <$cipher>::new(&key.into(), &IV.into());
+            let mut buf = pt;
+            let (l, r) = buf.split_at_mut(split);
+            cipher.encrypt(l);
+            cipher.encrypt(r);
+            assert_eq!(buf[..], ct[..]);
+
+            let mut cipher = <$cipher>::new(&key.into(), &IV.into());
+            let (l, r) = buf.split_at_mut(split);
+            cipher.decrypt(l);
+            cipher.decrypt(r);
+            assert_eq!(buf[..], pt[..]);
+        }
+    }};
+}
+
+/// SP 800-38A F.3.1
+#[test]
+fn cfb1_aes128() {
+    check!(Cfb1<Aes128>, hex!("6bc1"), hex!("68b3"));
+}
+
+/// SP 800-38A F.3.3
+#[test]
+fn cfb1_aes192() {
+    check!(Cfb1<Aes192>, KEY192, hex!("6bc1"), hex!("9359"));
+}
+
+/// SP 800-38A F.3.5
+#[test]
+fn cfb1_aes256() {
+    check!(Cfb1<Aes256>, KEY256, hex!("6bc1"), hex!("9029"));
+}
+
+/// SP 800-38A F.3.7
+#[test]
+fn cfb8_aes128() {
+    check!(
+        Cfb8<Aes128>,
+        hex!("6bc1bee22e409f96e93d7e117393172aae2d"),
+        hex!("3b79424c9c0dd436bace9e0ed4586a4f32b9")
+    );
+}
+
+/// SP 800-38A F.3.9
+#[test]
+fn cfb8_aes192() {
+    check!(
+        Cfb8<Aes192>,
+        KEY192,
+        hex!("6bc1bee22e409f96e93d7e117393172aae2d"),
+        hex!("cda2521ef0a905ca44cd057cbf0d47a0678a")
+    );
+}
+
+/// SP 800-38A F.3.11
+#[test]
+fn cfb8_aes256() {
+    check!(
+        Cfb8<Aes256>,
+        KEY256,
+        hex!("6bc1bee22e409f96e93d7e117393172aae2d"),
+        hex!("dc1f1a8520a64db55fcc8ac554844e889700")
+    );
+}
+
+/// SP 800-38A F.3.13
+#[test]
+fn cfb128_aes128() {
+    let ct = hex!(
+        "3b3fd92eb72dad20333449f8e83cfb4a c8a64537a0b3a93fcde3cdad9f1ce58b
+         26751f67a3cbb140b1808cf187a4f4df c04b05357c5d1c0eeac4c66f9ff7f2e6"
+    );
+    check!(Cfb128<Aes128>, PT, ct);
+    check!(Cfb<Aes128>, PT, ct);
+}
+
+/// Reference CFB with `s`-byte segments
+fn reference(s: usize, decrypt: bool, data: &mut [u8]) {
+    let aes = Aes128::new(&KEY.into());
+    let mut register = IV;
+    for segment in data.chunks_mut(s) {
+        let mut block = GenericArray::clone_from_slice(&register);
+        aes.encrypt_block(&mut block);
+        let input = segment.to_vec();
+        for (b, k) in segment.iter_mut().zip(block.iter()) {
+            *b ^= k;
+        }
+        let c = if decrypt { &input[..] } else { &segment[..] };
+        register.copy_within(s.., 0);
+        register[16 - s..16 - s + c.len()].copy_from_slice(c);
+    }
+}
+
+#[test]
+fn cfb64_aes128() {
+    let mut ct = PT;
+    reference(8, false, &mut ct);
+    check!(Cfb64<Aes128>, PT, ct);
+
+    // the last segment is incomplete
+    let pt = hex!("6bc1bee22e409f96e93d7e117393172aae2d");
+    let mut ct = pt;
+    reference(8, false, &mut ct);
+    check!(Cfb64<Aes128>, pt, ct);
+}
+
+#[test]
+fn cfb24_aes128() {
+    let mut ct = PT;
+    reference(3, false, &mut ct);
+    check!(CfbN<Aes128, U24>, PT, ct);
+}
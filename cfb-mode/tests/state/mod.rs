@@ -0,0 +1,46 @@
+//! Serialized CFB states
+
+use aes::Aes128;
+use cfb_mode::{
+    cipher::{
+        consts::{U1, U128, U24, U64, U8},
+        AsyncStreamCipher, NewCipher,
+    },
+    Cfb, CfbN,
+};
+
+const KEY: [u8; 16] = *b"very secret key.";
+const IV: [u8; 16] = *b"unique init vect";
+
+cipher_state::stream_async_state_test!(cfb, Cfb<Aes128>);
+cipher_state::stream_async_state_test!(cfb1, CfbN<Aes128, U1>);
+cipher_state::stream_async_state_test!(cfb8, CfbN<Aes128, U8>);
+cipher_state::stream_async_state_test!(cfb24, CfbN<Aes128, U24>);
+cipher_state::stream_async_state_test!(cfb64, CfbN<Aes128, U64>);
+cipher_state::stream_async_state_test!(cfb128, CfbN<Aes128, U128>);
+
+#[test]
+fn rejects_bad_position() {
+    let key = KEY.into();
+    let mut cipher = CfbN::<Aes128, U64>::new(&key, &IV.into());
+    cipher.encrypt(&mut [0u8; 3]);
+    let mut buf = [0u8; 64];
+    let len = cipher.to_bytes(None, &mut buf).unwrap().len();
+
+    // not a whole number of bytes into the segment
+    let mut state = buf;
+    state[len - 2] = 25;
+    assert!(CfbN::<Aes128, U64>::from_bytes(&state[..len], Some(&key)).is_err());
+
+    // past the end of the segment
+    let mut state = buf;
+    state[len - 2] = 64;
+    assert!(CfbN::<Aes128, U64>::from_bytes(&state[..len], Some(&key)).is_err());
+
+    // past the end of the block
+    let cipher = Cfb::<Aes128>::new(&key, &IV.into());
+    let len = cipher.to_bytes(None, &mut buf).unwrap().len();
+    let mut state = buf;
+    state[len - 1] = 16;
+    assert!(Cfb::<Aes128>::from_bytes(&state[..len], Some(&key)).is_err());
+}
@@ -40,6 +40,15 @@
 //! assert_eq!(data, &ciphertext[..]);
 //! ```
 //!
+//! The `state` feature provides `to_bytes` and `from_bytes` methods which
+//! save and restore the shift register of a cipher.
+//!
+//! The `io` feature re-exports the `EncryptReader`, `DecryptReader`,
+//! `EncryptWriter` and `DecryptWriter` adapters of the
+//! [`cipher-io`](https://docs.rs/cipher-io) crate, which encrypt or decrypt
+//! the data read from a `std::io::Read` or written to a `std::io::Write`
+//! implementation.
+//!
 //! [1]: https://en.wikipedia.org/wiki/Block_cipher_mode_of_operation#CFB
 //! [2]: https://en.wikipedia.org/wiki/Stream_cipher#Self-synchronizing_stream_ciphers
 
@@ -54,9 +63,21 @@
 pub use cipher;
 
 use cipher::{
-    generic_array::GenericArray, AsyncStreamCipher, BlockCipher, BlockEncrypt, FromBlockCipher,
+    generic_array::{typenum::Unsigned, GenericArray},
+    AsyncStreamCipher, BlockCipher, BlockEncrypt, FromBlockCipher, ParBlocks,
 };
 
+#[cfg(feature = "io")]
+pub use cipher_io::{DecryptReader, DecryptWriter, EncryptReader, EncryptWriter};
+
+#[cfg(feature = "state")]
+pub use cipher_state::StateError;
+
+#[cfg(feature = "state")]
+use cipher::{BlockCipherKey, NewBlockCipher};
+#[cfg(feature = "state")]
+use cipher_state::State;
+
 /// CFB self-synchronizing stream cipher instance.
 pub struct Cfb8<C: BlockCipher + BlockEncrypt> {
     cipher: C,
@@ -75,6 +96,50 @@ impl<C: BlockCipher + BlockEncrypt> FromBlockCipher for Cfb8<C> {
     }
 }
 
+#[cfg(feature = "state")]
+impl<C: BlockCipher + BlockEncrypt + NewBlockCipher> Cfb8<C> {
+    /// Length of the state serialized by [`Cfb8::to_bytes`], with or without
+    /// the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(C::KeySize::USIZE, C::BlockSize::USIZE, with_key)
+    }
+
+    /// Serialize the shift register into `buf`, returning the written prefix
+    /// of it.
+    ///
+    /// The block cipher does not expose its key, so it's included only if
+    /// passed as `key`, which must be the key the block cipher was
+    /// initialized with. The shift register is the last block of ciphertext,
+    /// so a state without the key reveals nothing the ciphertext doesn't.
+    ///
+    /// The format is a version byte, a flags byte, the key if included, then
+    /// the shift register, i.e. the last block of ciphertext.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&BlockCipherKey<C>>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write(buf, key, 0, C::BlockSize::USIZE, |register| {
+            register.copy_from_slice(&self.iv);
+            Ok(())
+        })
+    }
+
+    /// Restore an instance from a state serialized by [`Cfb8::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    pub fn from_bytes(bytes: &[u8], key: Option<&BlockCipherKey<C>>) -> Result<Self, StateError> {
+        let state = State::read(bytes, C::KeySize::USIZE, C::BlockSize::USIZE, 0)?;
+        let cipher =
+            C::new_from_slice(state.key(key.map(|k| k.as_slice()))?).map_err(|_| StateError)?;
+        Ok(Self::from_block_cipher(
+            cipher,
+            GenericArray::from_slice(state.body()),
+        ))
+    }
+}
+
 impl<C: BlockCipher + BlockEncrypt> AsyncStreamCipher for Cfb8<C> {
     fn encrypt(&mut self, data: &mut [u8]) {
         let mut iv = self.iv.clone();
@@ -92,6 +157,40 @@ impl<C: BlockCipher + BlockEncrypt> AsyncStreamCipher for Cfb8<C> {
     fn decrypt(&mut self, data: &mut [u8]) {
         let mut iv = self.iv.clone();
         let n = iv.len();
+        let pb = C::ParBlocks::USIZE;
+
+        // The shift register for each byte is made of the previous `n` bytes
+        // of IV and ciphertext, so the registers of `pb` consecutive bytes
+        // can be encrypted in parallel
+        let data = if pb > 1 {
+            let mut blocks = ParBlocks::<C>::default();
+            let mut chunks = data.chunks_exact_mut(pb);
+            for chunk in &mut chunks {
+                for (i, block) in blocks.iter_mut().enumerate() {
+                    if i < n {
+                        block[..n - i].copy_from_slice(&iv[i..]);
+                        block[n - i..].copy_from_slice(&chunk[..i]);
+                    } else {
+                        block.copy_from_slice(&chunk[i - n..i]);
+                    }
+                }
+                self.cipher.encrypt_par_blocks(&mut blocks);
+
+                if pb < n {
+                    iv.copy_within(pb.., 0);
+                    iv[n - pb..].copy_from_slice(chunk);
+                } else {
+                    iv.copy_from_slice(&chunk[pb - n..]);
+                }
+                for (b, block) in chunk.iter_mut().zip(blocks.iter()) {
+                    *b ^= block[0];
+                }
+            }
+            chunks.into_remainder()
+        } else {
+            data
+        };
+
         for b in data.iter_mut() {
             let iv_copy = iv.clone();
             self.cipher.encrypt_block(&mut iv);
@@ -5,3 +5,100 @@ use cfb8::Cfb8;
 cipher::stream_cipher_async_test!(cfb8_aes128, "aes128", Cfb8<aes::Aes128>);
 cipher::stream_cipher_async_test!(cfb8_aes192, "aes192", Cfb8<aes::Aes192>);
 cipher::stream_cipher_async_test!(cfb8_aes256, "aes256", Cfb8<aes::Aes256>);
+
+mod parallel {
+    use aes::{Aes128, BlockCipher, BlockEncrypt, NewBlockCipher};
+    use cfb8::Cfb8;
+    use cipher::{
+        consts::{U1, U16, U32},
+        generic_array::{ArrayLength, GenericArray},
+        AsyncStreamCipher, FromBlockCipher,
+    };
+    use core::marker::PhantomData;
+
+    /// AES-128 which claims to process `P` blocks in parallel
+    ///
+    /// The same fixture is in the `cfb-mode` tests. Integration tests can only
+    /// use their own crate's dev-dependencies, and a module shared by path from
+    /// outside the package would be missing from the published crate, so each
+    /// crate keeps its own copy.
+    #[derive(Clone)]
+    struct Par<P>(Aes128, PhantomData<P>);
+
+    impl<P: ArrayLength<GenericArray<u8, U16>>> BlockCipher for Par<P> {
+        type BlockSize = U16;
+        type ParBlocks = P;
+    }
+
+    impl<P: ArrayLength<GenericArray<u8, U16>>> BlockEncrypt for Par<P> {
+        fn encrypt_block(&self, block: &mut GenericArray<u8, U16>) {
+            self.0.encrypt_block(block);
+        }
+    }
+
+    const KEY: [u8; 16] = *b"very secret key.";
+    const IV: [u8; 16] = *b"unique init vect";
+
+    fn check<P: ArrayLength<GenericArray<u8, U16>>>() {
+        let new = || {
+            let aes = Par::<P>(Aes128::new(&KEY.into()), PhantomData);
+            Cfb8::from_block_cipher(aes, &IV.into())
+        };
+
+        let mut ct = [0u8; 100];
+        for (i, b) in ct.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let pt = ct;
+        new().encrypt(&mut ct);
+
+        for split in 0..=ct.len() {
+            let mut buf = ct;
+            let mut cipher = new();
+            let (l, r) = buf.split_at_mut(split);
+            cipher.decrypt(l);
+            cipher.decrypt(r);
+            assert_eq!(buf[..], pt[..], "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn decrypt_serial() {
+        check::<U1>();
+    }
+
+    #[test]
+    fn decrypt_par_aes() {
+        check::<<Aes128 as BlockCipher>::ParBlocks>();
+    }
+
+    #[test]
+    fn decrypt_par_wide() {
+        check::<U32>();
+    }
+}
+
+#[cfg(feature = "state")]
+cipher_state::stream_async_state_test!(cfb8_aes128_state, Cfb8<aes::Aes128>);
+
+/// The `cipher-io` adapters re-exported by the `io` feature
+#[cfg(feature = "io")]
+#[test]
+fn cfb8_aes128_io_round_trip() {
+    use cfb8::{DecryptReader, EncryptWriter};
+    use cipher::NewCipher;
+    use std::io::{Read, Write};
+
+    let key = [0x42; 16].into();
+    let iv = [0x24; 16].into();
+
+    let mut writer = EncryptWriter::new(Cfb8::<aes::Aes128>::new(&key, &iv), Vec::new());
+    writer.write_all(b"hello world").unwrap();
+    let (_, ciphertext) = writer.into_inner().unwrap();
+    assert_ne!(ciphertext, b"hello world");
+
+    let mut reader = DecryptReader::new(Cfb8::<aes::Aes128>::new(&key, &iv), &ciphertext[..]);
+    let mut plaintext = Vec::new();
+    reader.read_to_end(&mut plaintext).unwrap();
+    assert_eq!(plaintext, b"hello world");
+}
@@ -0,0 +1,236 @@
+//! ChaCha20Poly1305 and XChaCha20Poly1305 authenticated encryption
+//! ([RFC 8439] Section 2.8 and [draft-irtf-cfrg-xchacha]).
+//!
+//! The Poly1305 one-time key is derived from the first 32 bytes of keystream
+//! block 0, and the payload is encrypted starting at keystream block 1.
+//!
+//! [RFC 8439]: https://tools.ietf.org/html/rfc8439#section-2.8
+//! [draft-irtf-cfrg-xchacha]: https://tools.ietf.org/html/draft-arciszewski-xchacha-03
+
+use crate::{ChaCha20, Key, Nonce, BLOCK_SIZE};
+use cipher::{
+    consts::U16,
+    generic_array::GenericArray,
+    {NewCipher, StreamCipher, StreamCipherSeek},
+};
+use core::{convert::TryInto, fmt};
+use poly1305::{
+    universal_hash::{NewUniversalHash, UniversalHash},
+    Poly1305,
+};
+use subtle::ConstantTimeEq;
+
+#[cfg(feature = "xchacha")]
+use crate::{XChaCha20, XNonce};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Poly1305 authentication tag
+pub type Tag = GenericArray<u8, U16>;
+
+/// Error type returned when encryption fails because the message is too long,
+/// or when decryption fails because the authentication tag doesn't match.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Error;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("aead::Error")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// ChaCha20Poly1305 authenticated encryption with 96-bit nonces
+/// ([RFC 8439] Section 2.8).
+///
+/// [RFC 8439]: https://tools.ietf.org/html/rfc8439#section-2.8
+#[derive(Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "aead")))]
+pub struct ChaCha20Poly1305 {
+    key: Key,
+}
+
+impl ChaCha20Poly1305 {
+    /// Create a new ChaCha20Poly1305 instance with the given key.
+    pub fn new(key: &Key) -> Self {
+        Self { key: *key }
+    }
+
+    /// Encrypt the given buffer in-place, returning the authentication tag.
+    pub fn encrypt_in_place_detached(
+        &self,
+        nonce: &Nonce,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag, Error> {
+        Cipher::new(ChaCha20::new(&self.key, nonce))
+            .encrypt_in_place_detached(associated_data, buffer)
+    }
+
+    /// Authenticate the given buffer against `tag` and, if it matches,
+    /// decrypt it in-place.
+    ///
+    /// The buffer is left unmodified if authentication fails.
+    pub fn decrypt_in_place_detached(
+        &self,
+        nonce: &Nonce,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag,
+    ) -> Result<(), Error> {
+        Cipher::new(ChaCha20::new(&self.key, nonce)).decrypt_in_place_detached(
+            associated_data,
+            buffer,
+            tag,
+        )
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl Zeroize for ChaCha20Poly1305 {
+    fn zeroize(&mut self) {
+        self.key.as_mut_slice().zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl Drop for ChaCha20Poly1305 {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// XChaCha20Poly1305 authenticated encryption with 192-bit extended nonces
+/// ([draft-irtf-cfrg-xchacha]).
+///
+/// [draft-irtf-cfrg-xchacha]: https://tools.ietf.org/html/draft-arciszewski-xchacha-03
+#[cfg(feature = "xchacha")]
+#[derive(Clone)]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "aead", feature = "xchacha"))))]
+pub struct XChaCha20Poly1305 {
+    key: Key,
+}
+
+#[cfg(feature = "xchacha")]
+impl XChaCha20Poly1305 {
+    /// Create a new XChaCha20Poly1305 instance with the given key.
+    pub fn new(key: &Key) -> Self {
+        Self { key: *key }
+    }
+
+    /// Encrypt the given buffer in-place, returning the authentication tag.
+    pub fn encrypt_in_place_detached(
+        &self,
+        nonce: &XNonce,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag, Error> {
+        Cipher::new(XChaCha20::new(&self.key, nonce))
+            .encrypt_in_place_detached(associated_data, buffer)
+    }
+
+    /// Authenticate the given buffer against `tag` and, if it matches,
+    /// decrypt it in-place.
+    ///
+    /// The buffer is left unmodified if authentication fails.
+    pub fn decrypt_in_place_detached(
+        &self,
+        nonce: &XNonce,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag,
+    ) -> Result<(), Error> {
+        Cipher::new(XChaCha20::new(&self.key, nonce)).decrypt_in_place_detached(
+            associated_data,
+            buffer,
+            tag,
+        )
+    }
+}
+
+#[cfg(all(feature = "xchacha", feature = "zeroize"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl Zeroize for XChaCha20Poly1305 {
+    fn zeroize(&mut self) {
+        self.key.as_mut_slice().zeroize();
+    }
+}
+
+#[cfg(all(feature = "xchacha", feature = "zeroize"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl Drop for XChaCha20Poly1305 {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// AEAD construction instantiated with a particular key and nonce
+struct Cipher<C: StreamCipher + StreamCipherSeek> {
+    cipher: C,
+    mac: Poly1305,
+}
+
+impl<C: StreamCipher + StreamCipherSeek> Cipher<C> {
+    /// Derive the Poly1305 key from block 0 and position the keystream at
+    /// the start of block 1
+    fn new(mut cipher: C) -> Self {
+        let mut mac_key = poly1305::Key::default();
+        cipher.apply_keystream(&mut mac_key);
+        let mac = Poly1305::new(&mac_key);
+        #[cfg(feature = "zeroize")]
+        mac_key.as_mut_slice().zeroize();
+
+        cipher.seek(BLOCK_SIZE as u64);
+        Self { cipher, mac }
+    }
+
+    fn encrypt_in_place_detached(
+        mut self,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag, Error> {
+        // fails without modifying `buffer` if the keystream is exhausted
+        self.cipher.try_apply_keystream(buffer).map_err(|_| Error)?;
+
+        self.authenticate(associated_data, buffer)?;
+        Ok(self.mac.finalize().into_bytes())
+    }
+
+    fn decrypt_in_place_detached(
+        mut self,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag,
+    ) -> Result<(), Error> {
+        self.authenticate(associated_data, buffer)?;
+        let expected = self.mac.finalize().into_bytes();
+
+        if bool::from(expected.as_slice().ct_eq(tag.as_slice())) {
+            self.cipher.try_apply_keystream(buffer).map_err(|_| Error)
+        } else {
+            Err(Error)
+        }
+    }
+
+    /// Input the padded associated data and ciphertext, followed by their
+    /// lengths, into Poly1305
+    fn authenticate(&mut self, associated_data: &[u8], ciphertext: &[u8]) -> Result<(), Error> {
+        let associated_data_len: u64 = associated_data.len().try_into().map_err(|_| Error)?;
+        let ciphertext_len: u64 = ciphertext.len().try_into().map_err(|_| Error)?;
+
+        self.mac.update_padded(associated_data);
+        self.mac.update_padded(ciphertext);
+
+        let mut block = poly1305::Block::default();
+        block[..8].copy_from_slice(&associated_data_len.to_le_bytes());
+        block[8..].copy_from_slice(&ciphertext_len.to_le_bytes());
+        self.mac.update(&block);
+
+        Ok(())
+    }
+}
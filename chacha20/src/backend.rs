@@ -12,13 +12,29 @@ cfg_if! {
         not(feature = "force-soft")
     ))] {
         pub(crate) mod autodetect;
+        #[cfg(feature = "avx512")]
+        pub(crate) mod avx512;
         pub(crate) mod avx2;
         pub(crate) mod sse2;
 
         pub(crate) use self::autodetect::BUFFER_SIZE;
         pub use self::autodetect::Core;
 
-        #[cfg(feature = "xchacha")]
+        #[cfg(any(test, feature = "xchacha"))]
+        pub(crate) mod soft;
+    } else if #[cfg(all(
+        target_arch = "aarch64",
+        target_endian = "little",
+        target_feature = "neon",
+        feature = "neon",
+        not(feature = "force-soft")
+    ))] {
+        pub(crate) mod neon;
+
+        pub(crate) use self::neon::BUFFER_SIZE;
+        pub use self::neon::Core;
+
+        #[cfg(any(test, feature = "xchacha"))]
         pub(crate) mod soft;
     } else {
         pub(crate) mod soft;
@@ -1,85 +1,205 @@
-//! Autodetection support for AVX2 CPU intrinsics on x86 CPUs, with fallback
-//! to the SSE2 backend when it's unavailable (the `sse2` target feature is
-//! enabled-by-default on all x86(_64) CPUs)
+//! Autodetection support for AVX2 (and optionally AVX-512) CPU intrinsics on
+//! x86 CPUs, with fallback to the SSE2 backend when they're unavailable (the
+//! `sse2` target feature is enabled-by-default on all x86(_64) CPUs)
+//!
+//! Each backend operates on a different number of blocks in parallel, so the
+//! number of bytes produced per call is selected at runtime, see
+//! [`Core::buffer_size`].
 
+#[cfg(feature = "avx512")]
+use super::avx512;
 use super::{avx2, sse2};
 use crate::{rounds::Rounds, BLOCK_SIZE, IV_SIZE, KEY_SIZE};
 use core::mem::ManuallyDrop;
 
-/// Size of buffers passed to `generate` and `apply_keystream` for this
-/// backend, which operates on two blocks in parallel for optimal performance.
-pub(crate) const BUFFER_SIZE: usize = BLOCK_SIZE * 2;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Size of the largest buffer produced by any of the detected backends, i.e.
+/// the maximum value of [`Core::buffer_size`].
+#[cfg(feature = "avx512")]
+pub(crate) const BUFFER_SIZE: usize = avx512::BUFFER_SIZE;
+
+/// Size of the largest buffer produced by any of the detected backends, i.e.
+/// the maximum value of [`Core::buffer_size`].
+#[cfg(not(feature = "avx512"))]
+pub(crate) const BUFFER_SIZE: usize = avx2::BUFFER_SIZE;
 
 cpufeatures::new!(avx2_cpuid, "avx2");
+#[cfg(feature = "avx512")]
+cpufeatures::new!(avx512_cpuid, "avx512f");
 
 /// The ChaCha20 core function.
 pub struct Core<R: Rounds> {
     inner: Inner<R>,
-    token: avx2_cpuid::InitToken,
+    backend: Backend,
 }
 
 union Inner<R: Rounds> {
+    #[cfg(feature = "avx512")]
+    avx512: ManuallyDrop<avx512::Core<R>>,
     avx2: ManuallyDrop<avx2::Core<R>>,
     sse2: ManuallyDrop<sse2::Core<R>>,
 }
 
+/// Backend selected at runtime, i.e. the active field of [`Inner`]
+#[derive(Copy, Clone)]
+enum Backend {
+    #[cfg(feature = "avx512")]
+    Avx512,
+    Avx2,
+    Sse2,
+}
+
+/// Evaluate `$body` with `$core` bound to a reference to the active backend
+macro_rules! dispatch {
+    ($self:expr, $core:ident => $body:expr) => {
+        unsafe {
+            match $self.backend {
+                #[cfg(feature = "avx512")]
+                Backend::Avx512 => {
+                    let $core = &$self.inner.avx512;
+                    $body
+                }
+                Backend::Avx2 => {
+                    let $core = &$self.inner.avx2;
+                    $body
+                }
+                Backend::Sse2 => {
+                    let $core = &$self.inner.sse2;
+                    $body
+                }
+            }
+        }
+    };
+}
+
 impl<R: Rounds> Core<R> {
     /// Initialize ChaCha core function with the given key size, IV, and
     /// number of rounds.
     #[inline]
     pub fn new(key: &[u8; KEY_SIZE], iv: [u8; IV_SIZE]) -> Self {
-        let (token, avx2_present) = avx2_cpuid::init_get();
+        #[cfg(feature = "avx512")]
+        {
+            if avx512_cpuid::get() {
+                return Self {
+                    inner: Inner {
+                        avx512: ManuallyDrop::new(avx512::Core::new(key, iv)),
+                    },
+                    backend: Backend::Avx512,
+                };
+            }
+        }
 
-        let inner = if avx2_present {
-            Inner {
-                avx2: ManuallyDrop::new(avx2::Core::new(key, iv)),
+        if avx2_cpuid::get() {
+            Self {
+                inner: Inner {
+                    avx2: ManuallyDrop::new(avx2::Core::new(key, iv)),
+                },
+                backend: Backend::Avx2,
             }
         } else {
-            Inner {
-                sse2: ManuallyDrop::new(sse2::Core::new(key, iv)),
+            Self {
+                inner: Inner {
+                    sse2: ManuallyDrop::new(sse2::Core::new(key, iv)),
+                },
+                backend: Backend::Sse2,
             }
-        };
+        }
+    }
 
-        Self { inner, token }
+    /// Number of bytes of keystream produced by each call to the active
+    /// backend, which is always a multiple of `BLOCK_SIZE`.
+    #[inline]
+    pub fn buffer_size(&self) -> usize {
+        match self.backend {
+            #[cfg(feature = "avx512")]
+            Backend::Avx512 => avx512::BUFFER_SIZE,
+            Backend::Avx2 => avx2::BUFFER_SIZE,
+            Backend::Sse2 => sse2::BUFFER_SIZE,
+        }
     }
 
-    /// Generate output, overwriting data already in the buffer
+    /// Generate output, overwriting data already in the buffer.
+    ///
+    /// The length of `output` must be a multiple of [`Core::buffer_size`].
     #[inline]
     pub fn generate(&self, counter: u64, output: &mut [u8]) {
-        if self.token.get() {
-            unsafe { (*self.inner.avx2).generate(counter, output) }
-        } else {
-            unsafe { (*self.inner.sse2).generate(counter, output) }
+        let buffer_size = self.buffer_size();
+        debug_assert_eq!(output.len() % buffer_size, 0);
+        let blocks = (buffer_size / BLOCK_SIZE) as u64;
+
+        for (i, chunk) in output.chunks_exact_mut(buffer_size).enumerate() {
+            let counter = counter.wrapping_add(i as u64 * blocks);
+            dispatch!(self, core => core.generate(counter, chunk))
         }
     }
 
-    /// Apply generated keystream to the output buffer
+    /// Apply generated keystream to the output buffer.
+    ///
+    /// The length of `output` must be a multiple of [`Core::buffer_size`].
     #[inline]
     #[cfg(feature = "cipher")]
     pub fn apply_keystream(&self, counter: u64, output: &mut [u8]) {
-        if self.token.get() {
-            unsafe { (*self.inner.avx2).apply_keystream(counter, output) }
-        } else {
-            unsafe { (*self.inner.sse2).apply_keystream(counter, output) }
+        let buffer_size = self.buffer_size();
+        debug_assert_eq!(output.len() % buffer_size, 0);
+        let blocks = (buffer_size / BLOCK_SIZE) as u64;
+
+        for (i, chunk) in output.chunks_exact_mut(buffer_size).enumerate() {
+            let counter = counter.wrapping_add(i as u64 * blocks);
+            dispatch!(self, core => core.apply_keystream(counter, chunk))
         }
     }
 }
 
 impl<R: Rounds> Clone for Core<R> {
     fn clone(&self) -> Self {
-        let inner = if self.token.get() {
-            Inner {
+        let inner = match self.backend {
+            #[cfg(feature = "avx512")]
+            Backend::Avx512 => Inner {
+                avx512: ManuallyDrop::new(unsafe { (*self.inner.avx512).clone() }),
+            },
+            Backend::Avx2 => Inner {
                 avx2: ManuallyDrop::new(unsafe { (*self.inner.avx2).clone() }),
-            }
-        } else {
-            Inner {
+            },
+            Backend::Sse2 => Inner {
                 sse2: ManuallyDrop::new(unsafe { (*self.inner.sse2).clone() }),
-            }
+            },
         };
 
         Self {
             inner,
-            token: self.token,
+            backend: self.backend,
+        }
+    }
+}
+
+impl<R: Rounds> Drop for Core<R> {
+    fn drop(&mut self) {
+        // drop whichever backend is active, which wipes it when the
+        // `zeroize` feature is enabled
+        unsafe {
+            match self.backend {
+                #[cfg(feature = "avx512")]
+                Backend::Avx512 => ManuallyDrop::drop(&mut self.inner.avx512),
+                Backend::Avx2 => ManuallyDrop::drop(&mut self.inner.avx2),
+                Backend::Sse2 => ManuallyDrop::drop(&mut self.inner.sse2),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Rounds> Zeroize for Core<R> {
+    fn zeroize(&mut self) {
+        unsafe {
+            match self.backend {
+                #[cfg(feature = "avx512")]
+                Backend::Avx512 => (*self.inner.avx512).zeroize(),
+                Backend::Avx2 => (*self.inner.avx2).zeroize(),
+                Backend::Sse2 => (*self.inner.sse2).zeroize(),
+            }
         }
     }
 }
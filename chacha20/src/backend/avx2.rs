@@ -8,7 +8,6 @@
 //! Goll, M., and Gueron,S.: Vectorization of ChaCha Stream Cipher. Cryptology ePrint Archive,
 //! Report 2013/759, November, 2013, <https://eprint.iacr.org/2013/759.pdf>
 
-use super::autodetect::BUFFER_SIZE;
 use crate::{rounds::Rounds, BLOCK_SIZE, CONSTANTS, IV_SIZE, KEY_SIZE};
 use core::{convert::TryInto, marker::PhantomData};
 
@@ -17,8 +16,16 @@ use core::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
 use core::arch::x86_64::*;
 
+#[cfg(feature = "zeroize")]
+use core::{mem, ptr, sync::atomic};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Size of buffers passed to `generate` and `apply_keystream` for this
+/// backend, which operates on two blocks in parallel.
+pub(crate) const BUFFER_SIZE: usize = BLOCK_SIZE * 2;
+
 /// The ChaCha20 core function (AVX2 accelerated implementation for x86/x86_64)
-// TODO(tarcieri): zeroize?
 #[derive(Clone)]
 pub(crate) struct Core<R: Rounds> {
     v0: __m256i,
@@ -104,6 +111,28 @@ impl<R: Rounds> Core<R> {
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl<R: Rounds> Zeroize for Core<R> {
+    fn zeroize(&mut self) {
+        // `zeroize` only implements `Zeroize` for `__m256i` when AVX is enabled
+        // at compile time, so clear the registers' memory directly instead
+        unsafe {
+            ptr::write_volatile(&mut self.v0, mem::zeroed());
+            ptr::write_volatile(&mut self.v1, mem::zeroed());
+            ptr::write_volatile(&mut self.v2, mem::zeroed());
+        }
+        atomic::compiler_fence(atomic::Ordering::SeqCst);
+        self.iv.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<R: Rounds> Drop for Core<R> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 #[inline]
 #[target_feature(enable = "avx2")]
 #[allow(clippy::cast_ptr_alignment)] // loadu supports unaligned loads
@@ -0,0 +1,298 @@
+//! The ChaCha20 core function. Defined in RFC 8439 Section 2.3.
+//!
+//! <https://tools.ietf.org/html/rfc8439#section-2.3>
+//!
+//! AVX-512 implementation for x86/x86-64 CPUs. Each 512-bit register holds
+//! one row of the state for four blocks, and two such sets of registers are
+//! processed in an interleaved fashion for a total of eight blocks per call.
+
+use crate::{rounds::Rounds, BLOCK_SIZE, CONSTANTS, IV_SIZE, KEY_SIZE};
+use core::{convert::TryInto, marker::PhantomData};
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+#[cfg(feature = "zeroize")]
+use core::{mem, ptr, sync::atomic};
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Number of blocks held in each 512-bit register
+const LANES: usize = 4;
+
+/// Number of register sets processed in parallel
+const SETS: usize = 2;
+
+/// Size of buffers passed to `generate` and `apply_keystream` for this
+/// backend, which operates on eight blocks in parallel.
+pub(crate) const BUFFER_SIZE: usize = BLOCK_SIZE * LANES * SETS;
+
+/// The ChaCha20 core function (AVX-512 accelerated implementation for x86/x86_64)
+#[derive(Clone)]
+pub(crate) struct Core<R: Rounds> {
+    v0: __m512i,
+    v1: __m512i,
+    v2: __m512i,
+    iv: [i32; 2],
+    rounds: PhantomData<R>,
+}
+
+impl<R: Rounds> Core<R> {
+    /// Initialize core function with the given key size, IV, and number of rounds
+    #[inline]
+    pub fn new(key: &[u8; KEY_SIZE], iv: [u8; IV_SIZE]) -> Self {
+        let (v0, v1, v2) = unsafe { key_setup(key) };
+        let iv = [
+            i32::from_le_bytes(iv[4..].try_into().unwrap()),
+            i32::from_le_bytes(iv[..4].try_into().unwrap()),
+        ];
+
+        Self {
+            v0,
+            v1,
+            v2,
+            iv,
+            rounds: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn generate(&self, counter: u64, output: &mut [u8]) {
+        debug_assert_eq!(output.len(), BUFFER_SIZE);
+
+        unsafe {
+            let blocks = self.rounds(counter);
+            for (chunk, block) in output.chunks_exact_mut(BLOCK_SIZE).zip(&blocks) {
+                _mm512_storeu_si512(chunk.as_mut_ptr() as *mut __m512i, *block);
+            }
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "cipher")]
+    pub fn apply_keystream(&self, counter: u64, output: &mut [u8]) {
+        debug_assert_eq!(output.len(), BUFFER_SIZE);
+
+        unsafe {
+            let blocks = self.rounds(counter);
+            for (chunk, block) in output.chunks_exact_mut(BLOCK_SIZE).zip(&blocks) {
+                let b = _mm512_loadu_si512(chunk.as_ptr() as *const __m512i);
+                let out = _mm512_xor_si512(*block, b);
+                _mm512_storeu_si512(chunk.as_mut_ptr() as *mut __m512i, out);
+            }
+        }
+    }
+
+    /// Compute eight consecutive blocks starting at `counter`, each returned
+    /// as a single 512-bit register in keystream order
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn rounds(&self, counter: u64) -> [__m512i; LANES * SETS] {
+        let mut v0 = [self.v0; SETS];
+        let mut v1 = [self.v1; SETS];
+        let mut v2 = [self.v2; SETS];
+        let mut v3 = [_mm512_setzero_si512(); SETS];
+        for (i, v) in v3.iter_mut().enumerate() {
+            *v = iv_setup(self.iv, counter.wrapping_add((i * LANES) as u64));
+        }
+        let v3_orig = v3;
+
+        for _ in 0..(R::COUNT / 2) {
+            for i in 0..SETS {
+                double_quarter_round(&mut v0[i], &mut v1[i], &mut v2[i], &mut v3[i]);
+            }
+        }
+
+        let mut blocks = [_mm512_setzero_si512(); LANES * SETS];
+        for (i, chunk) in blocks.chunks_exact_mut(LANES).enumerate() {
+            let a = _mm512_add_epi32(v0[i], self.v0);
+            let b = _mm512_add_epi32(v1[i], self.v1);
+            let c = _mm512_add_epi32(v2[i], self.v2);
+            let d = _mm512_add_epi32(v3[i], v3_orig[i]);
+            chunk.copy_from_slice(&transpose(a, b, c, d));
+        }
+
+        blocks
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<R: Rounds> Zeroize for Core<R> {
+    fn zeroize(&mut self) {
+        // `zeroize` doesn't implement `Zeroize` for `__m512i`, so clear the
+        // registers' memory directly instead
+        unsafe {
+            ptr::write_volatile(&mut self.v0, mem::zeroed());
+            ptr::write_volatile(&mut self.v1, mem::zeroed());
+            ptr::write_volatile(&mut self.v2, mem::zeroed());
+        }
+        atomic::compiler_fence(atomic::Ordering::SeqCst);
+        self.iv.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<R: Rounds> Drop for Core<R> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[inline]
+#[target_feature(enable = "avx512f")]
+#[allow(clippy::cast_ptr_alignment)] // loadu supports unaligned loads
+unsafe fn key_setup(key: &[u8; KEY_SIZE]) -> (__m512i, __m512i, __m512i) {
+    let v0 = _mm_loadu_si128(CONSTANTS.as_ptr() as *const __m128i);
+    let v1 = _mm_loadu_si128(key.as_ptr().offset(0x00) as *const __m128i);
+    let v2 = _mm_loadu_si128(key.as_ptr().offset(0x10) as *const __m128i);
+
+    (
+        _mm512_broadcast_i32x4(v0),
+        _mm512_broadcast_i32x4(v1),
+        _mm512_broadcast_i32x4(v2),
+    )
+}
+
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn iv_setup(iv: [i32; 2], counter: u64) -> __m512i {
+    let s3 = _mm_set_epi32(
+        iv[0],
+        iv[1],
+        ((counter >> 32) & 0xffff_ffff) as i32,
+        (counter & 0xffff_ffff) as i32,
+    );
+
+    _mm512_add_epi64(
+        _mm512_broadcast_i32x4(s3),
+        _mm512_set_epi64(0, 3, 0, 2, 0, 1, 0, 0),
+    )
+}
+
+/// Rearrange the four rows of state held in `a`, `b`, `c` and `d` (one
+/// block per 128-bit lane) into four registers each containing a whole block
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn transpose(a: __m512i, b: __m512i, c: __m512i, d: __m512i) -> [__m512i; LANES] {
+    let ab01 = _mm512_shuffle_i32x4(a, b, 0b01_00_01_00);
+    let cd01 = _mm512_shuffle_i32x4(c, d, 0b01_00_01_00);
+    let ab23 = _mm512_shuffle_i32x4(a, b, 0b11_10_11_10);
+    let cd23 = _mm512_shuffle_i32x4(c, d, 0b11_10_11_10);
+
+    [
+        _mm512_shuffle_i32x4(ab01, cd01, 0b10_00_10_00),
+        _mm512_shuffle_i32x4(ab01, cd01, 0b11_01_11_01),
+        _mm512_shuffle_i32x4(ab23, cd23, 0b10_00_10_00),
+        _mm512_shuffle_i32x4(ab23, cd23, 0b11_01_11_01),
+    ]
+}
+
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn double_quarter_round(
+    v0: &mut __m512i,
+    v1: &mut __m512i,
+    v2: &mut __m512i,
+    v3: &mut __m512i,
+) {
+    add_xor_rot(v0, v1, v2, v3);
+    rows_to_cols(v0, v1, v2, v3);
+    add_xor_rot(v0, v1, v2, v3);
+    cols_to_rows(v0, v1, v2, v3);
+}
+
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn rows_to_cols(_v0: &mut __m512i, v1: &mut __m512i, v2: &mut __m512i, v3: &mut __m512i) {
+    *v1 = _mm512_shuffle_epi32(*v1, _MM_PERM_ADCB); // _MM_SHUFFLE(0, 3, 2, 1)
+    *v2 = _mm512_shuffle_epi32(*v2, _MM_PERM_BADC); // _MM_SHUFFLE(1, 0, 3, 2)
+    *v3 = _mm512_shuffle_epi32(*v3, _MM_PERM_CBAD); // _MM_SHUFFLE(2, 1, 0, 3)
+}
+
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn cols_to_rows(_v0: &mut __m512i, v1: &mut __m512i, v2: &mut __m512i, v3: &mut __m512i) {
+    *v1 = _mm512_shuffle_epi32(*v1, _MM_PERM_CBAD); // _MM_SHUFFLE(2, 1, 0, 3)
+    *v2 = _mm512_shuffle_epi32(*v2, _MM_PERM_BADC); // _MM_SHUFFLE(1, 0, 3, 2)
+    *v3 = _mm512_shuffle_epi32(*v3, _MM_PERM_ADCB); // _MM_SHUFFLE(0, 3, 2, 1)
+}
+
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn add_xor_rot(v0: &mut __m512i, v1: &mut __m512i, v2: &mut __m512i, v3: &mut __m512i) {
+    // a = ADD512_32(a,b); d = XOR512(d,a); d = ROL512_16(d);
+    *v0 = _mm512_add_epi32(*v0, *v1);
+    *v3 = _mm512_rol_epi32(_mm512_xor_si512(*v3, *v0), 16);
+
+    // c = ADD512_32(c,d); b = XOR512(b,c); b = ROL512_12(b);
+    *v2 = _mm512_add_epi32(*v2, *v3);
+    *v1 = _mm512_rol_epi32(_mm512_xor_si512(*v1, *v2), 12);
+
+    // a = ADD512_32(a,b); d = XOR512(d,a); d = ROL512_8(d);
+    *v0 = _mm512_add_epi32(*v0, *v1);
+    *v3 = _mm512_rol_epi32(_mm512_xor_si512(*v3, *v0), 8);
+
+    // c = ADD512_32(c,d); b = XOR512(b,c); b = ROL512_7(b);
+    *v2 = _mm512_add_epi32(*v2, *v3);
+    *v1 = _mm512_rol_epi32(_mm512_xor_si512(*v1, *v2), 7);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::soft, rounds::R20};
+
+    cpufeatures::new!(avx512_cpuid, "avx512f");
+
+    // random inputs for testing
+    const R_IV: [u8; IV_SIZE] = [0x2f, 0x96, 0xa8, 0x4a, 0xf8, 0x92, 0xbc, 0x94];
+    const R_KEY: [u8; KEY_SIZE] = [
+        0x11, 0xf2, 0x72, 0x99, 0xe1, 0x79, 0x6d, 0xef, 0xb, 0xdc, 0x6a, 0x58, 0x1f, 0x1, 0x58,
+        0x94, 0x92, 0x19, 0x69, 0x3f, 0xe9, 0x35, 0x16, 0x72, 0x63, 0xd1, 0xd, 0x94, 0x6d, 0x31,
+        0x34, 0x11,
+    ];
+
+    #[test]
+    fn generate_matches_soft() {
+        if !avx512_cpuid::get() {
+            return;
+        }
+
+        // straddle the boundary where the upper half of the counter changes
+        for &counter in &[0, 1, 0xffff_fffc, u64::MAX - 3] {
+            let mut expected = [0u8; BUFFER_SIZE];
+            let mut soft_core = soft::Core::<R20>::new(&R_KEY, R_IV);
+            for (i, block) in expected.chunks_exact_mut(BLOCK_SIZE).enumerate() {
+                soft_core.generate(counter.wrapping_add(i as u64), block);
+            }
+
+            let mut output = [0u8; BUFFER_SIZE];
+            Core::<R20>::new(&R_KEY, R_IV).generate(counter, &mut output);
+            assert_eq!(&output[..], &expected[..]);
+        }
+    }
+
+    #[cfg(feature = "cipher")]
+    #[test]
+    fn apply_keystream_matches_generate() {
+        if !avx512_cpuid::get() {
+            return;
+        }
+
+        let core = Core::<R20>::new(&R_KEY, R_IV);
+        let mut keystream = [0u8; BUFFER_SIZE];
+        core.generate(7, &mut keystream);
+
+        let mut data = [0u8; BUFFER_SIZE];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        core.apply_keystream(7, &mut data);
+
+        for (i, (a, b)) in data.iter().zip(&keystream).enumerate() {
+            assert_eq!(*a, *b ^ i as u8);
+        }
+    }
+}
@@ -8,6 +8,9 @@
 use crate::{rounds::Rounds, BLOCK_SIZE, CONSTANTS, IV_SIZE, KEY_SIZE};
 use core::{convert::TryInto, marker::PhantomData};
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
 /// Size of buffers passed to `generate` and `apply_keystream` for this backend
 #[allow(dead_code)]
 pub(crate) const BUFFER_SIZE: usize = BLOCK_SIZE;
@@ -16,7 +19,6 @@ pub(crate) const BUFFER_SIZE: usize = BLOCK_SIZE;
 const STATE_WORDS: usize = 16;
 
 /// The ChaCha20 core function.
-// TODO(tarcieri): zeroize?
 #[derive(Clone)]
 #[allow(dead_code)]
 pub struct Core<R: Rounds> {
@@ -56,6 +58,13 @@ impl<R: Rounds> Core<R> {
         }
     }
 
+    /// Number of bytes of keystream produced by each call to `generate` and
+    /// `apply_keystream`
+    #[inline]
+    pub fn buffer_size(&self) -> usize {
+        BUFFER_SIZE
+    }
+
     /// Generate output, overwriting data already in the buffer
     #[inline]
     pub fn generate(&mut self, counter: u64, output: &mut [u8]) {
@@ -115,6 +124,22 @@ impl<R: Rounds> Core<R> {
     }
 }
 
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Rounds> Zeroize for Core<R> {
+    fn zeroize(&mut self) {
+        self.state.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Rounds> Drop for Core<R> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 /// The ChaCha20 quarter round function
 #[inline]
 pub(crate) fn quarter_round(
@@ -4,7 +4,6 @@
 //!
 //! SSE2-optimized implementation for x86/x86-64 CPUs.
 
-use super::autodetect::BUFFER_SIZE;
 use crate::{rounds::Rounds, BLOCK_SIZE, CONSTANTS, IV_SIZE, KEY_SIZE};
 use core::{convert::TryInto, marker::PhantomData};
 
@@ -13,8 +12,14 @@ use core::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
 use core::arch::x86_64::*;
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Size of buffers passed to `generate` and `apply_keystream` for this
+/// backend, which operates on two blocks in parallel.
+pub(crate) const BUFFER_SIZE: usize = BLOCK_SIZE * 2;
+
 /// The ChaCha20 core function (SSE2 accelerated implementation for x86/x86_64)
-// TODO(tarcieri): zeroize?
 #[derive(Clone)]
 pub struct Core<R: Rounds> {
     v0: __m128i,
@@ -50,7 +55,7 @@ impl<R: Rounds> Core<R> {
         for (i, chunk) in output.chunks_exact_mut(BLOCK_SIZE).enumerate() {
             unsafe {
                 let (mut v0, mut v1, mut v2) = (self.v0, self.v1, self.v2);
-                let mut v3 = iv_setup(self.iv, counter.checked_add(i as u64).unwrap());
+                let mut v3 = iv_setup(self.iv, counter.wrapping_add(i as u64));
                 self.rounds(&mut v0, &mut v1, &mut v2, &mut v3);
                 store(v0, v1, v2, v3, chunk)
             }
@@ -66,7 +71,7 @@ impl<R: Rounds> Core<R> {
         for (i, chunk) in output.chunks_exact_mut(BLOCK_SIZE).enumerate() {
             unsafe {
                 let (mut v0, mut v1, mut v2) = (self.v0, self.v1, self.v2);
-                let mut v3 = iv_setup(self.iv, counter.checked_add(i as u64).unwrap());
+                let mut v3 = iv_setup(self.iv, counter.wrapping_add(i as u64));
                 self.rounds(&mut v0, &mut v1, &mut v2, &mut v3);
 
                 for (ch, a) in chunk.chunks_exact_mut(0x10).zip(&[v0, v1, v2, v3]) {
@@ -100,6 +105,23 @@ impl<R: Rounds> Core<R> {
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl<R: Rounds> Zeroize for Core<R> {
+    fn zeroize(&mut self) {
+        self.v0.zeroize();
+        self.v1.zeroize();
+        self.v2.zeroize();
+        self.iv.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<R: Rounds> Drop for Core<R> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 #[inline]
 #[target_feature(enable = "sse2")]
 #[allow(clippy::cast_ptr_alignment)] // loadu supports unaligned loads
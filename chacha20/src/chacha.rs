@@ -24,6 +24,17 @@ use core::{
 #[cfg(docsrs)]
 use cipher::generic_array::GenericArray;
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+#[cfg(feature = "state")]
+use crate::{StateError, KEY_SIZE};
+#[cfg(feature = "state")]
+use cipher_state::{SeekableState, POSITION_LEN};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
 /// ChaCha8 stream cipher (reduced-round variant of [`ChaCha20`] with 8 rounds)
 pub type ChaCha8 = ChaCha<R8, C32>;
 
@@ -45,14 +56,15 @@ pub type Key = cipher::CipherKey<ChaCha20>;
 /// Implemented as an alias for [`GenericArray`].
 pub type Nonce = cipher::Nonce<ChaCha20>;
 
-/// Internal buffer
+/// Internal buffer, large enough for the widest backend. Only the first
+/// `Core::buffer_size` bytes are used by the backend selected at runtime.
 type Buffer = [u8; BUFFER_SIZE];
 
-/// How much to increment the counter by for each buffer we generate.
-/// Normally this is 1 but the AVX2 backend uses double-wide buffers.
-// TODO(tarcieri): support a parallel blocks count like the `ctr` crate
-// See: <https://github.com/RustCrypto/stream-ciphers/blob/907e94b/ctr/src/lib.rs#L73>
-const COUNTER_INCR: u64 = (BUFFER_SIZE as u64) / (BLOCK_SIZE as u64);
+/// Size of the chunks of data processed by each task in
+/// [`ChaCha::par_apply_keystream`]. This is a multiple of the buffer size of
+/// every backend, so each chunk starts at a counter boundary.
+#[cfg(feature = "rayon")]
+const PAR_CHUNK_SIZE: usize = 1 << 16;
 
 /// ChaCha family stream cipher, generic around a number of rounds.
 ///
@@ -70,7 +82,7 @@ pub struct ChaCha<R: Rounds, MC: MaxCounter> {
     buffer: Buffer,
 
     /// Position within buffer, or `None` if the buffer is not in use
-    buffer_pos: u8,
+    buffer_pos: usize,
 
     /// Current counter value relative to the start of the keystream
     counter: u64,
@@ -79,6 +91,10 @@ pub struct ChaCha<R: Rounds, MC: MaxCounter> {
     /// the extra 4 bytes in the 96-byte nonce RFC 8439 version (or is always
     /// 0 in the legacy version)
     counter_offset: u64,
+
+    /// Nonce the cipher was initialized with, saved by `to_bytes`
+    #[cfg_attr(not(feature = "state"), allow(dead_code))]
+    nonce: Nonce,
 }
 
 impl<R: Rounds, MC: MaxCounter> NewCipher for ChaCha<R, MC> {
@@ -106,38 +122,161 @@ impl<R: Rounds, MC: MaxCounter> NewCipher for ChaCha<R, MC> {
             buffer_pos: 0,
             counter: 0,
             counter_offset,
+            nonce: *nonce,
         }
     }
 }
 
 impl<R: Rounds, MC: MaxCounter> StreamCipher for ChaCha<R, MC> {
-    fn try_apply_keystream(&mut self, mut data: &mut [u8]) -> Result<(), LoopError> {
+    fn try_apply_keystream(&mut self, data: &mut [u8]) -> Result<(), LoopError> {
+        self.process(data, xor, |core, counter, buf| {
+            core.apply_keystream(counter, buf)
+        })
+    }
+}
+
+impl<R: Rounds, MC: MaxCounter> StreamCipherSeek for ChaCha<R, MC> {
+    fn try_current_pos<T: SeekNum>(&self) -> Result<T, OverflowError> {
+        // the buffer may span several blocks, so split the position within
+        // it into whole blocks and a byte offset into the current block
+        let counter = self
+            .counter
+            .checked_add((self.buffer_pos / BLOCK_SIZE) as u64)
+            .ok_or(OverflowError)?;
+        let pos = (self.buffer_pos % BLOCK_SIZE) as u8;
+        T::from_block_byte(counter, pos, BLOCK_SIZE as u8)
+    }
+
+    fn try_seek<T: SeekNum>(&mut self, pos: T) -> Result<(), LoopError> {
+        let res: (u64, u8) = pos.to_block_byte(BLOCK_SIZE as u8)?;
+        let old_counter = self.counter;
+        let old_buffer_pos = self.buffer_pos;
+
+        // the buffer is refilled starting from the block being seeked to
+        self.counter = res.0;
+        self.buffer_pos = res.1 as usize;
+
+        if let Err(e) = self.check_data_len(&[0]) {
+            self.counter = old_counter;
+            self.buffer_pos = old_buffer_pos;
+            return Err(e);
+        }
+
+        if self.buffer_pos != 0 {
+            self.generate_block(self.counter);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "state")]
+#[cfg_attr(docsrs, doc(cfg(feature = "state")))]
+impl<R: Rounds, MC: MaxCounter> ChaCha<R, MC> {
+    /// Length of the state serialized by [`ChaCha::to_bytes`], with or
+    /// without the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(KEY_SIZE, 12 + POSITION_LEN, with_key)
+    }
+
+    /// Serialize the nonce and keystream position into `buf`, returning the
+    /// written prefix of it.
+    ///
+    /// The cipher does not retain its key, so it's included only if passed
+    /// as `key`, which must be the key the cipher was initialized with.
+    /// Without the key the state is just the nonce and a position, so it can
+    /// be stored alongside the ciphertext.
+    ///
+    /// The format is a version byte, a flags byte, the key if included, the
+    /// nonce, then the number of the current block as a little endian `u64`
+    /// and the byte position within that block. It doesn't depend on the
+    /// backend, so a state can be restored on a different CPU.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&Key>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let pos = self.try_current_pos().map_err(|_| StateError)?;
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write_seekable(buf, key, &self.nonce, pos, BLOCK_SIZE as u8)
+    }
+
+    /// Restore a cipher from a state serialized by [`ChaCha::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    pub fn from_bytes(bytes: &[u8], key: Option<&Key>) -> Result<Self, StateError> {
+        let state = SeekableState::read(bytes, KEY_SIZE, 12, BLOCK_SIZE as u8)?;
+        let key = Key::from_slice(state.key(key.map(|k| k.as_slice()))?);
+        let mut cipher = Self::new(key, Nonce::from_slice(state.nonce()));
+        cipher.try_seek(state.pos()).map_err(|_| StateError)?;
+        Ok(cipher)
+    }
+}
+
+impl<R: Rounds, MC: MaxCounter> ChaCha<R, MC> {
+    /// Write keystream to `out`, overwriting its contents.
+    ///
+    /// The output and the position of the cipher afterwards are the same as
+    /// for [`StreamCipher::apply_keystream`] on a zeroed buffer, without
+    /// paying for the XOR, e.g. to derive keys from the keystream.
+    ///
+    /// # Panics
+    ///
+    /// If the end of the keystream is reached with the given length.
+    pub fn write_keystream(&mut self, out: &mut [u8]) {
+        self.try_write_keystream(out).unwrap();
+    }
+
+    /// Write keystream to `out`, see [`ChaCha::write_keystream`].
+    ///
+    /// Returns [`LoopError`] and leaves the cipher unchanged if the end of
+    /// the keystream is reached with the given length.
+    pub fn try_write_keystream(&mut self, out: &mut [u8]) -> Result<(), LoopError> {
+        self.process(out, copy, |core, counter, buf| core.generate(counter, buf))
+    }
+
+    /// Nonce the cipher was initialized with
+    #[cfg(all(feature = "legacy", feature = "state"))]
+    pub(crate) fn nonce(&self) -> &Nonce {
+        &self.nonce
+    }
+
+    /// Combine `data` with the keystream, using `op` for the bytes from the
+    /// internal buffer and `bulk` for whole buffers of keystream.
+    #[inline]
+    fn process(
+        &mut self,
+        mut data: &mut [u8],
+        op: impl Fn(&mut [u8], &[u8]),
+        mut bulk: impl FnMut(&mut Core<R>, u64, &mut [u8]),
+    ) -> Result<(), LoopError> {
         self.check_data_len(data)?;
-        let pos = self.buffer_pos as usize;
+        let buffer_size = self.block.buffer_size();
+        let counter_incr = self.counter_incr();
+        let pos = self.buffer_pos;
 
         let mut counter = self.counter;
-        // xor with leftover bytes from the last call if any
+        // use up the leftover bytes from the last call if any
         if pos != 0 {
-            if data.len() < BUFFER_SIZE - pos {
+            if data.len() < buffer_size - pos {
                 let n = pos + data.len();
-                xor(data, &self.buffer[pos..n]);
-                self.buffer_pos = n as u8;
+                op(data, &self.buffer[pos..n]);
+                self.buffer_pos = n;
                 return Ok(());
             } else {
-                let (l, r) = data.split_at_mut(BUFFER_SIZE - pos);
+                let (l, r) = data.split_at_mut(buffer_size - pos);
                 data = r;
-                if let Some(new_ctr) = counter.checked_add(COUNTER_INCR) {
+                if let Some(new_ctr) = counter.checked_add(counter_incr) {
                     counter = new_ctr;
                 } else if data.is_empty() {
-                    self.buffer_pos = BUFFER_SIZE as u8;
+                    self.buffer_pos = buffer_size;
                 } else {
                     return Err(LoopError);
                 }
-                xor(l, &self.buffer[pos..]);
+                op(l, &self.buffer[pos..buffer_size]);
             }
         }
 
-        if self.buffer_pos == BUFFER_SIZE as u8 {
+        if self.buffer_pos == buffer_size {
             if data.is_empty() {
                 return Ok(());
             } else {
@@ -145,62 +284,54 @@ impl<R: Rounds, MC: MaxCounter> StreamCipher for ChaCha<R, MC> {
             }
         }
 
-        let mut chunks = data.chunks_exact_mut(BUFFER_SIZE);
-        for chunk in &mut chunks {
-            // TODO(tarcieri): double check this should be checked and not wrapping
-            let counter_with_offset = self.counter_offset.checked_add(counter).unwrap();
-            self.block.apply_keystream(counter_with_offset, chunk);
-            counter = counter.checked_add(COUNTER_INCR).unwrap();
+        let blocks = (data.len() / buffer_size) as u64 * counter_incr;
+        let mut chunks = data.chunks_exact_mut(buffer_size);
+        for (i, chunk) in (&mut chunks).enumerate() {
+            let counter = self.counter_with_offset(counter.wrapping_add(i as u64 * counter_incr));
+            bulk(&mut self.block, counter, chunk);
         }
 
         let rem = chunks.into_remainder();
-        self.buffer_pos = rem.len() as u8;
         self.counter = counter;
+        self.buffer_pos = 0;
+        self.advance(blocks);
         if !rem.is_empty() {
-            self.generate_block(counter);
-            xor(rem, &self.buffer[..rem.len()]);
+            self.buffer_pos = rem.len();
+            self.generate_block(self.counter);
+            op(rem, &self.buffer[..rem.len()]);
         }
 
         Ok(())
     }
-}
 
-impl<R: Rounds, MC: MaxCounter> StreamCipherSeek for ChaCha<R, MC> {
-    fn try_current_pos<T: SeekNum>(&self) -> Result<T, OverflowError> {
-        // quick and dirty fix, until ctr-like parallel block processing will be added
-        let (counter, pos) = if self.buffer_pos < BLOCK_SIZE as u8 {
-            (self.counter, self.buffer_pos)
-        } else {
-            (
-                self.counter.checked_add(1).ok_or(OverflowError)?,
-                self.buffer_pos - BLOCK_SIZE as u8,
-            )
-        };
-        T::from_block_byte(counter, pos, BLOCK_SIZE as u8)
-    }
-
-    fn try_seek<T: SeekNum>(&mut self, pos: T) -> Result<(), LoopError> {
-        let res: (u64, u8) = pos.to_block_byte(BUFFER_SIZE as u8)?;
-        let old_counter = self.counter;
-        let old_buffer_pos = self.buffer_pos;
-
-        self.counter = res.0.checked_mul(COUNTER_INCR).ok_or(LoopError)?;
-        self.buffer_pos = res.1;
-
-        if let Err(e) = self.check_data_len(&[0]) {
-            self.counter = old_counter;
-            self.buffer_pos = old_buffer_pos;
-            return Err(e);
+    /// Move the counter past `blocks` blocks of keystream which were
+    /// processed in whole buffers, bypassing the internal buffer.
+    ///
+    /// If these end at the last block of a 64-bit counter, there is no
+    /// counter value following them, so the counter is left at the start of
+    /// the last buffer with no bytes remaining in it instead.
+    #[inline]
+    fn advance(&mut self, blocks: u64) {
+        match self.counter.checked_add(blocks) {
+            Some(counter) => self.counter = counter,
+            None => {
+                self.counter += blocks - self.counter_incr();
+                self.buffer_pos = self.block.buffer_size();
+            }
         }
+    }
 
-        if self.buffer_pos != 0 {
-            self.generate_block(self.counter);
-        }
-        Ok(())
+    /// Value of the 64-bit counter words of the state for block `counter`.
+    ///
+    /// For blocks within the keystream this never overflows: `counter_offset`
+    /// is 0 for 64-bit counters, and only has its upper 32 bits set for
+    /// 32-bit counters, which are below 2<sup>32</sup>. Only blocks past the
+    /// end of the keystream, which fill the unused part of a buffer, wrap.
+    #[inline]
+    fn counter_with_offset(&self, counter: u64) -> u64 {
+        self.counter_offset.wrapping_add(counter)
     }
-}
 
-impl<R: Rounds, MC: MaxCounter> ChaCha<R, MC> {
     /// Check data length
     fn check_data_len(&self, data: &[u8]) -> Result<(), LoopError> {
         let buffer_plus_data = (self.buffer_pos as u64)
@@ -226,12 +357,107 @@ impl<R: Rounds, MC: MaxCounter> ChaCha<R, MC> {
         }
     }
 
+    /// How much to increment the counter by for each buffer we generate.
+    ///
+    /// This is the number of blocks the backend selected at runtime operates
+    /// on in parallel, e.g. 1 for the portable backend and 2 for AVX2.
+    #[inline]
+    fn counter_incr(&self) -> u64 {
+        (self.block.buffer_size() / BLOCK_SIZE) as u64
+    }
+
     /// Generate a block, storing it in the internal buffer
     #[inline]
     fn generate_block(&mut self, counter: u64) {
-        // TODO(tarcieri): double check this should be checked and not wrapping
-        let counter_with_offset = self.counter_offset.checked_add(counter).unwrap();
-        self.block.generate(counter_with_offset, &mut self.buffer);
+        let counter_with_offset = self.counter_with_offset(counter);
+        let buffer_size = self.block.buffer_size();
+        self.block
+            .generate(counter_with_offset, &mut self.buffer[..buffer_size]);
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+impl<R: Rounds + Send + Sync, MC: MaxCounter> ChaCha<R, MC> {
+    /// Apply keystream to `data` using the [`rayon`] thread pool.
+    ///
+    /// The output and the position of the cipher afterwards are the same as
+    /// for [`StreamCipher::apply_keystream`]. Only the whole buffers of
+    /// keystream following the current position are generated in parallel,
+    /// so this pays off for large inputs.
+    ///
+    /// # Panics
+    ///
+    /// If the end of the keystream is reached with the given data length.
+    pub fn par_apply_keystream(&mut self, data: &mut [u8]) {
+        self.try_par_apply_keystream(data).unwrap();
+    }
+
+    /// Apply keystream to `data` using the [`rayon`] thread pool, see
+    /// [`ChaCha::par_apply_keystream`].
+    ///
+    /// Returns [`LoopError`] and leaves the cipher unchanged if the end of
+    /// the keystream is reached with the given data length.
+    pub fn try_par_apply_keystream(&mut self, data: &mut [u8]) -> Result<(), LoopError> {
+        self.check_data_len(data)?;
+        let buffer_size = self.block.buffer_size();
+
+        // use up the leftover keystream first to get to a buffer boundary
+        let head = match self.buffer_pos {
+            0 => 0,
+            pos => core::cmp::min(buffer_size - pos, data.len()),
+        };
+        let (head, data) = data.split_at_mut(head);
+        self.try_apply_keystream(head)?;
+
+        let body_len = data.len() - data.len() % buffer_size;
+        let (body, tail) = data.split_at_mut(body_len);
+        let counter = self.counter;
+        let counter_offset = self.counter_offset;
+        let counter_incr = self.counter_incr();
+        body.par_chunks_mut(PAR_CHUNK_SIZE)
+            .enumerate()
+            .for_each_with(self.block.clone(), |block, (i, chunk)| {
+                let counter = counter.wrapping_add((i * PAR_CHUNK_SIZE / BLOCK_SIZE) as u64);
+                for (j, buf) in chunk.chunks_exact_mut(buffer_size).enumerate() {
+                    let counter = counter.wrapping_add(j as u64 * counter_incr);
+                    block.apply_keystream(counter_offset.wrapping_add(counter), buf);
+                }
+            });
+        self.advance((body_len / BLOCK_SIZE) as u64);
+
+        // the data length was checked above, but an empty slice would be
+        // rejected at the very end of the keystream
+        if !tail.is_empty() {
+            self.try_apply_keystream(tail)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Rounds, MC: MaxCounter> Zeroize for ChaCha<R, MC> {
+    fn zeroize(&mut self) {
+        self.block.zeroize();
+        self.buffer.zeroize();
+        self.buffer_pos.zeroize();
+        self.counter.zeroize();
+        self.counter_offset.zeroize();
+        self.nonce.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Rounds, MC: MaxCounter> Drop for ChaCha<R, MC> {
+    fn drop(&mut self) {
+        // `block` wipes itself when dropped
+        self.buffer.zeroize();
+        self.buffer_pos.zeroize();
+        self.counter.zeroize();
+        self.counter_offset.zeroize();
+        self.nonce.zeroize();
     }
 }
 
@@ -248,3 +474,8 @@ fn xor(buf: &mut [u8], key: &[u8]) {
         *a ^= *b;
     }
 }
+
+#[inline(always)]
+fn copy(buf: &mut [u8], key: &[u8]) {
+    buf.copy_from_slice(key);
+}
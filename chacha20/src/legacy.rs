@@ -12,6 +12,14 @@ use cipher::{
     NewCipher, SeekNum, StreamCipher, StreamCipherSeek,
 };
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+#[cfg(feature = "state")]
+use crate::{StateError, BLOCK_SIZE, KEY_SIZE};
+#[cfg(feature = "state")]
+use cipher_state::{SeekableState, POSITION_LEN};
+
 /// Size of the nonce for the legacy ChaCha20 stream cipher
 #[cfg_attr(docsrs, doc(cfg(feature = "legacy")))]
 pub type LegacyNonce = cipher::Nonce<ChaCha20Legacy>;
@@ -51,3 +59,67 @@ impl StreamCipherSeek for ChaCha20Legacy {
         self.0.try_seek(pos)
     }
 }
+
+#[cfg(feature = "state")]
+#[cfg_attr(docsrs, doc(cfg(feature = "state")))]
+impl ChaCha20Legacy {
+    /// Length of the state serialized by [`ChaCha20Legacy::to_bytes`], with
+    /// or without the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(KEY_SIZE, 8 + POSITION_LEN, with_key)
+    }
+
+    /// Serialize the nonce and keystream position into `buf`, returning the
+    /// written prefix of it.
+    ///
+    /// The cipher does not retain its key, so it's included only if passed
+    /// as `key`, which must be the key the cipher was initialized with.
+    /// Restoring a state to encrypt different data than was encrypted from
+    /// that position reuses keystream, and the 64-bit nonce is too short to
+    /// pick a fresh random one instead.
+    ///
+    /// The format is the same as for [`ChaCha::to_bytes`], with the 64-bit
+    /// nonce.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&Key>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let pos = self.try_current_pos().map_err(|_| StateError)?;
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write_seekable(buf, key, &self.0.nonce()[4..], pos, BLOCK_SIZE as u8)
+    }
+
+    /// Restore a cipher from a state serialized by
+    /// [`ChaCha20Legacy::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    pub fn from_bytes(bytes: &[u8], key: Option<&Key>) -> Result<Self, StateError> {
+        let state = SeekableState::read(bytes, KEY_SIZE, 8, BLOCK_SIZE as u8)?;
+        let key = Key::from_slice(state.key(key.map(|k| k.as_slice()))?);
+        let mut cipher = Self::new(key, LegacyNonce::from_slice(state.nonce()));
+        cipher.try_seek(state.pos()).map_err(|_| StateError)?;
+        Ok(cipher)
+    }
+}
+
+impl ChaCha20Legacy {
+    /// Write keystream to `out`, overwriting its contents, see
+    /// [`ChaCha::write_keystream`].
+    pub fn write_keystream(&mut self, out: &mut [u8]) {
+        self.0.write_keystream(out);
+    }
+
+    /// Write keystream to `out`, see [`ChaCha::try_write_keystream`].
+    pub fn try_write_keystream(&mut self, out: &mut [u8]) -> Result<(), LoopError> {
+        self.0.try_write_keystream(out)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl Zeroize for ChaCha20Legacy {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
@@ -16,6 +16,18 @@
 //! - [`XChaCha20`]: (gated under the `xchacha20` feature) 192-bit extended nonce variant
 //! - [`XChaCha8`] / [`XChaCha12`]: reduced round variants of XChaCha20
 //!
+//! Additionally, the `aead` feature provides the [`ChaCha20Poly1305`] and
+//! [`XChaCha20Poly1305`] authenticated encryption constructions, the
+//! `rayon` feature provides `par_apply_keystream` methods which generate the
+//! keystream for large inputs on multiple threads, and the `state` feature
+//! provides `to_bytes` and `from_bytes` methods which save and restore the
+//! nonce and keystream position of a cipher.
+//!
+//! The `io` feature re-exports the `StreamReader` and `StreamWriter` adapters
+//! of the [`cipher-io`](https://docs.rs/cipher-io) crate, which apply the
+//! keystream to the data read from a `std::io::Read` or written to a
+//! `std::io::Write` implementation.
+//!
 //! # ⚠️ Security Warning: [Hazmat!]
 //!
 //! This crate does not ensure ciphertexts are authentic, which can lead to
@@ -75,6 +87,12 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![warn(missing_docs, rust_2018_idioms, trivial_casts, unused_qualifications)]
 
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "aead")]
+#[cfg_attr(docsrs, doc(cfg(feature = "aead")))]
+pub mod aead;
 mod backend;
 #[cfg(feature = "cipher")]
 mod chacha;
@@ -84,15 +102,30 @@ mod max_blocks;
 #[cfg(feature = "rng")]
 mod rng;
 mod rounds;
+#[cfg(feature = "cipher")]
 #[cfg(feature = "xchacha")]
 mod xchacha;
 
 #[cfg(feature = "cipher")]
 pub use cipher;
 
+#[cfg(feature = "aead")]
+pub use crate::aead::ChaCha20Poly1305;
+
+#[cfg(all(feature = "aead", feature = "xchacha"))]
+pub use crate::aead::XChaCha20Poly1305;
+
 #[cfg(feature = "cipher")]
 pub use crate::chacha::{ChaCha, ChaCha12, ChaCha20, ChaCha8, Key, Nonce};
 
+#[cfg(feature = "io")]
+#[cfg_attr(docsrs, doc(cfg(feature = "io")))]
+pub use cipher_io::{StreamReader, StreamWriter};
+
+#[cfg(feature = "state")]
+#[cfg_attr(docsrs, doc(cfg(feature = "state")))]
+pub use cipher_state::StateError;
+
 #[cfg(feature = "expose-core")]
 pub use crate::{
     backend::Core,
@@ -8,10 +8,47 @@ use rand_core::{
 use crate::{
     backend::{Core, BUFFER_SIZE},
     rounds::{R12, R20, R8},
-    KEY_SIZE,
+    BLOCK_SIZE, KEY_SIZE,
 };
 use core::convert::TryInto;
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Number of 32-bit words per ChaCha block (fixed by algorithm definition).
+const BLOCK_WORDS: u8 = (BLOCK_SIZE / 4) as u8;
+
+/// Number of ChaCha blocks produced by each call to `BlockRngCore::generate`.
+///
+/// This depends on the backend in use (e.g. the AVX2 backend computes two
+/// blocks in parallel).
+const BUF_BLOCKS: u8 = (BUFFER_SIZE / BLOCK_SIZE) as u8;
+
+/// Output of `BlockRngCore::generate`: one word for each 4 bytes of buffer.
+///
+/// This wraps an array since `Default` is only implemented for arrays of up
+/// to 32 elements, which is fewer than the wider backends produce.
+#[derive(Clone, Copy)]
+pub struct BlockRngResults([u32; BUFFER_SIZE / 4]);
+
+impl Default for BlockRngResults {
+    fn default() -> Self {
+        Self([0u32; BUFFER_SIZE / 4])
+    }
+}
+
+impl AsRef<[u32]> for BlockRngResults {
+    fn as_ref(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl AsMut<[u32]> for BlockRngResults {
+    fn as_mut(&mut self) -> &mut [u32] {
+        &mut self.0
+    }
+}
+
 macro_rules! impl_chacha_rng {
     ($name:ident, $core:ident, $rounds:ident, $doc:expr) => {
         #[doc = $doc]
@@ -52,46 +89,150 @@ macro_rules! impl_chacha_rng {
 
         impl CryptoRng for $name {}
 
+        impl $name {
+            /// Get the offset from the start of the stream, in 32-bit words.
+            ///
+            /// Since the generated blocks are 16 words (2<sup>4</sup>) long and the
+            /// counter is 64-bits, the offset is a 68-bit number. Sub-word offsets are
+            /// not supported, hence the result can simply be multiplied by 4 to get a
+            /// byte-offset.
+            #[inline]
+            pub fn get_word_pos(&self) -> u128 {
+                // `counter` points at the block following the buffered output
+                let buf_start_block = self.0.core.counter.wrapping_sub(BUF_BLOCKS.into());
+                let buf_offset_words = self.0.index() as u64;
+                let pos_block =
+                    buf_start_block.wrapping_add(buf_offset_words / u64::from(BLOCK_WORDS));
+                let block_offset_words = buf_offset_words % u64::from(BLOCK_WORDS);
+                u128::from(pos_block) * u128::from(BLOCK_WORDS) + u128::from(block_offset_words)
+            }
+
+            /// Set the offset from the start of the stream, in 32-bit words.
+            ///
+            /// As with `get_word_pos`, we use a 68-bit number. Since the generator
+            /// simply cycles at the end of its period (1 ZiB), we ignore the upper
+            /// 60 bits.
+            #[inline]
+            pub fn set_word_pos(&mut self, word_offset: u128) {
+                let block = (word_offset / u128::from(BLOCK_WORDS)) as u64;
+                self.0.core.counter = block;
+                self.0
+                    .generate_and_set((word_offset % u128::from(BLOCK_WORDS)) as usize);
+            }
+
+            /// Set the stream number.
+            ///
+            /// This is initialized to zero; 2<sup>64</sup> unique streams of output
+            /// are available per seed/key. The stream number occupies the 64-bit
+            /// nonce of the "djb" ChaCha variant.
+            ///
+            /// Note that in order to reproduce ChaCha output with a specific 64-bit
+            /// nonce, one can convert that nonce to a `u64` in little-endian fashion
+            /// and pass to this function. The word position is retained, so output
+            /// continues from the same offset within the new stream.
+            #[inline]
+            pub fn set_stream(&mut self, stream: u64) {
+                self.0.core.set_stream(stream);
+
+                if self.0.index() != BUFFER_SIZE / 4 {
+                    // regenerate buffer with the new stream, preserving position
+                    let wp = self.get_word_pos();
+                    self.set_word_pos(wp);
+                }
+            }
+
+            /// Get the stream number.
+            #[inline]
+            pub fn get_stream(&self) -> u64 {
+                self.0.core.stream
+            }
+
+            /// Get the seed.
+            #[inline]
+            pub fn get_seed(&self) -> [u8; KEY_SIZE] {
+                self.0.core.seed
+            }
+        }
+
         #[doc = "Core random number generator, for use with [`rand_core::block::BlockRng`]"]
         #[cfg_attr(docsrs, doc(cfg(feature = "rng")))]
         pub struct $core {
             block: Core<$rounds>,
+            seed: [u8; KEY_SIZE],
+            stream: u64,
             counter: u64,
         }
 
+        impl $core {
+            /// Re-key the core function to output the given stream.
+            fn set_stream(&mut self, stream: u64) {
+                self.block = Core::new(&self.seed, stream.to_le_bytes());
+                self.stream = stream;
+            }
+        }
+
         impl SeedableRng for $core {
             type Seed = [u8; KEY_SIZE];
 
             #[inline]
             fn from_seed(seed: Self::Seed) -> Self {
                 let block = Core::new(&seed, Default::default());
-                Self { block, counter: 0 }
+                Self {
+                    block,
+                    seed,
+                    stream: 0,
+                    counter: 0,
+                }
             }
         }
 
         impl BlockRngCore for $core {
             type Item = u32;
-            type Results = [u32; BUFFER_SIZE / 4];
+            type Results = BlockRngResults;
 
+            /// Generate the next `BUF_BLOCKS` blocks of output.
+            ///
+            /// The block counter is 64-bits wide, so each stream provides
+            /// 2<sup>64</sup> blocks (1 ZiB) of output, after which the counter
+            /// wraps around and the stream repeats from the beginning.
             fn generate(&mut self, results: &mut Self::Results) {
-                // is this necessary?
-                assert!(
-                    self.counter < u32::MAX as u64,
-                    "maximum number of allowed ChaCha blocks exceeded"
-                );
-
                 let mut buffer = [0u8; BUFFER_SIZE];
                 self.block.generate(self.counter, &mut buffer);
 
-                for (n, chunk) in results.iter_mut().zip(buffer.chunks_exact(4)) {
+                for (n, chunk) in results.0.iter_mut().zip(buffer.chunks_exact(4)) {
                     *n = u32::from_le_bytes(chunk.try_into().unwrap());
                 }
 
-                self.counter += 1;
+                #[cfg(feature = "zeroize")]
+                buffer.zeroize();
+
+                self.counter = self.counter.wrapping_add(BUF_BLOCKS.into());
             }
         }
 
         impl CryptoRng for $core {}
+
+        #[cfg(feature = "zeroize")]
+        #[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+        impl Zeroize for $core {
+            fn zeroize(&mut self) {
+                self.block.zeroize();
+                self.seed.zeroize();
+                self.stream.zeroize();
+                self.counter.zeroize();
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        #[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+        impl Drop for $core {
+            fn drop(&mut self) {
+                // `block` wipes itself when dropped
+                self.seed.zeroize();
+                self.stream.zeroize();
+                self.counter.zeroize();
+            }
+        }
     };
 }
 
@@ -118,7 +259,7 @@ impl_chacha_rng!(
 
 #[cfg(test)]
 mod tests {
-    use super::ChaCha20Rng;
+    use super::{ChaCha20Rng, BLOCK_WORDS, BUF_BLOCKS};
     use crate::KEY_SIZE;
     use rand_core::{RngCore, SeedableRng};
 
@@ -144,4 +285,154 @@ mod tests {
             [167, 163, 252, 19, 79, 20, 152, 128, 232, 187, 43, 93, 35]
         );
     }
+
+    #[test]
+    fn test_chacha_true_values_c() {
+        // Test vector 4 from
+        // https://tools.ietf.org/html/draft-nir-cfrg-chacha20-poly1305-04
+        let mut seed = [0u8; KEY_SIZE];
+        seed[1] = 0xff;
+        let expected = [
+            0xfb4dd572, 0x4bc42ef1, 0xdf922636, 0x327f1394, 0xa78dea8f, 0x5e269039, 0xa1bebbc1,
+            0xcaf09aae, 0xa25ab213, 0x48a6b46c, 0x1b9d9bcb, 0x092c5be6, 0x546ca624, 0x1bec45d5,
+            0x87f47473, 0x96f0992e,
+        ];
+        let expected_end = 3 * 16;
+        let mut results = [0u32; 16];
+
+        // Test block 2 by skipping block 0 and 1
+        let mut rng1 = ChaCha20Rng::from_seed(seed);
+        for _ in 0..32 {
+            rng1.next_u32();
+        }
+        for i in results.iter_mut() {
+            *i = rng1.next_u32();
+        }
+        assert_eq!(results, expected);
+        assert_eq!(rng1.get_word_pos(), expected_end);
+
+        // Test block 2 by using `set_word_pos`
+        let mut rng2 = ChaCha20Rng::from_seed(seed);
+        rng2.set_word_pos(2 * 16);
+        for i in results.iter_mut() {
+            *i = rng2.next_u32();
+        }
+        assert_eq!(results, expected);
+        assert_eq!(rng2.get_word_pos(), expected_end);
+
+        // Test skipping behaviour with other types
+        let mut buf = [0u8; 32];
+        rng2.fill_bytes(&mut buf[..]);
+        assert_eq!(rng2.get_word_pos(), expected_end + 8);
+        rng2.fill_bytes(&mut buf[0..25]);
+        assert_eq!(rng2.get_word_pos(), expected_end + 15);
+        rng2.next_u64();
+        assert_eq!(rng2.get_word_pos(), expected_end + 17);
+        rng2.next_u32();
+        rng2.next_u64();
+        assert_eq!(rng2.get_word_pos(), expected_end + 20);
+        rng2.fill_bytes(&mut buf[0..1]);
+        assert_eq!(rng2.get_word_pos(), expected_end + 21);
+    }
+
+    #[test]
+    fn test_chacha_nonce() {
+        // Test vector 5 from
+        // https://tools.ietf.org/html/draft-nir-cfrg-chacha20-poly1305-04
+        let mut rng = ChaCha20Rng::from_seed([0u8; KEY_SIZE]);
+
+        // 96-bit nonce in LE order is: 0,0,0,0, 0,0,0,0, 0,0,0,2
+        rng.set_stream(2u64 << (24 + 32));
+        assert_eq!(rng.get_stream(), 2u64 << (24 + 32));
+
+        let mut results = [0u32; 16];
+        for i in results.iter_mut() {
+            *i = rng.next_u32();
+        }
+        let expected = [
+            0x374dc6c2, 0x3736d58c, 0xb904e24a, 0xcd3f93ef, 0x88228b1a, 0x96a4dfb3, 0x5b76ab72,
+            0xc727ee54, 0x0e0e978a, 0xf3145c95, 0x1b748ea8, 0xf786c297, 0x99c28f5f, 0x628314e8,
+            0x398a19fa, 0x6ded1b53,
+        ];
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn test_chacha_set_stream_mid_block() {
+        let mut rng = ChaCha20Rng::from_seed(KEY);
+        let mut other = ChaCha20Rng::from_seed(KEY);
+        other.set_stream(51);
+
+        for _ in 0..7 {
+            rng.next_u32();
+        }
+
+        // switch part way through a block, retaining the word position
+        rng.set_stream(51);
+        assert_eq!(rng.get_word_pos(), 7);
+        other.set_word_pos(7);
+
+        for _ in 7..64 {
+            assert_eq!(rng.next_u32(), other.next_u32());
+        }
+    }
+
+    #[test]
+    fn test_chacha_get_seed() {
+        let rng = ChaCha20Rng::from_seed(KEY);
+        assert_eq!(rng.get_seed(), KEY);
+    }
+
+    #[test]
+    fn test_chacha_word_pos_wrap_exact() {
+        let mut rng = ChaCha20Rng::from_seed(Default::default());
+        // refilling the buffer in set_word_pos will wrap the block counter to 0
+        let last_block = (1 << 68) - u128::from(BUF_BLOCKS * BLOCK_WORDS);
+        rng.set_word_pos(last_block);
+        assert_eq!(rng.get_word_pos(), last_block);
+    }
+
+    #[test]
+    fn test_chacha_word_pos_wrap_excess() {
+        let mut rng = ChaCha20Rng::from_seed(Default::default());
+        // refilling the buffer in set_word_pos will wrap the block counter past 0
+        let last_block = (1 << 68) - u128::from(BLOCK_WORDS);
+        rng.set_word_pos(last_block);
+        assert_eq!(rng.get_word_pos(), last_block);
+
+        // the counter wraps around to the start of the stream
+        for _ in 0..BLOCK_WORDS {
+            rng.next_u32();
+        }
+        assert_eq!(rng.get_word_pos(), 0);
+        let mut start = ChaCha20Rng::from_seed(Default::default());
+        assert_eq!(rng.next_u64(), start.next_u64());
+    }
+
+    #[test]
+    fn test_chacha_word_pos_zero() {
+        let mut rng = ChaCha20Rng::from_seed(Default::default());
+        assert_eq!(rng.get_word_pos(), 0);
+        rng.set_word_pos(0);
+        assert_eq!(rng.get_word_pos(), 0);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_chacha_core_zeroize() {
+        use super::{BlockRngResults, ChaCha20RngCore};
+        use rand_core::block::BlockRngCore;
+        use zeroize::Zeroize;
+
+        let mut core = ChaCha20RngCore::from_seed(KEY);
+        let mut results = BlockRngResults::default();
+        core.generate(&mut results);
+        assert!(results.0.iter().any(|&w| w != 0));
+
+        // an all-zero ChaCha state produces an all-zero first block
+        core.zeroize();
+        assert_eq!(core.seed, [0u8; KEY_SIZE]);
+        core.generate(&mut results);
+        assert!(results.0[..BLOCK_WORDS as usize].iter().all(|&w| w == 0));
+    }
 }
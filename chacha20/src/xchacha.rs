@@ -15,6 +15,14 @@ use cipher::{
 };
 use core::convert::TryInto;
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+#[cfg(feature = "state")]
+use crate::{StateError, BLOCK_SIZE, KEY_SIZE};
+#[cfg(feature = "state")]
+use cipher_state::{SeekableState, POSITION_LEN};
+
 /// EXtended ChaCha20 nonce (192-bits/24-bytes)
 #[cfg_attr(docsrs, doc(cfg(feature = "xchacha")))]
 pub type XNonce = cipher::Nonce<XChaCha20>;
@@ -54,7 +62,10 @@ pub type XChaCha12 = XChaCha<R12>;
 #[cfg_attr(docsrs, doc(cfg(feature = "xchacha")))]
 pub type XChaCha8 = XChaCha<R8>;
 
-pub struct XChaCha<R: Rounds>(ChaCha<R, C64>);
+pub struct XChaCha<R: Rounds>(
+    ChaCha<R, C64>,
+    #[cfg_attr(not(feature = "state"), allow(dead_code))] XNonce,
+);
 
 impl<R: Rounds> NewCipher for XChaCha<R> {
     /// Key size in bytes
@@ -65,11 +76,15 @@ impl<R: Rounds> NewCipher for XChaCha<R> {
 
     #[allow(unused_mut, clippy::let_and_return)]
     fn new(key: &Key, nonce: &XNonce) -> Self {
-        // TODO(tarcieri): zeroize subkey
-        let subkey = hchacha::<R>(key, nonce[..16].as_ref().into());
+        let mut subkey = hchacha::<R>(key, nonce[..16].as_ref().into());
         let mut padded_iv = GenericArray::default();
         padded_iv[4..].copy_from_slice(&nonce[16..]);
-        XChaCha(ChaCha::new(&subkey, &padded_iv))
+        let cipher = XChaCha(ChaCha::new(&subkey, &padded_iv), *nonce);
+
+        #[cfg(feature = "zeroize")]
+        subkey.as_mut_slice().zeroize();
+
+        cipher
     }
 }
 
@@ -89,6 +104,85 @@ impl<R: Rounds> StreamCipherSeek for XChaCha<R> {
     }
 }
 
+#[cfg(feature = "state")]
+#[cfg_attr(docsrs, doc(cfg(feature = "state")))]
+impl<R: Rounds> XChaCha<R> {
+    /// Length of the state serialized by [`XChaCha::to_bytes`], with or
+    /// without the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(KEY_SIZE, 24 + POSITION_LEN, with_key)
+    }
+
+    /// Serialize the nonce and keystream position into `buf`, returning the
+    /// written prefix of it.
+    ///
+    /// The cipher does not retain its key, so it's included only if passed
+    /// as `key`, which must be the key the cipher was initialized with (not
+    /// the HChaCha subkey). The state stores the extended nonce rather than
+    /// the subkey, so without the key it holds nothing secret.
+    ///
+    /// The format is the same as for [`ChaCha::to_bytes`], with the extended
+    /// nonce.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&Key>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let pos = self.try_current_pos().map_err(|_| StateError)?;
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write_seekable(buf, key, &self.1, pos, BLOCK_SIZE as u8)
+    }
+
+    /// Restore a cipher from a state serialized by [`XChaCha::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    pub fn from_bytes(bytes: &[u8], key: Option<&Key>) -> Result<Self, StateError> {
+        let state = SeekableState::read(bytes, KEY_SIZE, 24, BLOCK_SIZE as u8)?;
+        let key = Key::from_slice(state.key(key.map(|k| k.as_slice()))?);
+        let mut cipher = Self::new(key, XNonce::from_slice(state.nonce()));
+        cipher.try_seek(state.pos()).map_err(|_| StateError)?;
+        Ok(cipher)
+    }
+}
+
+impl<R: Rounds> XChaCha<R> {
+    /// Write keystream to `out`, overwriting its contents, see
+    /// [`ChaCha::write_keystream`].
+    pub fn write_keystream(&mut self, out: &mut [u8]) {
+        self.0.write_keystream(out);
+    }
+
+    /// Write keystream to `out`, see [`ChaCha::try_write_keystream`].
+    pub fn try_write_keystream(&mut self, out: &mut [u8]) -> Result<(), LoopError> {
+        self.0.try_write_keystream(out)
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+impl<R: Rounds + Send + Sync> XChaCha<R> {
+    /// Apply keystream to `data` using the [`rayon`] thread pool, see
+    /// [`ChaCha::par_apply_keystream`].
+    pub fn par_apply_keystream(&mut self, data: &mut [u8]) {
+        self.0.par_apply_keystream(data);
+    }
+
+    /// Apply keystream to `data` using the [`rayon`] thread pool, see
+    /// [`ChaCha::try_par_apply_keystream`].
+    pub fn try_par_apply_keystream(&mut self, data: &mut [u8]) -> Result<(), LoopError> {
+        self.0.try_par_apply_keystream(data)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<R: Rounds> Zeroize for XChaCha<R> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+        self.1.zeroize();
+    }
+}
+
 /// The HChaCha function: adapts the ChaCha core function in the same
 /// manner that HSalsa adapts the Salsa function.
 ///
@@ -141,6 +235,9 @@ pub fn hchacha<R: Rounds>(key: &Key, input: &GenericArray<u8, U16>) -> GenericAr
         chunk.copy_from_slice(&state[i + 12].to_le_bytes());
     }
 
+    #[cfg(feature = "zeroize")]
+    state.zeroize();
+
     output
 }
 
@@ -174,7 +271,7 @@ mod hchacha20_tests {
     fn test_vector() {
         let actual = hchacha::<R20>(
             GenericArray::from_slice(&KEY),
-            &GenericArray::from_slice(&INPUT),
+            GenericArray::from_slice(&INPUT),
         );
         assert_eq!(actual.as_slice(), &OUTPUT);
     }
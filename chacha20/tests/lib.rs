@@ -114,6 +114,32 @@ mod overflow {
         }
     }
 
+    /// Whole buffers of keystream processed in a single call can end at the
+    /// last block, whatever the buffer size of the backend in use
+    #[test]
+    fn whole_buffers_up_to_the_limit() {
+        let key = Default::default();
+        let nonce = [0xff; 12].into();
+        let mut expected = [0u8; 4096];
+        let mut cipher = chacha20::ChaCha20::new(&key, &nonce);
+        cipher.seek(OFFSET_256GB - 4096);
+        for chunk in expected.chunks_mut(100) {
+            cipher.apply_keystream(chunk);
+        }
+
+        let mut cipher = chacha20::ChaCha20::new(&key, &nonce);
+        cipher.seek(OFFSET_256GB - 4096);
+        let mut data = [0u8; 4096];
+        cipher
+            .try_apply_keystream(&mut data)
+            .expect("Couldn't encrypt up to the last byte of 256GB");
+        assert_eq!(&data[..], &expected[..]);
+        assert_eq!(cipher.try_current_pos::<u64>().unwrap(), OFFSET_256GB);
+        cipher
+            .try_apply_keystream(&mut [0u8; 1])
+            .expect_err("Could encrypt past the last byte of 256GB");
+    }
+
     #[cfg(feature = "xchacha")]
     #[test]
     fn xchacha_256gb() {
@@ -195,6 +221,23 @@ mod overflow {
             .expect_err("Could encrypt past 1 zebibyte");
     }
 
+    #[cfg(feature = "legacy")]
+    #[test]
+    fn legacy_whole_buffers_up_to_the_limit() {
+        let mut cipher = chacha20::ChaCha20Legacy::new(&Default::default(), &Default::default());
+        cipher
+            .try_seek(OFFSET_1ZB - 4096)
+            .expect("Couldn't seek to nearly 1 zebibyte");
+        let mut data = [0u8; 4096];
+        cipher
+            .try_apply_keystream(&mut data)
+            .expect("Couldn't encrypt up to the last byte of 1 zebibyte");
+        assert!(cipher.try_current_pos::<u64>().is_err());
+        cipher
+            .try_apply_keystream(&mut [0u8; 1])
+            .expect_err("Could encrypt past 1 zebibyte");
+    }
+
     #[cfg(feature = "legacy")]
     #[test]
     fn legacy_has_a_big_counter() {
@@ -284,7 +327,7 @@ mod xchacha20 {
     #[test]
     fn xchacha20_encryption() {
         let mut cipher = XChaCha20::new(&Key::from(KEY), &XNonce::from(IV));
-        let mut buf = PLAINTEXT.clone();
+        let mut buf = PLAINTEXT;
 
         // The test vectors omit the first 64-bytes of the keystream
         let mut prefix = [0u8; 64];
@@ -295,6 +338,442 @@ mod xchacha20 {
     }
 }
 
+// ChaCha20Poly1305 and XChaCha20Poly1305 AEADs
+#[cfg(feature = "aead")]
+#[rustfmt::skip]
+mod aead {
+    use chacha20::{aead::Tag, ChaCha20Poly1305, Key, Nonce};
+    use hex_literal::hex;
+
+    //
+    // Test vectors common to RFC 8439 Section 2.8.2 and
+    // <https://tools.ietf.org/html/draft-arciszewski-xchacha-03#appendix-A.3.1>
+    //
+
+    const KEY: [u8; 32] = hex!("
+        808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f
+    ");
+
+    const AAD: [u8; 12] = hex!("50515253c0c1c2c3c4c5c6c7");
+
+    const PLAINTEXT: &[u8] = b"Ladies and Gentlemen of the class of '99: \
+        If I could offer you only one tip for the future, sunscreen would be it.";
+
+    const NONCE: [u8; 12] = hex!("070000004041424344454647");
+
+    const CIPHERTEXT: [u8; 114] = hex!("
+        d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6
+        3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36
+        92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc
+        3ff4def08e4b7a9de576d26586cec64b6116
+    ");
+
+    const TAG: [u8; 16] = hex!("1ae10b594f09e26a7e902ecbd0600691");
+
+    //
+    // Test vectors from RFC 8439 Appendix A.5
+    //
+
+    const A5_KEY: [u8; 32] = hex!("
+        1c9240a5eb55d38af333888604f6b5f0473917c1402b80099dca5cbc207075c0
+    ");
+
+    const A5_NONCE: [u8; 12] = hex!("000000000102030405060708");
+
+    const A5_AAD: [u8; 12] = hex!("f33388860000000000004e91");
+
+    const A5_PLAINTEXT: [u8; 265] = hex!("
+        496e7465726e65742d4472616674732061726520647261667420646f63756d65
+        6e74732076616c696420666f722061206d6178696d756d206f6620736978206d
+        6f6e74687320616e64206d617920626520757064617465642c207265706c6163
+        65642c206f72206f62736f6c65746564206279206f7468657220646f63756d65
+        6e747320617420616e792074696d652e20497420697320696e617070726f7072
+        6961746520746f2075736520496e7465726e65742d4472616674732061732072
+        65666572656e6365206d6174657269616c206f7220746f206369746520746865
+        6d206f74686572207468616e206173202fe2809c776f726b20696e2070726f67
+        726573732e2fe2809d
+    ");
+
+    const A5_CIPHERTEXT: [u8; 265] = hex!("
+        64a0861575861af460f062c79be643bd5e805cfd345cf389f108670ac76c8cb2
+        4c6cfc18755d43eea09ee94e382d26b0bdb7b73c321b0100d4f03b7f355894cf
+        332f830e710b97ce98c8a84abd0b948114ad176e008d33bd60f982b1ff37c855
+        9797a06ef4f0ef61c186324e2b3506383606907b6a7c02b0f9f6157b53c867e4
+        b9166c767b804d46a59b5216cde7a4e99040c5a40433225ee282a1b0a06c523e
+        af4534d7f83fa1155b0047718cbc546a0d072b04b3564eea1b422273f548271a
+        0bb2316053fa76991955ebd63159434ecebb4e466dae5a1073a6727627097a10
+        49e617d91d361094fa68f0ff77987130305beaba2eda04df997b714d6c6f2c29
+        a6ad5cb4022b02709b
+    ");
+
+    const A5_TAG: [u8; 16] = hex!("eead9d67890cbb22392336fea1851f38");
+
+    #[test]
+    fn chacha20poly1305_encrypt() {
+        let aead = ChaCha20Poly1305::new(&Key::from(KEY));
+        let mut buf = CIPHERTEXT;
+        buf.copy_from_slice(PLAINTEXT);
+
+        let tag = aead
+            .encrypt_in_place_detached(&Nonce::from(NONCE), &AAD, &mut buf)
+            .unwrap();
+        assert_eq!(&buf[..], &CIPHERTEXT[..]);
+        assert_eq!(tag, Tag::from(TAG));
+    }
+
+    #[test]
+    fn chacha20poly1305_decrypt() {
+        let aead = ChaCha20Poly1305::new(&Key::from(KEY));
+        let mut buf = CIPHERTEXT;
+
+        aead.decrypt_in_place_detached(&Nonce::from(NONCE), &AAD, &mut buf, &Tag::from(TAG))
+            .unwrap();
+        assert_eq!(&buf[..], PLAINTEXT);
+    }
+
+    #[test]
+    fn chacha20poly1305_a5_encrypt() {
+        let aead = ChaCha20Poly1305::new(&Key::from(A5_KEY));
+        let mut buf = A5_PLAINTEXT;
+
+        let tag = aead
+            .encrypt_in_place_detached(&Nonce::from(A5_NONCE), &A5_AAD, &mut buf)
+            .unwrap();
+        assert_eq!(&buf[..], &A5_CIPHERTEXT[..]);
+        assert_eq!(tag, Tag::from(A5_TAG));
+    }
+
+    #[test]
+    fn chacha20poly1305_a5_decrypt() {
+        let aead = ChaCha20Poly1305::new(&Key::from(A5_KEY));
+        let mut buf = A5_CIPHERTEXT;
+
+        aead.decrypt_in_place_detached(
+            &Nonce::from(A5_NONCE),
+            &A5_AAD,
+            &mut buf,
+            &Tag::from(A5_TAG),
+        )
+        .unwrap();
+        assert_eq!(&buf[..], &A5_PLAINTEXT[..]);
+    }
+
+    #[test]
+    fn chacha20poly1305_decrypt_modified() {
+        let aead = ChaCha20Poly1305::new(&Key::from(A5_KEY));
+        let nonce = Nonce::from(A5_NONCE);
+
+        // modified ciphertext
+        let mut buf = A5_CIPHERTEXT;
+        buf[0] ^= 1;
+        assert!(aead
+            .decrypt_in_place_detached(&nonce, &A5_AAD, &mut buf, &Tag::from(A5_TAG))
+            .is_err());
+
+        // the buffer must be left untouched on failure
+        let mut expected = A5_CIPHERTEXT;
+        expected[0] ^= 1;
+        assert_eq!(&buf[..], &expected[..]);
+
+        // modified associated data
+        let mut buf = A5_CIPHERTEXT;
+        let mut aad = A5_AAD;
+        aad[11] ^= 0x80;
+        assert!(aead
+            .decrypt_in_place_detached(&nonce, &aad, &mut buf, &Tag::from(A5_TAG))
+            .is_err());
+
+        // modified tag
+        let mut tag = A5_TAG;
+        tag[15] ^= 1;
+        assert!(aead
+            .decrypt_in_place_detached(&nonce, &A5_AAD, &mut buf, &Tag::from(tag))
+            .is_err());
+        assert_eq!(&buf[..], &A5_CIPHERTEXT[..]);
+    }
+
+    #[cfg(feature = "xchacha")]
+    mod xchacha20poly1305 {
+        use super::{AAD, KEY, PLAINTEXT};
+        use chacha20::{aead::Tag, Key, XChaCha20Poly1305, XNonce};
+        use hex_literal::hex;
+
+        const NONCE: [u8; 24] = hex!("404142434445464748494a4b4c4d4e4f5051525354555657");
+
+        const CIPHERTEXT: [u8; 114] = hex!("
+            bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb
+            731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452
+            2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9
+            21f9664c97637da9768812f615c68b13b52e
+        ");
+
+        const TAG: [u8; 16] = hex!("c0875924c1c7987947deafd8780acf49");
+
+        #[test]
+        fn xchacha20poly1305_encrypt() {
+            let aead = XChaCha20Poly1305::new(&Key::from(KEY));
+            let mut buf = CIPHERTEXT;
+            buf.copy_from_slice(PLAINTEXT);
+
+            let tag = aead
+                .encrypt_in_place_detached(&XNonce::from(NONCE), &AAD, &mut buf)
+                .unwrap();
+            assert_eq!(&buf[..], &CIPHERTEXT[..]);
+            assert_eq!(tag, Tag::from(TAG));
+        }
+
+        #[test]
+        fn xchacha20poly1305_decrypt() {
+            let aead = XChaCha20Poly1305::new(&Key::from(KEY));
+            let mut buf = CIPHERTEXT;
+
+            aead.decrypt_in_place_detached(&XNonce::from(NONCE), &AAD, &mut buf, &Tag::from(TAG))
+                .unwrap();
+            assert_eq!(&buf[..], PLAINTEXT);
+
+            let mut tag = TAG;
+            tag[0] ^= 1;
+            let mut buf = CIPHERTEXT;
+            assert!(aead
+                .decrypt_in_place_detached(&XNonce::from(NONCE), &AAD, &mut buf, &Tag::from(tag))
+                .is_err());
+            assert_eq!(&buf[..], &CIPHERTEXT[..]);
+        }
+    }
+}
+
+/// Seeking must be consistent with the keystream regardless of how many
+/// blocks the backend selected at runtime generates per call
+mod seek {
+    use chacha20::{ChaCha20, Key, Nonce};
+    use cipher::{NewCipher, StreamCipher, StreamCipherSeek};
+
+    const LEN: usize = 1100;
+
+    fn cipher() -> ChaCha20 {
+        ChaCha20::new(&Key::from([0x42; 32]), &Nonce::from([0x24; 12]))
+    }
+
+    #[test]
+    fn seek_matches_keystream() {
+        let mut keystream = [0u8; LEN];
+        cipher().apply_keystream(&mut keystream);
+
+        for pos in 0..LEN {
+            let mut cipher = cipher();
+            cipher.seek(pos as u64);
+            assert_eq!(cipher.current_pos::<u64>(), pos as u64);
+
+            // apply in uneven pieces to exercise the internal buffer
+            let mut buf = [0u8; LEN];
+            let (l, r) = buf[pos..].split_at_mut((LEN - pos) / 3);
+            cipher.apply_keystream(l);
+            assert_eq!(cipher.current_pos::<u64>(), (pos + l.len()) as u64);
+            cipher.apply_keystream(r);
+            assert_eq!(cipher.current_pos::<u64>(), LEN as u64);
+            assert_eq!(&buf[pos..], &keystream[pos..]);
+        }
+    }
+}
+
+/// Serialized states must resume the keystream where it was saved
+#[cfg(feature = "state")]
+mod state {
+    use chacha20::{ChaCha12, ChaCha20};
+
+    cipher_state::stream_state_test!(chacha20, ChaCha20);
+    cipher_state::stream_state_test!(chacha12, ChaCha12);
+
+    #[cfg(feature = "xchacha")]
+    cipher_state::stream_state_test!(xchacha20, chacha20::XChaCha20);
+
+    #[cfg(feature = "legacy")]
+    cipher_state::stream_state_test!(legacy, chacha20::ChaCha20Legacy);
+}
+
+/// Parallel keystream application must match the serial one, including the
+/// position of the cipher afterwards
+mod keystream {
+    use chacha20::{ChaCha20, Key, Nonce};
+    use cipher::{NewCipher, StreamCipher, StreamCipherSeek};
+
+    /// Chunk sizes around block and buffer boundaries
+    const CHUNKS: [usize; 9] = [1, 63, 64, 65, 7, 256, 1000, 3, 511];
+
+    fn cipher() -> ChaCha20 {
+        ChaCha20::new(&Key::from([0x42; 32]), &Nonce::from([0x24; 12]))
+    }
+
+    /// Writing keystream interleaved with applying it must match applying
+    /// it to zeroes, and track the position the same way
+    #[test]
+    fn matches_apply_keystream() {
+        let len = CHUNKS.iter().sum::<usize>();
+        let mut expected = vec![0u8; len];
+        cipher().apply_keystream(&mut expected);
+
+        let mut cipher = cipher();
+        let mut buf = vec![0xffu8; len];
+        let mut pos = 0;
+        for (i, &n) in CHUNKS.iter().enumerate() {
+            let chunk = &mut buf[pos..pos + n];
+            if i % 2 == 0 {
+                cipher.write_keystream(chunk);
+            } else {
+                chunk.iter_mut().for_each(|b| *b = 0);
+                cipher.apply_keystream(chunk);
+            }
+            pos += n;
+            assert_eq!(cipher.current_pos::<usize>(), pos);
+        }
+        assert!(buf == expected);
+
+        cipher.seek(100u32);
+        cipher.write_keystream(&mut buf[..50]);
+        assert_eq!(&buf[..50], &expected[100..150]);
+    }
+
+    #[test]
+    fn end_of_keystream() {
+        let mut cipher = cipher();
+        let start = (1u64 << 38) - 100;
+        cipher.seek(start);
+        assert!(cipher.try_write_keystream(&mut [0u8; 101]).is_err());
+        assert_eq!(cipher.current_pos::<u64>(), start);
+
+        let mut buf = [0u8; 100];
+        cipher.try_write_keystream(&mut buf).unwrap();
+        assert!(cipher.try_write_keystream(&mut [0u8; 1]).is_err());
+
+        let mut expected = [0u8; 100];
+        let mut cipher = self::cipher();
+        cipher.seek(start);
+        cipher.apply_keystream(&mut expected);
+        assert_eq!(buf, expected);
+    }
+
+    #[cfg(feature = "xchacha")]
+    #[test]
+    fn xchacha20() {
+        use chacha20::{XChaCha20, XNonce};
+
+        let new = || XChaCha20::new(&Key::from([0x42; 32]), &XNonce::from([0x24; 24]));
+        let mut expected = [0u8; 300];
+        new().apply_keystream(&mut expected);
+
+        let mut cipher = new();
+        let mut buf = [0u8; 300];
+        cipher.write_keystream(&mut buf[..10]);
+        cipher.write_keystream(&mut buf[10..]);
+        assert_eq!(&buf[..], &expected[..]);
+    }
+
+    #[cfg(feature = "legacy")]
+    #[test]
+    fn legacy() {
+        use chacha20::{ChaCha20Legacy, LegacyNonce};
+
+        let new = || ChaCha20Legacy::new(&Key::from([0x42; 32]), &LegacyNonce::from([0x24; 8]));
+        let mut expected = [0u8; 300];
+        new().apply_keystream(&mut expected);
+
+        let mut cipher = new();
+        let mut buf = [0u8; 300];
+        cipher.write_keystream(&mut buf[..130]);
+        cipher.write_keystream(&mut buf[130..]);
+        assert_eq!(&buf[..], &expected[..]);
+    }
+}
+
+#[cfg(feature = "rayon")]
+mod par {
+    use chacha20::{ChaCha20, Key, Nonce};
+    use cipher::{NewCipher, StreamCipher, StreamCipherSeek};
+
+    const LEN: usize = 3 * (1 << 16) + 1000;
+
+    /// Start positions and lengths around block, buffer and chunk boundaries
+    const CASES: [(usize, usize); 8] = [
+        (0, 0),
+        (0, 10),
+        (5, 100),
+        (0, LEN),
+        (1, LEN - 1),
+        (63, 1 << 16),
+        (64, 2 * (1 << 16) + 64),
+        (130, LEN - 130),
+    ];
+
+    fn cipher() -> ChaCha20 {
+        ChaCha20::new(&Key::from([0x42; 32]), &Nonce::from([0x24; 12]))
+    }
+
+    #[test]
+    fn matches_serial() {
+        let mut expected = vec![0u8; LEN + 100];
+        cipher().apply_keystream(&mut expected);
+
+        for &(start, len) in &CASES {
+            let mut cipher = cipher();
+            cipher.seek(start as u64);
+            let mut buf = vec![0u8; len + 100];
+            cipher.par_apply_keystream(&mut buf[..len]);
+            assert_eq!(cipher.current_pos::<usize>(), start + len);
+            cipher.apply_keystream(&mut buf[len..]);
+            assert_eq!(&buf[..], &expected[start..start + len + 100]);
+        }
+    }
+
+    #[test]
+    fn end_of_keystream() {
+        let mut cipher = cipher();
+        let start = (1u64 << 38) - LEN as u64;
+        cipher.seek(start);
+        assert!(cipher.try_par_apply_keystream(&mut [0u8; LEN + 1]).is_err());
+        assert_eq!(cipher.current_pos::<u64>(), start);
+
+        let mut buf = vec![0u8; LEN];
+        let mut expected = buf.clone();
+        cipher.try_par_apply_keystream(&mut buf).unwrap();
+        assert!(cipher.try_par_apply_keystream(&mut [0u8; 1]).is_err());
+
+        let mut cipher = self::cipher();
+        cipher.seek(start);
+        cipher.apply_keystream(&mut expected);
+        assert!(buf == expected);
+    }
+
+    #[cfg(feature = "xchacha")]
+    #[test]
+    fn xchacha20_matches_serial() {
+        use chacha20::{XChaCha20, XNonce};
+
+        let new = || XChaCha20::new(&Key::from([0x42; 32]), &XNonce::from([0x24; 24]));
+        let mut expected = vec![0u8; LEN];
+        new().apply_keystream(&mut expected);
+
+        let mut cipher = new();
+        let mut buf = vec![0u8; LEN];
+        cipher.par_apply_keystream(&mut buf[..7]);
+        cipher.par_apply_keystream(&mut buf[7..]);
+        assert_eq!(cipher.current_pos::<usize>(), LEN);
+        assert!(buf == expected);
+    }
+
+    /// Whole buffers can end at the last block of a 64-bit counter
+    #[cfg(feature = "xchacha")]
+    #[test]
+    fn xchacha20_end_of_keystream() {
+        use chacha20::{XChaCha20, XNonce};
+
+        let mut cipher = XChaCha20::new(&Key::from([0x42; 32]), &XNonce::from([0x24; 24]));
+        cipher.seek((1u128 << 70) - LEN as u128);
+        let mut buf = vec![0u8; LEN];
+        cipher.try_par_apply_keystream(&mut buf).unwrap();
+        assert!(cipher.try_par_apply_keystream(&mut [0u8; 1]).is_err());
+    }
+}
+
 // Legacy "djb" version of ChaCha20 (64-bit nonce)
 #[cfg(feature = "legacy")]
 #[rustfmt::skip]
@@ -345,3 +824,145 @@ mod legacy {
         }
     }
 }
+
+#[cfg(feature = "zeroize")]
+mod zeroize {
+    use chacha20::{ChaCha20, Key, Nonce};
+    use cipher::{NewCipher, StreamCipher, StreamCipherSeek};
+    use core::{
+        mem::{self, MaybeUninit},
+        ptr, slice,
+    };
+    use zeroize::Zeroize;
+
+    const KEY: [u8; 32] = [0x42; 32];
+    const NONCE: [u8; 12] = [0x24; 12];
+
+    /// Check that `cipher` has been wiped: an all-zero ChaCha state produces
+    /// an all-zero first block of keystream.
+    fn assert_cleared<C: StreamCipher + StreamCipherSeek>(mut cipher: C) {
+        assert_eq!(cipher.current_pos::<u64>(), 0);
+        let mut buf = [0u8; 64];
+        cipher.apply_keystream(&mut buf);
+        assert!(buf.iter().all(|&b| b == 0));
+    }
+
+    /// Drop `value` in place and check that no 4-byte window of any of
+    /// `secrets` is left in the memory it occupied.
+    ///
+    /// The memory is inspected as bytes rather than read back as a `T`. It's
+    /// searched for the secrets instead of checked for zeros, as padding
+    /// bytes aren't wiped and may hold unrelated stale data.
+    fn assert_dropped_cleared<T>(value: T, secrets: &[&[u8]]) {
+        let mut slot = MaybeUninit::new(value);
+        let bytes = unsafe {
+            ptr::drop_in_place(slot.as_mut_ptr());
+            slice::from_raw_parts(slot.as_ptr() as *const u8, mem::size_of::<T>())
+        };
+        for secret in secrets {
+            for window in secret.windows(4).filter(|w| w.iter().any(|&b| b != 0)) {
+                assert!(!bytes.windows(4).any(|b| b == window));
+            }
+        }
+    }
+
+    #[test]
+    fn chacha20_zeroize() {
+        let mut cipher = ChaCha20::new(&Key::from(KEY), &Nonce::from(NONCE));
+        let mut buf = [0u8; 100];
+        cipher.apply_keystream(&mut buf);
+        assert!(buf.iter().any(|&b| b != 0));
+
+        cipher.zeroize();
+        assert_cleared(cipher);
+    }
+
+    #[test]
+    fn chacha20_drop() {
+        let mut cipher = ChaCha20::new(&Key::from(KEY), &Nonce::from(NONCE));
+        cipher.apply_keystream(&mut [0u8; 100]);
+        // the blocks the cipher has generated so far
+        let mut keystream = [0u8; 128];
+        ChaCha20::new(&Key::from(KEY), &Nonce::from(NONCE)).apply_keystream(&mut keystream);
+        assert_dropped_cleared(cipher, &[&KEY, &NONCE, &keystream]);
+    }
+
+    #[cfg(feature = "xchacha")]
+    #[test]
+    fn xchacha20_zeroize() {
+        use chacha20::{XChaCha20, XNonce};
+
+        let mut cipher = XChaCha20::new(&Key::from(KEY), &XNonce::from([0x24; 24]));
+        cipher.apply_keystream(&mut [0u8; 100]);
+
+        cipher.zeroize();
+        assert_cleared(cipher);
+    }
+
+    #[cfg(feature = "legacy")]
+    #[test]
+    fn chacha20_legacy_zeroize() {
+        use chacha20::{ChaCha20Legacy, LegacyNonce};
+
+        let mut cipher = ChaCha20Legacy::new(&Key::from(KEY), &LegacyNonce::from([0x24; 8]));
+        cipher.apply_keystream(&mut [0u8; 100]);
+
+        cipher.zeroize();
+        assert_cleared(cipher);
+    }
+
+    /// A wiped AEAD instance behaves like one with an all-zero key
+    #[cfg(feature = "aead")]
+    fn assert_aead_cleared(aead: chacha20::ChaCha20Poly1305) {
+        use chacha20::ChaCha20Poly1305;
+
+        let nonce = Nonce::from(NONCE);
+        let (mut actual, mut expected) = ([0u8; 16], [0u8; 16]);
+        let tag = aead
+            .encrypt_in_place_detached(&nonce, b"", &mut actual)
+            .unwrap();
+        let expected_tag = ChaCha20Poly1305::new(&Key::default())
+            .encrypt_in_place_detached(&nonce, b"", &mut expected)
+            .unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(tag, expected_tag);
+    }
+
+    #[cfg(feature = "aead")]
+    #[test]
+    fn chacha20poly1305_zeroize() {
+        let mut aead = chacha20::ChaCha20Poly1305::new(&Key::from(KEY));
+        aead.zeroize();
+        assert_aead_cleared(aead);
+    }
+
+    #[cfg(feature = "aead")]
+    #[test]
+    fn chacha20poly1305_drop() {
+        let aead = chacha20::ChaCha20Poly1305::new(&Key::from(KEY));
+        assert_dropped_cleared(aead, &[&KEY]);
+    }
+}
+
+/// The `cipher-io` adapters re-exported by the `io` feature
+#[cfg(feature = "io")]
+mod io {
+    use chacha20::{cipher::NewCipher, ChaCha20, Key, Nonce, StreamReader, StreamWriter};
+    use std::io::{Read, Write};
+
+    #[test]
+    fn round_trip() {
+        let key = Key::from([0x42; 32]);
+        let nonce = Nonce::from([0x24; 12]);
+
+        let mut writer = StreamWriter::new(ChaCha20::new(&key, &nonce), Vec::new());
+        writer.write_all(b"hello world").unwrap();
+        let (_, ciphertext) = writer.into_inner().unwrap();
+        assert_ne!(ciphertext, b"hello world");
+
+        let mut reader = StreamReader::new(ChaCha20::new(&key, &nonce), &ciphertext[..]);
+        let mut plaintext = Vec::new();
+        reader.read_to_end(&mut plaintext).unwrap();
+        assert_eq!(plaintext, b"hello world");
+    }
+}
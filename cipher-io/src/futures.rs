@@ -0,0 +1,112 @@
+//! Adapters for the [`AsyncRead`] and [`AsyncWrite`] traits from
+//! `futures-io`.
+
+use crate::{apply_read_keystream, keystream_error};
+use cipher::{AsyncStreamCipher, StreamCipher};
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures_io_crate::{AsyncRead, AsyncWrite};
+use std::io;
+
+/// Implement [`AsyncRead`] for a reader which passes the data read from the
+/// inner reader through `$apply`, with `$cipher` and `$data` bound to the
+/// cipher and the data
+macro_rules! impl_read {
+    ($name:ident, $bound:ident, |$cipher:ident, $data:ident| $apply:expr) => {
+        impl<C: $bound, R: AsyncRead> AsyncRead for $name<C, R> {
+            fn poll_read(
+                self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                let this = self.project();
+                let n = ready!(this.inner.poll_read(cx, buf));
+
+                let res: io::Result<()> = {
+                    let $cipher = this.cipher;
+                    let $data = &mut buf[..n];
+                    $apply
+                };
+                Poll::Ready(res.map(|()| n))
+            }
+        }
+    };
+}
+
+async_reader!(
+    /// Reader which applies the keystream of a [`StreamCipher`] to the data
+    /// read from the inner reader, i.e. encrypts or decrypts it.
+    ///
+    /// Reaching the end of the keystream is reported as an error of kind
+    /// [`io::ErrorKind::Other`], and the data read by that call is discarded
+    /// and zeroed in the buffer.
+    StreamReader
+);
+
+impl_read!(StreamReader, StreamCipher, |cipher, data| {
+    apply_read_keystream(cipher, data)
+});
+
+async_reader!(
+    /// Reader which encrypts the data read from the inner reader with an
+    /// [`AsyncStreamCipher`].
+    EncryptReader
+);
+
+impl_read!(EncryptReader, AsyncStreamCipher, |cipher, data| {
+    cipher.encrypt(data);
+    Ok(())
+});
+
+async_reader!(
+    /// Reader which decrypts the data read from the inner reader with an
+    /// [`AsyncStreamCipher`].
+    DecryptReader
+);
+
+impl_read!(DecryptReader, AsyncStreamCipher, |cipher, data| {
+    cipher.decrypt(data);
+    Ok(())
+});
+
+async_writer!(
+    AsyncWrite,
+    poll_close,
+    /// Writer which applies the keystream of a [`StreamCipher`] to the data
+    /// written to it, i.e. encrypts or decrypts it, before passing it on to
+    /// the inner writer.
+    ///
+    /// Reaching the end of the keystream is reported as an error of kind
+    /// [`io::ErrorKind::Other`], without accepting any data.
+    StreamWriter,
+    StreamCipher,
+    |cipher, data| cipher.try_apply_keystream(data).map_err(keystream_error)
+);
+
+async_writer!(
+    AsyncWrite,
+    poll_close,
+    /// Writer which encrypts the data written to it with an
+    /// [`AsyncStreamCipher`] before passing it on to the inner writer.
+    EncryptWriter,
+    AsyncStreamCipher,
+    |cipher, data| {
+        cipher.encrypt(data);
+        Ok(())
+    }
+);
+
+async_writer!(
+    AsyncWrite,
+    poll_close,
+    /// Writer which decrypts the data written to it with an
+    /// [`AsyncStreamCipher`] before passing it on to the inner writer.
+    DecryptWriter,
+    AsyncStreamCipher,
+    |cipher, data| {
+        cipher.decrypt(data);
+        Ok(())
+    }
+);
@@ -0,0 +1,149 @@
+//! [`std::io`] adapters for stream ciphers implementing the traits from the
+//! [`cipher`](https://docs.rs/cipher) crate.
+//!
+//! [`StreamReader`] and [`StreamWriter`] apply the keystream of a
+//! [`StreamCipher`] to the data read from a [`Read`] or written to a
+//! [`Write`] implementation. If the cipher implements [`StreamCipherSeek`]
+//! and the wrapped reader or writer implements [`Seek`], so does the adapter:
+//! seeking moves the keystream position by the same amount as the position
+//! in the stream.
+//!
+//! Self-synchronizing modes such as CFB implement [`AsyncStreamCipher`],
+//! which encrypts and decrypts differently, so they are wrapped by
+//! [`EncryptReader`], [`DecryptReader`], [`EncryptWriter`] or
+//! [`DecryptWriter`] instead.
+//!
+//! The cipher crates of the
+//! [RustCrypto/stream-ciphers](https://github.com/RustCrypto/stream-ciphers)
+//! repository re-export these adapters under their `io` feature.
+//!
+//! The `tokio` and `futures-io` features provide the same adapters for the
+//! asynchronous `AsyncRead` and `AsyncWrite` traits of the respective crates
+//! in the `tokio` and `futures` modules. They apply the keystream in place as
+//! data is polled, and buffer data the inner writer hasn't accepted yet the
+//! same way, so partial or pending reads and writes don't desynchronize the
+//! keystream.
+//!
+//! # ⚠️ Security Warning: [Hazmat!]
+//!
+//! These adapters do not ensure ciphertexts are authentic! Thus ciphertext
+//! integrity is not verified, which can lead to serious vulnerabilities!
+//!
+//! # Usage
+//!
+//! ```
+//! use chacha20::{ChaCha20, Key, Nonce};
+//! use chacha20::cipher::NewCipher;
+//! use cipher_io::{StreamReader, StreamWriter};
+//! use std::io::{Read, Write};
+//!
+//! let key = Key::from_slice(b"an example very very secret key.");
+//! let nonce = Nonce::from_slice(b"secret nonce");
+//!
+//! // encrypt data while writing it
+//! let mut writer = StreamWriter::new(ChaCha20::new(key, nonce), Vec::new());
+//! writer.write_all(b"hello world")?;
+//! let (_, ciphertext) = writer.into_inner()?;
+//! assert_ne!(ciphertext, b"hello world");
+//!
+//! // and decrypt it while reading it back
+//! let mut reader = StreamReader::new(ChaCha20::new(key, nonce), &ciphertext[..]);
+//! let mut plaintext = String::new();
+//! reader.read_to_string(&mut plaintext)?;
+//! assert_eq!(plaintext, "hello world");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! [Hazmat!]: https://github.com/RustCrypto/meta/blob/master/HAZMAT.md
+
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/RustCrypto/meta/master/logo.svg",
+    html_favicon_url = "https://raw.githubusercontent.com/RustCrypto/meta/master/logo.svg"
+)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms)]
+
+pub use cipher;
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+#[macro_use]
+mod poll;
+mod read;
+mod write;
+
+#[cfg(feature = "futures-io")]
+#[cfg_attr(docsrs, doc(cfg(feature = "futures-io")))]
+pub mod futures;
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub mod tokio;
+
+pub use crate::{
+    read::{DecryptReader, EncryptReader, StreamReader},
+    write::{DecryptWriter, EncryptWriter, StreamWriter},
+};
+
+use cipher::{errors::LoopError, StreamCipher, StreamCipherSeek};
+use std::io::{self, Seek, SeekFrom};
+
+#[cfg(doc)]
+use cipher::AsyncStreamCipher;
+#[cfg(doc)]
+use std::io::{Read, Write};
+
+/// Maximum number of bytes accepted by a single write to a writer adapter
+const BUF_SIZE: usize = 8 * 1024;
+
+/// Error returned when the end of the keystream is reached
+fn keystream_error(err: LoopError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+/// Apply the keystream of `cipher` to the data read into `data`.
+///
+/// If the end of the keystream is reached, `data` is zeroed so the
+/// unprocessed input isn't left in the caller's buffer.
+fn apply_read_keystream<C: StreamCipher>(cipher: &mut C, data: &mut [u8]) -> io::Result<()> {
+    cipher.try_apply_keystream(data).map_err(|err| {
+        data.iter_mut().for_each(|b| *b = 0);
+        keystream_error(err)
+    })
+}
+
+/// Seek `inner` to `pos` and move the keystream position of `cipher` by the
+/// same amount, returning the new position of `inner`.
+///
+/// If the keystream position can't be moved, `inner` is sought back to
+/// where it was.
+fn seek<C, S>(cipher: &mut C, inner: &mut S, pos: SeekFrom) -> io::Result<u64>
+where
+    C: StreamCipherSeek,
+    S: Seek,
+{
+    let old = inner.seek(SeekFrom::Current(0))?;
+    let new = inner.seek(pos)?;
+
+    let res = cipher
+        .try_current_pos::<u64>()
+        .map_err(|err| keystream_error(err.into()))
+        .and_then(|cur| {
+            let target = if new >= old {
+                cur.checked_add(new - old)
+            } else {
+                cur.checked_sub(old - new)
+            };
+            target.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "seek outside of the keystream")
+            })
+        })
+        .and_then(|target| cipher.try_seek(target).map_err(keystream_error));
+
+    match res {
+        Ok(()) => Ok(new),
+        Err(err) => {
+            inner.seek(SeekFrom::Start(old))?;
+            Err(err)
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Helpers shared by the asynchronous adapters.
+
+use std::{io, task::Poll};
+
+/// Write out the processed data in `buf` using `write`, keeping whatever
+/// hasn't been accepted yet if it returns an error or is pending.
+pub(crate) fn poll_write_buf(
+    buf: &mut Vec<u8>,
+    mut write: impl FnMut(&[u8]) -> Poll<io::Result<usize>>,
+) -> Poll<io::Result<()>> {
+    let mut written = 0;
+    let res = loop {
+        if written == buf.len() {
+            break Poll::Ready(Ok(()));
+        }
+        match write(&buf[written..]) {
+            Poll::Ready(Ok(0)) => {
+                break Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write the buffered data",
+                )))
+            }
+            Poll::Ready(Ok(n)) => written += n,
+            Poll::Ready(Err(err)) if err.kind() == io::ErrorKind::Interrupted => {}
+            Poll::Ready(Err(err)) => break Poll::Ready(Err(err)),
+            Poll::Pending => break Poll::Pending,
+        }
+    };
+    buf.drain(..written);
+    res
+}
+
+/// Define a reader adapter struct with its constructor and accessors
+macro_rules! async_reader {
+    ($(#[$attr:meta])* $name:ident) => {
+        pin_project_lite::pin_project! {
+            $(#[$attr])*
+            pub struct $name<C, R> {
+                cipher: C,
+                #[pin]
+                inner: R,
+            }
+        }
+
+        impl<C, R> $name<C, R> {
+            /// Wrap `inner`, starting at the current keystream position of
+            /// `cipher`.
+            pub fn new(cipher: C, inner: R) -> Self {
+                Self { cipher, inner }
+            }
+
+            /// Get a reference to the inner reader.
+            pub fn get_ref(&self) -> &R {
+                &self.inner
+            }
+
+            /// Get a mutable reference to the inner reader.
+            ///
+            /// Reading from it directly desynchronizes the keystream.
+            pub fn get_mut(&mut self) -> &mut R {
+                &mut self.inner
+            }
+
+            /// Get a pinned mutable reference to the inner reader.
+            ///
+            /// Reading from it directly desynchronizes the keystream.
+            pub fn get_pin_mut(self: core::pin::Pin<&mut Self>) -> core::pin::Pin<&mut R> {
+                self.project().inner
+            }
+
+            /// Unwrap the cipher and the inner reader.
+            pub fn into_inner(self) -> (C, R) {
+                (self.cipher, self.inner)
+            }
+        }
+    };
+}
+
+/// Return early from the enclosing function unless `$e` is ready and
+/// successful
+macro_rules! ready {
+    ($e:expr) => {
+        match $e {
+            core::task::Poll::Ready(Ok(val)) => val,
+            core::task::Poll::Ready(Err(err)) => return core::task::Poll::Ready(Err(err)),
+            core::task::Poll::Pending => return core::task::Poll::Pending,
+        }
+    };
+}
+
+/// Define a writer adapter implementing `$write`, whose close method is
+/// `$close`, which passes the data written to it through `$apply`, with
+/// `$cipher` and `$data` bound to the cipher and the data
+macro_rules! async_writer {
+    (
+        $write:path, $close:ident,
+        $(#[$attr:meta])*
+        $name:ident, $bound:ident, |$cipher:ident, $data:ident| $apply:expr
+    ) => {
+        pin_project_lite::pin_project! {
+            $(#[$attr])*
+            ///
+            /// Once the keystream has been applied to data, it's buffered
+            /// until the inner writer accepts it, so partial writes, pending
+            /// writes and errors of the inner writer don't desynchronize the
+            /// keystream. Errors writing out buffered data are returned by the
+            /// next call to `poll_write` or `poll_flush`, so flush or shut
+            /// down the writer when done.
+            pub struct $name<C, W> {
+                cipher: C,
+                #[pin]
+                inner: W,
+                buf: Vec<u8>,
+            }
+        }
+
+        impl<C, W> $name<C, W> {
+            /// Wrap `inner`, starting at the current keystream position of
+            /// `cipher`.
+            pub fn new(cipher: C, inner: W) -> Self {
+                Self {
+                    cipher,
+                    inner,
+                    buf: Vec::new(),
+                }
+            }
+
+            /// Get a reference to the inner writer.
+            pub fn get_ref(&self) -> &W {
+                &self.inner
+            }
+
+            /// Get a mutable reference to the inner writer.
+            ///
+            /// Writing to it directly while data is buffered corrupts the
+            /// output.
+            pub fn get_mut(&mut self) -> &mut W {
+                &mut self.inner
+            }
+
+            /// Get a pinned mutable reference to the inner writer.
+            ///
+            /// Writing to it directly while data is buffered corrupts the
+            /// output.
+            pub fn get_pin_mut(self: core::pin::Pin<&mut Self>) -> core::pin::Pin<&mut W> {
+                self.project().inner
+            }
+
+            /// Unwrap the cipher and the inner writer.
+            ///
+            /// Data which hasn't been written out yet is lost, so flush the
+            /// writer first.
+            pub fn into_inner(self) -> (C, W) {
+                (self.cipher, self.inner)
+            }
+        }
+
+        impl<C: $bound, W: $write> $write for $name<C, W> {
+            fn poll_write(
+                self: core::pin::Pin<&mut Self>,
+                cx: &mut core::task::Context<'_>,
+                data: &[u8],
+            ) -> core::task::Poll<std::io::Result<usize>> {
+                let this = self.project();
+                let mut inner = this.inner;
+                ready!(crate::poll::poll_write_buf(this.buf, |buf| inner
+                    .as_mut()
+                    .poll_write(cx, buf)));
+
+                let n = core::cmp::min(data.len(), crate::BUF_SIZE);
+                this.buf.extend_from_slice(&data[..n]);
+                let res: std::io::Result<()> = {
+                    let $cipher = this.cipher;
+                    let $data = &mut this.buf[..];
+                    $apply
+                };
+                if let Err(err) = res {
+                    this.buf.clear();
+                    return core::task::Poll::Ready(Err(err));
+                }
+
+                // the data has been accepted at this point, so errors are
+                // reported by the next call
+                let _ = crate::poll::poll_write_buf(this.buf, |buf| {
+                    inner.as_mut().poll_write(cx, buf)
+                });
+                core::task::Poll::Ready(Ok(n))
+            }
+
+            fn poll_flush(
+                self: core::pin::Pin<&mut Self>,
+                cx: &mut core::task::Context<'_>,
+            ) -> core::task::Poll<std::io::Result<()>> {
+                let this = self.project();
+                let mut inner = this.inner;
+                ready!(crate::poll::poll_write_buf(this.buf, |buf| inner
+                    .as_mut()
+                    .poll_write(cx, buf)));
+                inner.poll_flush(cx)
+            }
+
+            fn $close(
+                self: core::pin::Pin<&mut Self>,
+                cx: &mut core::task::Context<'_>,
+            ) -> core::task::Poll<std::io::Result<()>> {
+                let this = self.project();
+                let mut inner = this.inner;
+                ready!(crate::poll::poll_write_buf(this.buf, |buf| inner
+                    .as_mut()
+                    .poll_write(cx, buf)));
+                inner.$close(cx)
+            }
+        }
+    };
+}
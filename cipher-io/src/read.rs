@@ -0,0 +1,89 @@
+//! Adapters for [`Read`] implementations.
+
+use crate::apply_read_keystream;
+use cipher::{AsyncStreamCipher, StreamCipher, StreamCipherSeek};
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Define a reader which passes the data read from the inner reader through
+/// `$apply`, with `$cipher` and `$data` bound to the cipher and the data
+macro_rules! impl_reader {
+    (
+        $(#[$attr:meta])*
+        $name:ident, $bound:ident, |$cipher:ident, $data:ident| $apply:expr
+    ) => {
+        $(#[$attr])*
+        pub struct $name<C, R> {
+            cipher: C,
+            inner: R,
+        }
+
+        impl<C, R> $name<C, R> {
+            /// Wrap `inner`, starting at the current keystream position of
+            /// `cipher`.
+            pub fn new(cipher: C, inner: R) -> Self {
+                Self { cipher, inner }
+            }
+
+            /// Get a reference to the inner reader.
+            pub fn get_ref(&self) -> &R {
+                &self.inner
+            }
+
+            /// Get a mutable reference to the inner reader.
+            ///
+            /// Reading from it directly desynchronizes the keystream.
+            pub fn get_mut(&mut self) -> &mut R {
+                &mut self.inner
+            }
+
+            /// Unwrap the cipher and the inner reader.
+            pub fn into_inner(self) -> (C, R) {
+                (self.cipher, self.inner)
+            }
+        }
+
+        impl<C: $bound, R: Read> Read for $name<C, R> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = self.inner.read(buf)?;
+                let $cipher = &mut self.cipher;
+                let $data = &mut buf[..n];
+                $apply;
+                Ok(n)
+            }
+        }
+    };
+}
+
+impl_reader!(
+    /// Reader which applies the keystream of a [`StreamCipher`] to the data
+    /// read from the inner reader, i.e. encrypts or decrypts it.
+    ///
+    /// Reaching the end of the keystream is reported as an error of kind
+    /// [`io::ErrorKind::Other`], and the data read by that call is discarded
+    /// and zeroed in `buf`.
+    StreamReader,
+    StreamCipher,
+    |cipher, data| apply_read_keystream(cipher, data)?
+);
+
+impl_reader!(
+    /// Reader which encrypts the data read from the inner reader with an
+    /// [`AsyncStreamCipher`].
+    EncryptReader,
+    AsyncStreamCipher,
+    |cipher, data| cipher.encrypt(data)
+);
+
+impl_reader!(
+    /// Reader which decrypts the data read from the inner reader with an
+    /// [`AsyncStreamCipher`].
+    DecryptReader,
+    AsyncStreamCipher,
+    |cipher, data| cipher.decrypt(data)
+);
+
+impl<C: StreamCipherSeek, R: Seek> Seek for StreamReader<C, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        crate::seek(&mut self.cipher, &mut self.inner, pos)
+    }
+}
@@ -0,0 +1,156 @@
+//! Adapters for [`Write`] implementations.
+
+use crate::{keystream_error, BUF_SIZE};
+use cipher::{AsyncStreamCipher, StreamCipher, StreamCipherSeek};
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// Write out the processed data in `buf` which the inner writer hasn't
+/// accepted yet, keeping whatever is left on error.
+fn write_buf<W: Write>(inner: &mut W, buf: &mut Vec<u8>) -> io::Result<()> {
+    let mut written = 0;
+    let res = loop {
+        if written == buf.len() {
+            break Ok(());
+        }
+        match inner.write(&buf[written..]) {
+            Ok(0) => {
+                break Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write the buffered data",
+                ))
+            }
+            Ok(n) => written += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => break Err(err),
+        }
+    };
+    buf.drain(..written);
+    res
+}
+
+/// Define a writer which passes the data written to it through `$apply`,
+/// with `$cipher` and `$data` bound to the cipher and the data
+macro_rules! impl_writer {
+    (
+        $(#[$attr:meta])*
+        $name:ident, $bound:ident, |$cipher:ident, $data:ident| $apply:expr
+    ) => {
+        $(#[$attr])*
+        ///
+        /// Once the keystream has been applied to data, it's buffered until
+        /// the inner writer accepts it, so partial writes and errors of the
+        /// inner writer don't desynchronize the keystream. Errors writing out
+        /// buffered data are returned by the next call to `write` or `flush`,
+        /// so call [`Write::flush`] or the `into_inner` method when done.
+        pub struct $name<C, W> {
+            cipher: C,
+            inner: W,
+            buf: Vec<u8>,
+        }
+
+        impl<C, W> $name<C, W> {
+            /// Wrap `inner`, starting at the current keystream position of
+            /// `cipher`.
+            pub fn new(cipher: C, inner: W) -> Self {
+                Self {
+                    cipher,
+                    inner,
+                    buf: Vec::new(),
+                }
+            }
+
+            /// Get a reference to the inner writer.
+            pub fn get_ref(&self) -> &W {
+                &self.inner
+            }
+
+            /// Get a mutable reference to the inner writer.
+            ///
+            /// Writing to it directly while data is buffered corrupts the
+            /// output.
+            pub fn get_mut(&mut self) -> &mut W {
+                &mut self.inner
+            }
+        }
+
+        impl<C, W: Write> $name<C, W> {
+            /// Write out the buffered data, then unwrap the cipher and the
+            /// inner writer.
+            pub fn into_inner(mut self) -> io::Result<(C, W)> {
+                write_buf(&mut self.inner, &mut self.buf)?;
+                Ok((self.cipher, self.inner))
+            }
+        }
+
+        impl<C: $bound, W: Write> Write for $name<C, W> {
+            fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+                write_buf(&mut self.inner, &mut self.buf)?;
+
+                let n = core::cmp::min(data.len(), BUF_SIZE);
+                self.buf.extend_from_slice(&data[..n]);
+                let res = {
+                    let $cipher = &mut self.cipher;
+                    let $data = &mut self.buf[..];
+                    $apply
+                };
+                if let Err(err) = res {
+                    self.buf.clear();
+                    return Err(err);
+                }
+
+                // the data has been accepted at this point, so errors are
+                // reported by the next call
+                let _ = write_buf(&mut self.inner, &mut self.buf);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                write_buf(&mut self.inner, &mut self.buf)?;
+                self.inner.flush()
+            }
+        }
+    };
+}
+
+impl_writer!(
+    /// Writer which applies the keystream of a [`StreamCipher`] to the data
+    /// written to it, i.e. encrypts or decrypts it, before passing it on to
+    /// the inner writer.
+    ///
+    /// Reaching the end of the keystream is reported as an error of kind
+    /// [`io::ErrorKind::Other`], without accepting any data.
+    StreamWriter,
+    StreamCipher,
+    |cipher, data| cipher.try_apply_keystream(data).map_err(keystream_error)
+);
+
+impl_writer!(
+    /// Writer which encrypts the data written to it with an
+    /// [`AsyncStreamCipher`] before passing it on to the inner writer.
+    EncryptWriter,
+    AsyncStreamCipher,
+    |cipher, data| {
+        cipher.encrypt(data);
+        Ok::<(), io::Error>(())
+    }
+);
+
+impl_writer!(
+    /// Writer which decrypts the data written to it with an
+    /// [`AsyncStreamCipher`] before passing it on to the inner writer.
+    DecryptWriter,
+    AsyncStreamCipher,
+    |cipher, data| {
+        cipher.decrypt(data);
+        Ok::<(), io::Error>(())
+    }
+);
+
+impl<C: StreamCipherSeek, W: Write + Seek> Seek for StreamWriter<C, W> {
+    /// Write out the buffered data, then seek the inner writer and the
+    /// keystream.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        write_buf(&mut self.inner, &mut self.buf)?;
+        crate::seek(&mut self.cipher, &mut self.inner, pos)
+    }
+}
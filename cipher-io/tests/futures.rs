@@ -0,0 +1,180 @@
+//! Tests for the `futures-io` adapters
+#![cfg(feature = "futures-io")]
+
+use aes::Aes128;
+use cipher::{AsyncStreamCipher, NewCipher, StreamCipher};
+use cipher_io::futures::{DecryptReader, DecryptWriter, EncryptReader, EncryptWriter};
+use cipher_io::futures::{StreamReader, StreamWriter};
+use futures::{
+    executor::block_on,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+type Aes128Cfb = cfb_mode::Cfb<Aes128>;
+
+const KEY: [u8; 16] = *b"very secret key.";
+const NONCE: [u8; 16] = *b"and secret nonce";
+const LEN: usize = 20_000;
+
+fn ctr() -> Aes128Ctr {
+    Aes128Ctr::new(&KEY.into(), &NONCE.into())
+}
+
+fn cfb() -> Aes128Cfb {
+    Aes128Cfb::new(&KEY.into(), &NONCE.into())
+}
+
+fn plaintext() -> Vec<u8> {
+    (0..LEN).map(|i| (i % 251) as u8).collect()
+}
+
+/// Writer which accepts at most 7 bytes at a time, and every other call is
+/// pending or fails without accepting anything
+#[derive(Default)]
+struct Unreliable {
+    data: Vec<u8>,
+    calls: usize,
+}
+
+impl AsyncWrite for Unreliable {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.calls += 1;
+        match self.calls % 4 {
+            0 => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            2 => Poll::Ready(Err(io::ErrorKind::Interrupted.into())),
+            _ => {
+                let n = std::cmp::min(buf.len(), 7);
+                self.data.extend_from_slice(&buf[..n]);
+                Poll::Ready(Ok(n))
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Reader which returns at most 5 bytes at a time, and every other call is
+/// pending
+struct Trickle<'a> {
+    data: &'a [u8],
+    calls: usize,
+}
+
+impl<'a> Trickle<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, calls: 0 }
+    }
+}
+
+impl AsyncRead for Trickle<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.calls += 1;
+        if self.calls % 2 == 0 {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let n = std::cmp::min(std::cmp::min(buf.len(), 5), self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[test]
+fn stream_round_trip() {
+    block_on(async {
+        let mut ct = plaintext();
+        ctr().apply_keystream(&mut ct);
+
+        let mut writer = StreamWriter::new(ctr(), Unreliable::default());
+        for chunk in plaintext().chunks(100) {
+            writer.write_all(chunk).await.unwrap();
+        }
+        writer.close().await.unwrap();
+        assert!(writer.get_ref().data == ct);
+
+        let mut out = Vec::new();
+        StreamReader::new(ctr(), Trickle::new(&ct))
+            .read_to_end(&mut out)
+            .await
+            .unwrap();
+        assert!(out == plaintext());
+    });
+}
+
+#[test]
+fn xchacha20_round_trip() {
+    use chacha20::XChaCha20;
+    let new = || XChaCha20::new(&[0x42; 32].into(), &[0x24; 24].into());
+
+    block_on(async {
+        let mut writer = StreamWriter::new(new(), Unreliable::default());
+        writer.write_all(&plaintext()).await.unwrap();
+        writer.flush().await.unwrap();
+        let (_, inner) = writer.into_inner();
+
+        let mut out = Vec::new();
+        StreamReader::new(new(), Trickle::new(&inner.data))
+            .read_to_end(&mut out)
+            .await
+            .unwrap();
+        assert!(out == plaintext());
+    });
+}
+
+#[test]
+fn cfb_round_trip() {
+    block_on(async {
+        let mut ct = plaintext();
+        cfb().encrypt(&mut ct);
+
+        let mut writer = EncryptWriter::new(cfb(), Unreliable::default());
+        for chunk in plaintext().chunks(333) {
+            writer.write_all(chunk).await.unwrap();
+        }
+        writer.close().await.unwrap();
+        assert!(writer.get_ref().data == ct);
+
+        let mut out = Vec::new();
+        EncryptReader::new(cfb(), Trickle::new(&plaintext()))
+            .read_to_end(&mut out)
+            .await
+            .unwrap();
+        assert!(out == ct);
+
+        let mut writer = DecryptWriter::new(cfb(), Unreliable::default());
+        writer.write_all(&ct).await.unwrap();
+        writer.close().await.unwrap();
+        assert!(writer.get_ref().data == plaintext());
+
+        let mut out = Vec::new();
+        DecryptReader::new(cfb(), Trickle::new(&ct))
+            .read_to_end(&mut out)
+            .await
+            .unwrap();
+        assert!(out == plaintext());
+    });
+}
@@ -0,0 +1,275 @@
+//! Tests for the `std::io` adapters
+
+use aes::Aes128;
+use cipher::{AsyncStreamCipher, NewCipher, StreamCipher, StreamCipherSeek};
+use cipher_io::{DecryptReader, DecryptWriter, EncryptReader, EncryptWriter};
+use cipher_io::{StreamReader, StreamWriter};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+type Aes128Cfb8 = cfb8::Cfb8<Aes128>;
+
+const KEY: [u8; 16] = *b"very secret key.";
+const NONCE: [u8; 16] = *b"and secret nonce";
+const LEN: usize = 20_000;
+
+fn ctr() -> Aes128Ctr {
+    Aes128Ctr::new(&KEY.into(), &NONCE.into())
+}
+
+fn plaintext() -> Vec<u8> {
+    (0..LEN).map(|i| (i % 251) as u8).collect()
+}
+
+fn ciphertext() -> Vec<u8> {
+    let mut buf = plaintext();
+    ctr().apply_keystream(&mut buf);
+    buf
+}
+
+/// Writer which accepts at most 7 bytes at a time, and every third call
+/// fails without accepting anything
+struct Unreliable {
+    data: Vec<u8>,
+    calls: usize,
+}
+
+impl Write for Unreliable {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.calls += 1;
+        match self.calls % 3 {
+            0 => Err(io::ErrorKind::WouldBlock.into()),
+            1 => Err(io::ErrorKind::Interrupted.into()),
+            _ => {
+                let n = std::cmp::min(buf.len(), 7);
+                self.data.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reader which returns at most 5 bytes at a time
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = std::cmp::min(std::cmp::min(buf.len(), 5), self.0.len());
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn stream_round_trip() {
+    let mut writer = StreamWriter::new(ctr(), Vec::new());
+    for chunk in plaintext().chunks(999) {
+        writer.write_all(chunk).unwrap();
+    }
+    let (_, out) = writer.into_inner().unwrap();
+    assert!(out == ciphertext());
+
+    let ct = ciphertext();
+    let mut reader = StreamReader::new(ctr(), Trickle(&ct));
+    let mut out = Vec::new();
+    reader.read_to_end(&mut out).unwrap();
+    assert!(out == plaintext());
+}
+
+#[test]
+fn partial_writes_keep_keystream_in_sync() {
+    let mut writer = StreamWriter::new(
+        ctr(),
+        Unreliable {
+            data: Vec::new(),
+            calls: 0,
+        },
+    );
+    let pt = plaintext();
+    let mut data = &pt[..];
+    while !data.is_empty() {
+        match writer.write(&data[..std::cmp::min(data.len(), 100)]) {
+            Ok(n) => data = &data[n..],
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::WouldBlock),
+        }
+    }
+    loop {
+        match writer.flush() {
+            Ok(()) => break,
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::WouldBlock),
+        }
+    }
+    assert!(writer.get_ref().data == ciphertext());
+}
+
+#[test]
+fn reader_seek() {
+    let ct = ciphertext();
+    let pt = plaintext();
+    let mut reader = StreamReader::new(ctr(), Cursor::new(&ct));
+    let mut buf = [0u8; 100];
+
+    for &pos in &[
+        SeekFrom::Start(1000),
+        SeekFrom::Current(-500),
+        SeekFrom::Current(17),
+        SeekFrom::End(-100),
+        SeekFrom::Start(0),
+    ] {
+        let start = reader.seek(pos).unwrap() as usize;
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &pt[start..start + 100]);
+    }
+
+    let err = reader.seek(SeekFrom::Current(-101)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(reader.get_ref().position(), 100);
+}
+
+/// The keystream position follows the stream relative to where it started
+#[test]
+fn reader_seek_after_header() {
+    let mut data = b"header".to_vec();
+    data.extend_from_slice(&ciphertext());
+    let mut inner = Cursor::new(&data);
+    inner.seek(SeekFrom::Start(6)).unwrap();
+
+    let mut reader = StreamReader::new(ctr(), inner);
+    reader.seek(SeekFrom::Current(300)).unwrap();
+    let mut buf = [0u8; 100];
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf[..], &plaintext()[300..400]);
+
+    // seeking before the start of the keystream fails
+    assert!(reader.seek(SeekFrom::Start(0)).is_err());
+    assert_eq!(reader.get_ref().position(), 406);
+}
+
+#[test]
+fn writer_seek() {
+    let pt = plaintext();
+    let mut writer = StreamWriter::new(ctr(), Cursor::new(Vec::new()));
+    writer.write_all(&[0u8; LEN]).unwrap();
+    writer.seek(SeekFrom::Start(0)).unwrap();
+    writer.write_all(&pt[..LEN / 2]).unwrap();
+    writer.seek(SeekFrom::End(-((LEN / 2) as i64))).unwrap();
+    writer.write_all(&pt[LEN / 2..]).unwrap();
+
+    let (_, out) = writer.into_inner().unwrap();
+    assert!(out.into_inner() == ciphertext());
+}
+
+#[test]
+fn end_of_keystream() {
+    let mut cipher =
+        ctr::CtrBitsBE::<Aes128, cipher::consts::U8>::new(&KEY.into(), &[0; 16].into());
+    cipher.seek(255 * 16u32 + 10);
+
+    let mut reader = StreamReader::new(cipher, Cursor::new([0x55u8; 10]));
+    let mut buf = [0u8; 10];
+    reader.read_exact(&mut buf[..6]).unwrap();
+    let err = reader.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    // the unprocessed input read by the failed call isn't left in `buf`
+    assert_eq!(buf[..4], [0; 4]);
+
+    let (cipher, _) = reader.into_inner();
+    let mut writer = StreamWriter::new(cipher, Vec::new());
+    assert!(writer.write(&[0u8; 1]).is_err());
+    assert_eq!(writer.write(&[]).unwrap(), 0);
+}
+
+#[test]
+fn cfb8_round_trip() {
+    let cfb8 = || Aes128Cfb8::new(&KEY.into(), &NONCE.into());
+    let pt = plaintext();
+    let mut ct = pt.clone();
+    cfb8().encrypt(&mut ct);
+
+    let mut writer = EncryptWriter::new(cfb8(), Vec::new());
+    for chunk in pt.chunks(333) {
+        writer.write_all(chunk).unwrap();
+    }
+    assert!(writer.into_inner().unwrap().1 == ct);
+
+    let mut out = Vec::new();
+    EncryptReader::new(cfb8(), Trickle(&pt))
+        .read_to_end(&mut out)
+        .unwrap();
+    assert!(out == ct);
+
+    let mut out = Vec::new();
+    DecryptReader::new(cfb8(), Trickle(&ct))
+        .read_to_end(&mut out)
+        .unwrap();
+    assert!(out == pt);
+
+    let mut writer = DecryptWriter::new(
+        cfb8(),
+        Unreliable {
+            data: Vec::new(),
+            calls: 0,
+        },
+    );
+    let mut data = &ct[..];
+    while !data.is_empty() {
+        match writer.write(data) {
+            Ok(n) => data = &data[n..],
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::WouldBlock),
+        }
+    }
+    while writer.flush().is_err() {}
+    assert!(writer.get_ref().data == pt);
+}
+
+#[test]
+fn chacha20_round_trip() {
+    use chacha20::{ChaCha20, Key, Nonce};
+
+    let key = Key::from([0x42; 32]);
+    let nonce = Nonce::from([0x24; 12]);
+    let mut writer = StreamWriter::new(ChaCha20::new(&key, &nonce), Cursor::new(Vec::new()));
+    writer.write_all(&plaintext()).unwrap();
+    writer.seek(SeekFrom::Start(64)).unwrap();
+    writer.write_all(&plaintext()[64..128]).unwrap();
+    let (_, out) = writer.into_inner().unwrap();
+
+    let mut reader = StreamReader::new(ChaCha20::new(&key, &nonce), Cursor::new(out.into_inner()));
+    reader.seek(SeekFrom::Start(3)).unwrap();
+    let mut out = Vec::new();
+    reader.read_to_end(&mut out).unwrap();
+    assert!(out[..] == plaintext()[3..]);
+}
+
+/// Read the ciphertext back at a few positions through a seekable reader.
+///
+/// The positions are increasing, as HC-256 only supports seeking forward.
+fn check_seek<C: StreamCipher + StreamCipherSeek>(new: impl Fn() -> C) {
+    let pt = plaintext();
+    let mut ct = pt.clone();
+    new().apply_keystream(&mut ct);
+
+    let mut reader = StreamReader::new(new(), Cursor::new(&ct));
+    let mut buf = [0u8; 100];
+    for &pos in &[3, 200, 4095, 5000] {
+        reader.seek(SeekFrom::Start(pos)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        let pos = pos as usize;
+        assert_eq!(&buf[..], &pt[pos..pos + 100]);
+    }
+}
+
+#[test]
+fn seekable_ciphers() {
+    check_seek(ctr);
+    check_seek(|| chacha20::XChaCha20::new(&[0x42; 32].into(), &[0x24; 24].into()));
+    check_seek(|| salsa20::Salsa20::new(&[0x42; 32].into(), &[0x24; 8].into()));
+    check_seek(|| hc_256::Hc256::new(&[0x42; 32].into(), &[0x24; 32].into()));
+    check_seek(|| rabbit::Rabbit::new(&[0x42; 16].into(), &[0x24; 8].into()));
+    check_seek(|| ofb::Ofb::<Aes128>::new(&KEY.into(), &NONCE.into()));
+}
@@ -0,0 +1,213 @@
+//! Tests for the `tokio` adapters
+#![cfg(feature = "tokio")]
+// `tokio` itself requires a newer Rust than the rest of this crate
+#![allow(clippy::incompatible_msrv)]
+
+extern crate tokio_crate as tokio;
+
+use aes::Aes128;
+use cipher::{AsyncStreamCipher, NewCipher, StreamCipher};
+use cipher_io::tokio::{DecryptReader, EncryptWriter, StreamReader, StreamWriter};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{duplex, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+const KEY: [u8; 16] = *b"very secret key.";
+const NONCE: [u8; 16] = *b"and secret nonce";
+const LEN: usize = 20_000;
+
+fn ctr() -> Aes128Ctr {
+    Aes128Ctr::new(&KEY.into(), &NONCE.into())
+}
+
+fn plaintext() -> Vec<u8> {
+    (0..LEN).map(|i| (i % 251) as u8).collect()
+}
+
+/// Encrypt the plaintext through a writer and decrypt it through a reader
+/// connected by a small in-memory pipe, so both see partial and pending
+/// reads and writes.
+async fn round_trip<E, D>(writer: impl FnOnce(tokio::io::DuplexStream) -> E, reader: D)
+where
+    E: AsyncWrite + Unpin,
+    D: FnOnce(tokio::io::DuplexStream) -> Box<dyn AsyncRead + Unpin>,
+{
+    let (a, b) = duplex(61);
+    let mut writer = writer(a);
+    let mut reader = reader(b);
+
+    let pt = plaintext();
+    let write = async {
+        for chunk in pt.chunks(999) {
+            writer.write_all(chunk).await.unwrap();
+        }
+        writer.shutdown().await.unwrap();
+    };
+    let mut out = Vec::new();
+    let read = reader.read_to_end(&mut out);
+    let (_, res) = tokio::join!(write, read);
+    res.unwrap();
+    assert!(out == pt);
+}
+
+#[tokio::test]
+async fn ctr_round_trip() {
+    round_trip(
+        |w| StreamWriter::new(ctr(), w),
+        |r| Box::new(StreamReader::new(ctr(), r)),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn ctr_ciphertext() {
+    let (a, mut b) = duplex(61);
+    let mut writer = StreamWriter::new(ctr(), a);
+    let pt = plaintext();
+    let write = async {
+        writer.write_all(&pt).await.unwrap();
+        writer.shutdown().await.unwrap();
+    };
+    let mut out = Vec::new();
+    let (_, res) = tokio::join!(write, b.read_to_end(&mut out));
+    res.unwrap();
+
+    let mut ct = plaintext();
+    ctr().apply_keystream(&mut ct);
+    assert!(out == ct);
+}
+
+#[tokio::test]
+async fn chacha20_round_trip() {
+    use chacha20::{ChaCha20, Key, Nonce};
+    let new = || ChaCha20::new(&Key::from([0x42; 32]), &Nonce::from([0x24; 12]));
+    round_trip(
+        |w| StreamWriter::new(new(), w),
+        |r| Box::new(StreamReader::new(new(), r)),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn salsa20_round_trip() {
+    use salsa20::{Key, Nonce, Salsa20};
+    let new = || Salsa20::new(&Key::from([0x42; 32]), &Nonce::from([0x24; 8]));
+    round_trip(
+        |w| StreamWriter::new(new(), w),
+        |r| Box::new(StreamReader::new(new(), r)),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn cfb_round_trip() {
+    type Aes128Cfb = cfb_mode::Cfb<Aes128>;
+    let new = || Aes128Cfb::new(&KEY.into(), &NONCE.into());
+    round_trip(
+        |w| EncryptWriter::new(new(), w),
+        |r| Box::new(DecryptReader::new(new(), r)),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn cfb8_round_trip() {
+    type Aes128Cfb8 = cfb8::Cfb8<Aes128>;
+    let new = || Aes128Cfb8::new(&KEY.into(), &NONCE.into());
+    round_trip(
+        |w| EncryptWriter::new(new(), w),
+        |r| Box::new(DecryptReader::new(new(), r)),
+    )
+    .await;
+
+    let mut ct = plaintext();
+    new().encrypt(&mut ct);
+    let mut out = Vec::new();
+    DecryptReader::new(new(), &ct[..])
+        .read_to_end(&mut out)
+        .await
+        .unwrap();
+    assert!(out == plaintext());
+}
+
+/// Writer which accepts at most 7 bytes at a time, and every other call is
+/// pending or fails without accepting anything
+struct Unreliable {
+    data: Vec<u8>,
+    calls: usize,
+}
+
+impl AsyncWrite for Unreliable {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.calls += 1;
+        match self.calls % 4 {
+            0 => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            2 => Poll::Ready(Err(io::ErrorKind::Interrupted.into())),
+            _ => {
+                let n = std::cmp::min(buf.len(), 7);
+                self.data.extend_from_slice(&buf[..n]);
+                Poll::Ready(Ok(n))
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn pending_writes_keep_keystream_in_sync() {
+    let mut writer = StreamWriter::new(
+        ctr(),
+        Unreliable {
+            data: Vec::new(),
+            calls: 0,
+        },
+    );
+    for chunk in plaintext().chunks(100) {
+        writer.write_all(chunk).await.unwrap();
+    }
+    writer.flush().await.unwrap();
+
+    let mut ct = plaintext();
+    ctr().apply_keystream(&mut ct);
+    assert!(writer.get_ref().data == ct);
+}
+
+#[tokio::test]
+async fn end_of_keystream() {
+    use cipher::StreamCipherSeek;
+
+    let mut cipher =
+        ctr::CtrBitsBE::<Aes128, cipher::consts::U8>::new(&KEY.into(), &[0; 16].into());
+    cipher.seek(255 * 16u32 + 10);
+
+    let data = [0u8; 10];
+    let mut reader = StreamReader::new(cipher, &data[..]);
+    let mut buf = [0u8; 10];
+    let err = reader.read_exact(&mut buf).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+
+    let (cipher, _) = reader.into_inner();
+    let mut writer = StreamWriter::new(cipher, Vec::new());
+    let err = writer.write(&[0u8; 7]).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert!(writer.get_ref().is_empty());
+}
@@ -0,0 +1,126 @@
+//! Development-related functionality
+
+use crate::StateError;
+
+/// Length of the messages processed by the state tests
+const LEN: usize = 200;
+
+/// Keystream positions at which the state tests take a state, around the
+/// word and block boundaries of the ciphers
+const POSITIONS: [usize; 16] = [0, 1, 3, 4, 5, 7, 8, 9, 15, 16, 17, 31, 63, 64, 65, 130];
+
+/// Test the `to_bytes`, `from_bytes` and `state_len` methods of a stream
+/// cipher
+///
+/// A state without the key is restored by passing the key to `from_bytes`,
+/// one with the key without it. The restored cipher must serialize to the
+/// same state and continue the output of `process` where the original one
+/// stopped. Changing the version, the flags or the length of a state must
+/// make it invalid.
+#[macro_export]
+#[cfg_attr(docsrs, doc(cfg(feature = "dev")))]
+macro_rules! stream_state_test {
+    ($name:ident, $cipher:ty) => {
+        #[test]
+        fn $name() {
+            use cipher::StreamCipher;
+
+            $crate::__check_state!($cipher, |cipher: &mut $cipher, data: &mut [u8]| {
+                cipher.apply_keystream(data)
+            });
+        }
+    };
+}
+
+/// Test the `to_bytes`, `from_bytes` and `state_len` methods of an
+/// asynchronous stream cipher, see [`stream_state_test!`]
+#[macro_export]
+#[cfg_attr(docsrs, doc(cfg(feature = "dev")))]
+macro_rules! stream_async_state_test {
+    ($name:ident, $cipher:ty) => {
+        #[test]
+        fn $name() {
+            use cipher::AsyncStreamCipher;
+
+            $crate::__check_state!($cipher, |cipher: &mut $cipher, data: &mut [u8]| {
+                cipher.encrypt(data)
+            });
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __check_state {
+    ($cipher:ty, $process:expr) => {{
+        use cipher::{CipherKey, NewCipher, Nonce};
+
+        let mut key = CipherKey::<$cipher>::default();
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = i as u8 + 1;
+        }
+        let mut nonce = Nonce::<$cipher>::default();
+        for (i, b) in nonce.iter_mut().enumerate() {
+            *b = 0x80 | i as u8;
+        }
+        let key_opt = |with_key: bool| if with_key { Some(&key) } else { None };
+
+        $crate::check_state(
+            || <$cipher>::new(&key, &nonce),
+            $process,
+            |cipher: &$cipher, with_key, buf| {
+                cipher.to_bytes(key_opt(with_key), buf).map(|s| s.len())
+            },
+            |bytes, with_key| <$cipher>::from_bytes(bytes, key_opt(with_key)),
+            <$cipher>::state_len,
+        );
+    }};
+}
+
+#[doc(hidden)]
+pub fn check_state<C>(
+    new: impl Fn() -> C,
+    process: impl Fn(&mut C, &mut [u8]),
+    to_bytes: impl Fn(&C, bool, &mut [u8]) -> Result<usize, StateError>,
+    from_bytes: impl Fn(&[u8], bool) -> Result<C, StateError>,
+    state_len: impl Fn(bool) -> usize,
+) {
+    let mut expected = [0u8; LEN];
+    process(&mut new(), &mut expected);
+
+    let mut buf = [0u8; 256];
+    let mut other = [0u8; 256];
+    for &pos in POSITIONS.iter() {
+        let mut cipher = new();
+        process(&mut cipher, &mut [0u8; LEN][..pos]);
+
+        for &with_key in [false, true].iter() {
+            let len = to_bytes(&cipher, with_key, &mut buf).unwrap();
+            assert_eq!(len, state_len(with_key));
+            assert_eq!(
+                to_bytes(&cipher, with_key, &mut other[..len - 1]),
+                Err(StateError)
+            );
+            let state = &buf[..len];
+
+            assert_eq!(from_bytes(state, false).is_ok(), with_key);
+            let mut restored = from_bytes(state, !with_key).unwrap();
+            let restored_len = to_bytes(&restored, with_key, &mut other).unwrap();
+            assert_eq!(&other[..restored_len], state, "position {}", pos);
+            let mut out = [0u8; LEN];
+            process(&mut restored, &mut out[pos..]);
+            assert_eq!(&out[pos..], &expected[pos..], "position {}", pos);
+
+            let mut rejected = |i: usize, f: fn(u8) -> u8| {
+                other[..len].copy_from_slice(state);
+                other[i] = f(other[i]);
+                from_bytes(&other[..len], true).is_err()
+            };
+            assert!(rejected(0, |v| v.wrapping_add(1)), "version");
+            assert!(rejected(1, |f| f | 0x80), "unknown flag");
+            assert!(rejected(1, |f| f ^ 1), "key flag");
+            assert!(from_bytes(&state[..len - 1], true).is_err());
+            assert!(from_bytes(&buf[..len + 1], true).is_err());
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Serialization format of the state of the stream ciphers in the
+//! [RustCrypto/stream-ciphers](https://github.com/RustCrypto/stream-ciphers)
+//! repository, used by their `to_bytes` and `from_bytes` methods.
+//!
+//! A serialized state is a version byte, a flags byte, the key if its least
+//! significant flag is set, then a body whose layout is specific to the
+//! cipher. Seekable ciphers whose state is just the nonce and the keystream
+//! position use the body written by [`write_seekable`].
+//!
+//! The key is only included if the caller passes it, as the ciphers don't
+//! retain it. The body holds enough to regenerate the keystream from the
+//! current position, or the keystream itself, so it has to be kept secret
+//! even if the key is not included.
+//!
+//! The `dev` feature provides the [`stream_state_test!`] and
+//! [`stream_async_state_test!`] macros which test the methods of a cipher.
+
+#![no_std]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/RustCrypto/meta/master/logo.svg",
+    html_favicon_url = "https://raw.githubusercontent.com/RustCrypto/meta/master/logo.svg"
+)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+#![forbid(unsafe_code)]
+#![warn(missing_docs, rust_2018_idioms)]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "dev")]
+mod dev;
+
+#[cfg(feature = "dev")]
+#[doc(hidden)]
+pub use dev::check_state;
+
+use core::{convert::TryInto, fmt};
+
+/// Version of the serialized state format
+const VERSION: u8 = 1;
+
+/// Flag: the key is included. The other bits of the flags byte are available
+/// to the ciphers.
+const FLAG_KEY: u8 = 1;
+
+/// Length of a keystream position encoded by [`write_position`]
+pub const POSITION_LEN: usize = 8 + 1;
+
+/// Error returned when a serialized cipher state can't be written or
+/// restored.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct StateError;
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid cipher state")
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for StateError {}
+
+/// Length of a serialized state with a key of `key_len` bytes if `with_key`
+/// is set and a body of `body_len` bytes.
+pub fn state_len(key_len: usize, body_len: usize, with_key: bool) -> usize {
+    let key_len = if with_key { key_len } else { 0 };
+    2 + key_len + body_len
+}
+
+/// Serialize a state into `buf`, returning the written prefix of it.
+///
+/// `flags` are the cipher-specific flags, which must leave the least
+/// significant bit clear; it's set if `key` is given. `body` is called with the
+/// `body_len` bytes following the key to fill in.
+pub fn write<'a>(
+    buf: &'a mut [u8],
+    key: Option<&[u8]>,
+    flags: u8,
+    body_len: usize,
+    body: impl FnOnce(&mut [u8]) -> Result<(), StateError>,
+) -> Result<&'a [u8], StateError> {
+    debug_assert_eq!(flags & FLAG_KEY, 0);
+    let key_len = key.map_or(0, <[u8]>::len);
+    let buf = buf
+        .get_mut(..state_len(key_len, body_len, key.is_some()))
+        .ok_or(StateError)?;
+    let (header, rest) = buf.split_at_mut(2);
+    let (key_buf, body_buf) = rest.split_at_mut(key_len);
+
+    header[0] = VERSION;
+    header[1] = flags;
+    if let Some(key) = key {
+        header[1] |= FLAG_KEY;
+        key_buf.copy_from_slice(key);
+    }
+    body(body_buf)?;
+    Ok(buf)
+}
+
+/// Parsed serialized state
+#[derive(Copy, Clone, Debug)]
+pub struct State<'a> {
+    key: Option<&'a [u8]>,
+    flags: u8,
+    body: &'a [u8],
+}
+
+impl<'a> State<'a> {
+    /// Parse a serialized state with a key of `key_len` bytes, if included,
+    /// and a body of `body_len` bytes.
+    ///
+    /// `flags` are the cipher-specific flags which may be set in the state.
+    pub fn read(
+        bytes: &'a [u8],
+        key_len: usize,
+        body_len: usize,
+        flags: u8,
+    ) -> Result<Self, StateError> {
+        let state_flags = *bytes.get(1).ok_or(StateError)?;
+        let with_key = state_flags & FLAG_KEY != 0;
+        if bytes[0] != VERSION
+            || state_flags & !(flags | FLAG_KEY) != 0
+            || bytes.len() != state_len(key_len, body_len, with_key)
+        {
+            return Err(StateError);
+        }
+
+        let (key, body) = if with_key {
+            let (key, body) = bytes[2..].split_at(key_len);
+            (Some(key), body)
+        } else {
+            (None, &bytes[2..])
+        };
+        Ok(Self {
+            key,
+            flags: state_flags & !FLAG_KEY,
+            body,
+        })
+    }
+
+    /// Key to restore the cipher with: `key` if given, otherwise the key
+    /// included in the state.
+    ///
+    /// Returns [`StateError`] if neither is available.
+    pub fn key(&self, key: Option<&'a [u8]>) -> Result<&'a [u8], StateError> {
+        key.or(self.key).ok_or(StateError)
+    }
+
+    /// Cipher-specific flags set in the state
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Cipher-specific body of the state
+    pub fn body(&self) -> &'a [u8] {
+        self.body
+    }
+}
+
+/// Encode the keystream position `pos` of a cipher with blocks of
+/// `block_size` bytes into the first [`POSITION_LEN`] bytes of `buf`: the
+/// block number as a little endian `u64`, then the byte position within the
+/// block.
+///
+/// Returns [`StateError`] if the block number doesn't fit in a `u64`.
+pub fn write_position(buf: &mut [u8], pos: u128, block_size: u8) -> Result<(), StateError> {
+    let block: u64 = (pos / block_size as u128)
+        .try_into()
+        .map_err(|_| StateError)?;
+    buf[..8].copy_from_slice(&block.to_le_bytes());
+    buf[8] = (pos % block_size as u128) as u8;
+    Ok(())
+}
+
+/// Decode a keystream position encoded by [`write_position`] from the first
+/// [`POSITION_LEN`] bytes of `bytes`.
+///
+/// Returns [`StateError`] if the byte position is not within a block.
+pub fn read_position(bytes: &[u8], block_size: u8) -> Result<u128, StateError> {
+    let block = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let byte = bytes[8];
+    if byte >= block_size {
+        return Err(StateError);
+    }
+    Ok(block as u128 * block_size as u128 + byte as u128)
+}
+
+/// Serialize the state of a seekable cipher into `buf`, returning the written
+/// prefix of it.
+///
+/// The body is `nonce` followed by the keystream position `pos` of a cipher
+/// with blocks of `block_size` bytes, encoded by [`write_position`].
+pub fn write_seekable<'a>(
+    buf: &'a mut [u8],
+    key: Option<&[u8]>,
+    nonce: &[u8],
+    pos: u128,
+    block_size: u8,
+) -> Result<&'a [u8], StateError> {
+    write(buf, key, 0, nonce.len() + POSITION_LEN, |body| {
+        let (nonce_buf, pos_buf) = body.split_at_mut(nonce.len());
+        nonce_buf.copy_from_slice(nonce);
+        write_position(pos_buf, pos, block_size)
+    })
+}
+
+/// Parsed state serialized by [`write_seekable`]
+#[derive(Copy, Clone, Debug)]
+pub struct SeekableState<'a> {
+    state: State<'a>,
+    nonce_len: usize,
+    pos: u128,
+}
+
+impl<'a> SeekableState<'a> {
+    /// Parse a state serialized by [`write_seekable`] with a key of `key_len`
+    /// bytes, if included, a nonce of `nonce_len` bytes and blocks of
+    /// `block_size` bytes.
+    pub fn read(
+        bytes: &'a [u8],
+        key_len: usize,
+        nonce_len: usize,
+        block_size: u8,
+    ) -> Result<Self, StateError> {
+        let state = State::read(bytes, key_len, nonce_len + POSITION_LEN, 0)?;
+        let pos = read_position(&state.body()[nonce_len..], block_size)?;
+        Ok(Self {
+            state,
+            nonce_len,
+            pos,
+        })
+    }
+
+    /// Key to restore the cipher with, see [`State::key`].
+    pub fn key(&self, key: Option<&'a [u8]>) -> Result<&'a [u8], StateError> {
+        self.state.key(key)
+    }
+
+    /// Nonce the cipher was initialized with
+    pub fn nonce(&self) -> &'a [u8] {
+        &self.state.body()[..self.nonce_len]
+    }
+
+    /// Keystream position
+    pub fn pos(&self) -> u128 {
+        self.pos
+    }
+}
@@ -0,0 +1,80 @@
+use cipher_state::{
+    read_position, state_len, write, write_position, write_seekable, SeekableState, State,
+    StateError,
+};
+
+const KEY: [u8; 4] = [1, 2, 3, 4];
+const BODY: [u8; 3] = [5, 6, 7];
+
+fn fill(body: &mut [u8]) -> Result<(), StateError> {
+    body.copy_from_slice(&BODY);
+    Ok(())
+}
+
+#[test]
+fn round_trip() {
+    let mut buf = [0u8; 16];
+
+    let state = write(&mut buf, Some(&KEY), 2, BODY.len(), fill).unwrap();
+    assert_eq!(state, &[1, 3, 1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(state.len(), state_len(KEY.len(), BODY.len(), true));
+    let state = State::read(state, KEY.len(), BODY.len(), 2).unwrap();
+    assert_eq!(state.key(None), Ok(&KEY[..]));
+    assert_eq!(state.key(Some(&[9; 4])), Ok(&[9; 4][..]));
+    assert_eq!(state.flags(), 2);
+    assert_eq!(state.body(), &BODY);
+
+    let state = write(&mut buf, None, 0, BODY.len(), fill).unwrap();
+    assert_eq!(state, &[1, 0, 5, 6, 7]);
+    let state = State::read(state, KEY.len(), BODY.len(), 0).unwrap();
+    assert_eq!(state.key(None), Err(StateError));
+    assert_eq!(state.key(Some(&KEY)), Ok(&KEY[..]));
+    assert_eq!(state.flags(), 0);
+    assert_eq!(state.body(), &BODY);
+}
+
+#[test]
+fn rejects_malformed() {
+    assert!(write(&mut [0u8; 4], None, 0, BODY.len(), fill).is_err());
+    assert!(write(&mut [0u8; 8], None, 0, BODY.len(), |_| Err(StateError)).is_err());
+
+    let read = |bytes: &[u8]| State::read(bytes, KEY.len(), BODY.len(), 2).map(|_| ());
+    assert!(read(&[1, 0, 5, 6, 7]).is_ok());
+    assert!(read(&[]).is_err());
+    assert!(read(&[1]).is_err());
+    assert!(read(&[2, 0, 5, 6, 7]).is_err());
+    assert!(read(&[1, 4, 5, 6, 7]).is_err());
+    assert!(read(&[1, 0, 5, 6]).is_err());
+    assert!(read(&[1, 1, 5, 6, 7]).is_err());
+}
+
+#[test]
+fn position() {
+    let mut buf = [0u8; 9];
+    write_position(&mut buf, 64 * 5 + 3, 64).unwrap();
+    assert_eq!(buf, [5, 0, 0, 0, 0, 0, 0, 0, 3]);
+    assert_eq!(read_position(&buf, 64), Ok(64 * 5 + 3));
+
+    let max = u64::max_value() as u128 * 64 + 63;
+    write_position(&mut buf, max, 64).unwrap();
+    assert_eq!(read_position(&buf, 64), Ok(max));
+    assert!(write_position(&mut buf, max + 1, 64).is_err());
+
+    buf[8] = 64;
+    assert!(read_position(&buf, 64).is_err());
+}
+
+#[test]
+fn seekable() {
+    let mut buf = [0u8; 32];
+    let state = write_seekable(&mut buf, Some(&KEY), &BODY, 64 * 5 + 3, 64).unwrap();
+    assert_eq!(state.len(), state_len(KEY.len(), BODY.len() + 9, true));
+    let state = SeekableState::read(state, KEY.len(), BODY.len(), 64).unwrap();
+    assert_eq!(state.key(None), Ok(&KEY[..]));
+    assert_eq!(state.nonce(), &BODY);
+    assert_eq!(state.pos(), 64 * 5 + 3);
+
+    let len = write_seekable(&mut buf, None, &BODY, 63, 64).unwrap().len();
+    buf[len - 1] = 64;
+    assert!(SeekableState::read(&buf[..len], KEY.len(), BODY.len(), 64).is_err());
+}
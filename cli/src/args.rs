@@ -0,0 +1,175 @@
+//! Command-line argument parsing.
+
+use crate::{cipher::Mode, Error};
+use std::{ffi::OsString, fs, path::PathBuf};
+
+/// Usage message printed by `--help`
+pub const USAGE: &str = "\
+Encrypt, decrypt or print the keystream of a stream cipher
+
+USAGE:
+    stream-ciphers <COMMAND> [OPTIONS]
+
+COMMANDS:
+    encrypt      Encrypt the input and write the ciphertext to the output
+    decrypt      Decrypt the input and write the plaintext to the output
+    keystream    Write the raw keystream to the output
+    ciphers      List the supported ciphers
+
+OPTIONS:
+    -c, --cipher <NAME>        Cipher to use, e.g. `chacha20` or `aes-128-ctr`
+    -k, --key <HEX>            Key as a hex string, which other users can see
+                               in the process list
+        --key-file <PATH>      Read the raw key bytes from a file, the
+                               recommended way to pass the key
+    -n, --nonce <HEX>          Nonce or IV as a hex string
+        --nonce-file <PATH>    Read the raw nonce or IV bytes from a file
+        --offset <N>           Start at byte N of the stream: the input is
+                               read from that position and the keystream is
+                               sought to it, which allows random access.
+                               HC-256, Rabbit and OFB modes regenerate the
+                               keystream up to the offset, so seeking takes
+                               time proportional to N, as does skipping N
+                               bytes of stdin
+        --length <N>           Process at most N bytes, required by `keystream`
+    -i, --input <PATH>         Read from a file instead of stdin
+    -o, --output <PATH>        Write to a file instead of stdout
+    -h, --help                 Print this message
+
+Numbers may be given in decimal or as hex prefixed with `0x`. CFB modes don't
+have a keystream independent of the data, so they don't support `keystream`
+or `--offset`.
+";
+
+/// What to run
+#[derive(Debug)]
+pub enum Command {
+    /// Process data with a cipher
+    Run(Args),
+    /// List the supported ciphers
+    Ciphers,
+    /// Print the usage message
+    Help,
+}
+
+/// Arguments of the `encrypt`, `decrypt` and `keystream` commands
+#[derive(Debug)]
+pub struct Args {
+    pub mode: Mode,
+    pub cipher: String,
+    pub key: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub offset: u64,
+    pub length: Option<u64>,
+    pub input: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+}
+
+/// Parse the arguments following the program name
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Command, Error> {
+    let mut args = args.into_iter();
+    let mode = match args.next().as_ref().and_then(|cmd| cmd.to_str()) {
+        Some("encrypt") => Mode::Encrypt,
+        Some("decrypt") => Mode::Decrypt,
+        Some("keystream") => Mode::Keystream,
+        Some("ciphers") => return Ok(Command::Ciphers),
+        Some("-h") | Some("--help") | Some("help") | None => return Ok(Command::Help),
+        Some(cmd) => return Err(Error::new(format!("unknown command `{}`", cmd))),
+    };
+
+    let mut cipher = None;
+    let mut key = None;
+    let mut nonce = None;
+    let mut offset = 0;
+    let mut length = None;
+    let mut input = None;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| Error::new(format!("unknown option `{}`", arg.to_string_lossy())))?;
+
+        // support both `--opt value` and `--opt=value`
+        let (name, inline) = match arg.find('=') {
+            Some(i) if arg.starts_with("--") => (&arg[..i], Some(OsString::from(&arg[i + 1..]))),
+            _ => (&arg[..], None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| Error::new(format!("`{}` requires a value", name)))
+        };
+
+        match name {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-c" | "--cipher" => cipher = Some(string(value()?)?),
+            "-k" | "--key" => key = Some(hex(&string(value()?)?)?),
+            "--key-file" => key = Some(fs::read(value()?)?),
+            "-n" | "--nonce" | "--iv" => nonce = Some(hex(&string(value()?)?)?),
+            "--nonce-file" | "--iv-file" => nonce = Some(fs::read(value()?)?),
+            "--offset" => offset = number(&string(value()?)?)?,
+            "--length" => length = Some(number(&string(value()?)?)?),
+            "-i" | "--input" => input = Some(PathBuf::from(value()?)),
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            _ => return Err(Error::new(format!("unknown option `{}`", name))),
+        }
+    }
+
+    if mode == Mode::Keystream && length.is_none() {
+        return Err(Error::new("`keystream` requires `--length`"));
+    }
+
+    Ok(Command::Run(Args {
+        mode,
+        cipher: cipher.ok_or_else(|| Error::new("missing `--cipher`"))?,
+        key: key.ok_or_else(|| Error::new("missing `--key` or `--key-file`"))?,
+        nonce: nonce.ok_or_else(|| Error::new("missing `--nonce` or `--nonce-file`"))?,
+        offset,
+        length,
+        input,
+        output,
+    }))
+}
+
+fn string(value: OsString) -> Result<String, Error> {
+    value
+        .into_string()
+        .map_err(|value| Error::new(format!("invalid value `{}`", value.to_string_lossy())))
+}
+
+/// Decode a hex string
+///
+/// Errors only report the length or the position of the invalid character,
+/// as the string may be a key.
+fn hex(s: &str) -> Result<Vec<u8>, Error> {
+    if s.len() % 2 != 0 {
+        return Err(Error::new(format!(
+            "invalid hex string: odd length {}",
+            s.len()
+        )));
+    }
+    let digit = |i: usize| {
+        (s.as_bytes()[i] as char).to_digit(16).ok_or_else(|| {
+            Error::new(format!(
+                "invalid hex string: bad character at position {}",
+                i + 1
+            ))
+        })
+    };
+    (0..s.len())
+        .step_by(2)
+        .map(|i| Ok((digit(i)? << 4 | digit(i + 1)?) as u8))
+        .collect()
+}
+
+/// Parse a decimal or `0x`-prefixed hex number
+fn number(s: &str) -> Result<u64, Error> {
+    let res = if s.starts_with("0x") || s.starts_with("0X") {
+        u64::from_str_radix(&s[2..], 16)
+    } else {
+        s.parse()
+    };
+    res.map_err(|_| Error::new(format!("invalid number `{}`", s)))
+}
@@ -0,0 +1,211 @@
+//! Object-safe interface over the supported ciphers.
+
+use crate::Error;
+use aes::{Aes128, Aes192, Aes256};
+use cipher::{
+    errors::LoopError, generic_array::typenum::Unsigned, AsyncStreamCipher, NewCipher,
+    StreamCipher, StreamCipherSeek,
+};
+
+/// What to do with the data passed to [`Cipher::apply`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// Encrypt the data
+    Encrypt,
+    /// Decrypt the data
+    Decrypt,
+    /// Replace the data with raw keystream
+    Keystream,
+}
+
+/// Cipher which can be driven without knowing its concrete type
+pub trait Cipher {
+    /// Process `data` in place according to `mode`.
+    fn apply(&mut self, mode: Mode, data: &mut [u8]) -> Result<(), Error>;
+
+    /// Move to byte `pos` of the stream.
+    fn seek(&mut self, pos: u64) -> Result<(), Error>;
+}
+
+/// Output of raw keystream, which the ciphers provide as inherent methods
+trait WriteKeystream {
+    /// Overwrite `out` with keystream.
+    fn write_keystream(&mut self, out: &mut [u8]) -> Result<(), LoopError>;
+}
+
+/// Implement [`WriteKeystream`] with the `try_write_keystream` method of
+/// ciphers whose keystream can end
+macro_rules! impl_try_write_keystream {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl WriteKeystream for $ty {
+                fn write_keystream(&mut self, out: &mut [u8]) -> Result<(), LoopError> {
+                    self.try_write_keystream(out)
+                }
+            }
+        )*
+    };
+}
+
+/// Implement [`WriteKeystream`] with the `write_keystream` method of ciphers
+/// whose keystream doesn't end
+macro_rules! impl_write_keystream {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl WriteKeystream for $ty {
+                fn write_keystream(&mut self, out: &mut [u8]) -> Result<(), LoopError> {
+                    <$ty>::write_keystream(self, out);
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_try_write_keystream!(
+    chacha20::ChaCha20,
+    chacha20::ChaCha12,
+    chacha20::ChaCha8,
+    chacha20::ChaCha20Legacy,
+    chacha20::XChaCha20,
+    chacha20::XChaCha12,
+    chacha20::XChaCha8,
+    salsa20::Salsa20,
+    salsa20::Salsa12,
+    salsa20::Salsa8,
+    salsa20::XSalsa20,
+    rabbit::Rabbit,
+    ctr::Ctr128BE<Aes128>,
+    ctr::Ctr128BE<Aes192>,
+    ctr::Ctr128BE<Aes256>,
+);
+
+impl_write_keystream!(
+    hc_256::Hc256,
+    ofb::Ofb<Aes128>,
+    ofb::Ofb<Aes192>,
+    ofb::Ofb<Aes256>,
+);
+
+/// Synchronous stream cipher, whose keystream doesn't depend on the data
+struct Sync<C>(C);
+
+impl<C: StreamCipher + StreamCipherSeek + WriteKeystream> Cipher for Sync<C> {
+    fn apply(&mut self, mode: Mode, data: &mut [u8]) -> Result<(), Error> {
+        match mode {
+            Mode::Keystream => self.0.write_keystream(data),
+            _ => self.0.try_apply_keystream(data),
+        }
+        .map_err(|_| Error::new("reached the end of the keystream"))
+    }
+
+    fn seek(&mut self, pos: u64) -> Result<(), Error> {
+        self.0
+            .try_seek(pos)
+            .map_err(|_| Error::new("offset is outside of the keystream"))
+    }
+}
+
+/// Self-synchronizing mode, whose keystream depends on the ciphertext
+struct SelfSync<C>(C);
+
+impl<C: AsyncStreamCipher> Cipher for SelfSync<C> {
+    fn apply(&mut self, mode: Mode, data: &mut [u8]) -> Result<(), Error> {
+        match mode {
+            Mode::Encrypt => self.0.encrypt(data),
+            Mode::Decrypt => self.0.decrypt(data),
+            Mode::Keystream => {
+                return Err(Error::new(
+                    "CFB modes have no keystream independent of the data",
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn seek(&mut self, pos: u64) -> Result<(), Error> {
+        if pos == 0 {
+            Ok(())
+        } else {
+            Err(Error::new("CFB modes don't support offsets"))
+        }
+    }
+}
+
+/// Create a cipher of type `C`, checking the key and nonce lengths
+fn new<C: NewCipher>(key: &[u8], nonce: &[u8]) -> Result<C, Error> {
+    let (key_len, nonce_len) = (C::KeySize::USIZE, C::NonceSize::USIZE);
+    if key.len() != key_len {
+        return Err(Error::new(format!(
+            "expected a {}-byte key, got {} bytes",
+            key_len,
+            key.len()
+        )));
+    }
+    if nonce.len() != nonce_len {
+        return Err(Error::new(format!(
+            "expected a {}-byte nonce, got {} bytes",
+            nonce_len,
+            nonce.len()
+        )));
+    }
+    Ok(C::new(key.into(), nonce.into()))
+}
+
+fn sync<C>(key: &[u8], nonce: &[u8]) -> Result<Box<dyn Cipher>, Error>
+where
+    C: NewCipher + StreamCipher + StreamCipherSeek + WriteKeystream + 'static,
+{
+    Ok(Box::new(Sync(new::<C>(key, nonce)?)))
+}
+
+fn self_sync<C>(key: &[u8], nonce: &[u8]) -> Result<Box<dyn Cipher>, Error>
+where
+    C: NewCipher + AsyncStreamCipher + 'static,
+{
+    Ok(Box::new(SelfSync(new::<C>(key, nonce)?)))
+}
+
+/// Constructor of a cipher from a key and a nonce
+type Constructor = fn(&[u8], &[u8]) -> Result<Box<dyn Cipher>, Error>;
+
+/// Names of the supported ciphers and their constructors
+pub const CIPHERS: &[(&str, Constructor)] = &[
+    ("chacha20", sync::<chacha20::ChaCha20>),
+    ("chacha12", sync::<chacha20::ChaCha12>),
+    ("chacha8", sync::<chacha20::ChaCha8>),
+    ("chacha20-legacy", sync::<chacha20::ChaCha20Legacy>),
+    ("xchacha20", sync::<chacha20::XChaCha20>),
+    ("xchacha12", sync::<chacha20::XChaCha12>),
+    ("xchacha8", sync::<chacha20::XChaCha8>),
+    ("salsa20", sync::<salsa20::Salsa20>),
+    ("salsa12", sync::<salsa20::Salsa12>),
+    ("salsa8", sync::<salsa20::Salsa8>),
+    ("xsalsa20", sync::<salsa20::XSalsa20>),
+    ("hc-256", sync::<hc_256::Hc256>),
+    ("rabbit", sync::<rabbit::Rabbit>),
+    ("aes-128-ctr", sync::<ctr::Ctr128BE<Aes128>>),
+    ("aes-192-ctr", sync::<ctr::Ctr128BE<Aes192>>),
+    ("aes-256-ctr", sync::<ctr::Ctr128BE<Aes256>>),
+    ("aes-128-ofb", sync::<ofb::Ofb<Aes128>>),
+    ("aes-192-ofb", sync::<ofb::Ofb<Aes192>>),
+    ("aes-256-ofb", sync::<ofb::Ofb<Aes256>>),
+    ("aes-128-cfb", self_sync::<cfb_mode::Cfb<Aes128>>),
+    ("aes-192-cfb", self_sync::<cfb_mode::Cfb<Aes192>>),
+    ("aes-256-cfb", self_sync::<cfb_mode::Cfb<Aes256>>),
+    ("aes-128-cfb8", self_sync::<cfb8::Cfb8<Aes128>>),
+    ("aes-192-cfb8", self_sync::<cfb8::Cfb8<Aes192>>),
+    ("aes-256-cfb8", self_sync::<cfb8::Cfb8<Aes256>>),
+];
+
+/// Create the cipher called `name`
+pub fn from_name(name: &str, key: &[u8], nonce: &[u8]) -> Result<Box<dyn Cipher>, Error> {
+    let name = name.to_ascii_lowercase();
+    match CIPHERS.iter().find(|(n, _)| *n == name) {
+        Some((_, new)) => new(key, nonce),
+        None => Err(Error::new(format!(
+            "unknown cipher `{}`, run `stream-ciphers ciphers` for a list",
+            name
+        ))),
+    }
+}
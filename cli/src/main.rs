@@ -0,0 +1,132 @@
+//! Command-line tool for encrypting and decrypting files with the stream
+//! ciphers of this repository, and for printing their raw keystream.
+//!
+//! Run `stream-ciphers --help` for usage.
+
+#![forbid(unsafe_code)]
+#![warn(rust_2018_idioms)]
+
+mod args;
+mod cipher;
+
+use crate::{
+    args::{Args, Command},
+    cipher::Mode,
+};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    process,
+};
+use zeroize::Zeroize;
+
+/// Size of the chunks the data is processed in
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Error reported to the user
+#[derive(Debug)]
+pub enum Error {
+    /// I/O error
+    Io(io::Error),
+    /// Any other error
+    Msg(String),
+}
+
+impl Error {
+    fn new(msg: impl Into<String>) -> Self {
+        Error::Msg(msg.into())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => err.fmt(f),
+            Error::Msg(msg) => f.write_str(msg),
+        }
+    }
+}
+
+fn main() {
+    match run() {
+        Ok(()) => {}
+        // e.g. piped into `head`
+        Err(Error::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(err) => {
+            eprintln!("stream-ciphers: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut args = match args::parse(std::env::args_os().skip(1))? {
+        Command::Run(args) => args,
+        Command::Ciphers => {
+            for (name, _) in cipher::CIPHERS {
+                println!("{}", name);
+            }
+            return Ok(());
+        }
+        Command::Help => {
+            print!("{}", args::USAGE);
+            return Ok(());
+        }
+    };
+
+    // the key isn't needed once the cipher is initialized
+    let cipher = cipher::from_name(&args.cipher, &args.key, &args.nonce);
+    args.key.zeroize();
+    let mut cipher = cipher?;
+    cipher.seek(args.offset)?;
+
+    let stdout = io::stdout();
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(stdout.lock()),
+    };
+
+    let stdin = io::stdin();
+    let input: Box<dyn Read> = match args.mode {
+        Mode::Keystream => Box::new(io::repeat(0)),
+        _ => open_input(&args, &stdin)?,
+    };
+    let mut input = input.take(args.length.unwrap_or(u64::MAX));
+
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        cipher.apply(args.mode, &mut buf[..n])?;
+        output.write_all(&buf[..n])?;
+    }
+    output.flush()?;
+    Ok(())
+}
+
+/// Open the input, positioned at the offset
+fn open_input<'a>(args: &Args, stdin: &'a io::Stdin) -> Result<Box<dyn Read + 'a>, Error> {
+    match &args.input {
+        Some(path) => {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(args.offset))?;
+            Ok(Box::new(file))
+        }
+        None => {
+            let mut stdin = stdin.lock();
+            io::copy(&mut (&mut stdin).take(args.offset), &mut io::sink())?;
+            Ok(Box::new(stdin))
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Tests running the `stream-ciphers` binary
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+const CHACHA_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+const CHACHA_NONCE: &str = "000000090000004a00000000";
+
+/// Run the binary with `args`, passing `stdin` to it
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stream-ciphers"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Run the binary with `args`, expecting it to succeed
+fn ok(args: &[&str], stdin: &[u8]) -> Vec<u8> {
+    let out = run(args, stdin);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    out.stdout
+}
+
+/// Run the binary with the whitespace-separated `args`, expecting it to fail
+/// with a message containing `msg`
+fn err(args: &str, msg: &str) {
+    let args: Vec<_> = args.split_whitespace().collect();
+    let out = run(&args, &[]);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains(msg), "{}", stderr);
+}
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn tmp(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("stream-ciphers-{}-{}", std::process::id(), name))
+}
+
+/// RFC 8439 Section 2.3.2: the block with counter 1 is at offset 64
+#[test]
+fn chacha20_keystream() {
+    let args = [
+        "keystream",
+        "-c",
+        "chacha20",
+        "-k",
+        CHACHA_KEY,
+        "-n",
+        CHACHA_NONCE,
+        "--offset",
+        "0x40",
+        "--length",
+        "16",
+    ];
+    assert_eq!(ok(&args, &[]), hex("10f1e7e4d13b5915500fdd1fa32071c4"));
+}
+
+/// NIST SP 800-38A F.5.1
+#[test]
+fn aes_128_ctr_encrypt() {
+    let args = [
+        "encrypt",
+        "--cipher=aes-128-ctr",
+        "--key=2b7e151628aed2a6abf7158809cf4f3c",
+        "--iv=f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff",
+    ];
+    let pt = hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+    let ct = hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff");
+    assert_eq!(ok(&args, &pt), ct);
+}
+
+/// NIST SP 800-38A F.4.1: the output blocks of AES-128-OFB
+#[test]
+fn aes_128_ofb_keystream() {
+    let args = [
+        "keystream",
+        "-c",
+        "aes-128-ofb",
+        "-k",
+        "2b7e151628aed2a6abf7158809cf4f3c",
+        "--iv",
+        "000102030405060708090a0b0c0d0e0f",
+        "--length",
+        "32",
+    ];
+    let ks = hex("50fe67cc996d32b6da0937e99bafec60d9a4dada0892239f6b8b3d7680e15674");
+    assert_eq!(ok(&args, &[]), ks);
+}
+
+#[test]
+fn round_trip_files() {
+    let pt: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+    let (pt_path, ct_path) = (tmp("round-trip.pt"), tmp("round-trip.ct"));
+    fs::write(&pt_path, &pt).unwrap();
+    let key = "42".repeat(32);
+
+    for &cipher in &["xchacha20", "xsalsa20", "hc-256", "aes-256-cfb8"] {
+        let nonce = match cipher {
+            "hc-256" => "24".repeat(32),
+            "aes-256-cfb8" => "24".repeat(16),
+            _ => "24".repeat(24),
+        };
+        let common = ["-c", cipher, "-k", &key, "-n", &nonce];
+        let mut args = vec!["encrypt", "-i", pt_path.to_str().unwrap()];
+        args.extend_from_slice(&["-o", ct_path.to_str().unwrap()]);
+        args.extend_from_slice(&common);
+        ok(&args, &[]);
+
+        let ct = fs::read(&ct_path).unwrap();
+        assert_eq!(ct.len(), pt.len());
+        assert!(ct != pt);
+
+        let mut args = vec!["decrypt", "-i", ct_path.to_str().unwrap()];
+        args.extend_from_slice(&common);
+        assert!(ok(&args, &[]) == pt, "{}", cipher);
+    }
+
+    fs::remove_file(pt_path).unwrap();
+    fs::remove_file(ct_path).unwrap();
+}
+
+/// Decrypting a range of the ciphertext, from a file or stdin
+#[test]
+fn offset() {
+    let pt: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+    let key = "42".repeat(16);
+    let iv = "24".repeat(8);
+    let common = ["-c", "rabbit", "-k", &key, "-n", &iv];
+
+    let ct = ok(&[&["encrypt"][..], &common].concat(), &pt);
+    let path = tmp("offset.ct");
+    fs::write(&path, &ct).unwrap();
+
+    let range = ["--offset", "1234", "--length", "4321"];
+    let file = ["decrypt", "-i", path.to_str().unwrap()];
+    assert!(ok(&[&file[..], &common, &range].concat(), &[]) == pt[1234..5555]);
+    let stdin = ok(&[&["decrypt"][..], &common, &range].concat(), &ct);
+    assert!(stdin == pt[1234..5555]);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn key_file() {
+    let path = tmp("key");
+    fs::write(&path, hex(CHACHA_KEY)).unwrap();
+    let args = [
+        "keystream",
+        "-c",
+        "chacha20",
+        "--key-file",
+        path.to_str().unwrap(),
+        "-n",
+        CHACHA_NONCE,
+        "--length",
+        "80",
+    ];
+    assert_eq!(
+        ok(&args, &[])[64..],
+        hex("10f1e7e4d13b5915500fdd1fa32071c4")[..]
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn errors() {
+    let key = "00".repeat(16);
+    let iv = "00".repeat(16);
+    let aes = format!("-k {} -n {}", key, iv);
+    let chacha = format!("-c chacha20 -k {} -n {}", CHACHA_KEY, CHACHA_NONCE);
+
+    err("frobnicate", "unknown command");
+    err(&format!("encrypt -c rc4 {}", aes), "unknown cipher");
+    err(&format!("encrypt --bogus {}", chacha), "unknown option");
+    err(&format!("encrypt -c chacha20 {}", aes), "32-byte key");
+    err("encrypt -c chacha20 -k xyz", "odd length 3");
+    err(
+        "encrypt -c chacha20 -k 00c0ffeex0",
+        "bad character at position 9",
+    );
+    let out = run(&["encrypt", "-c", "chacha20", "-k", "00c0ffeex0"], &[]);
+    assert!(!String::from_utf8_lossy(&out.stderr).contains("c0ffee"));
+    err(&format!("encrypt {} --offset", chacha), "requires a value");
+    err(&format!("keystream {}", chacha), "requires `--length`");
+    err(
+        &format!("decrypt -c aes-128-cfb {} --offset 16", aes),
+        "offsets",
+    );
+    err(
+        &format!("keystream -c aes-128-cfb8 {} --length 1", aes),
+        "no keystream",
+    );
+    err(
+        &format!("encrypt -c chacha20 -k {}", CHACHA_KEY),
+        "missing `--nonce`",
+    );
+}
+
+#[test]
+fn list_ciphers() {
+    let out = String::from_utf8(ok(&["ciphers"], &[])).unwrap();
+    for name in &["chacha8", "xchacha12", "salsa20", "rabbit", "aes-256-cfb8"] {
+        assert!(out.lines().any(|line| line == *name));
+    }
+}
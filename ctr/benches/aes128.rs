@@ -1,3 +1,3 @@
 #![feature(test)]
 
-cipher::stream_cipher_sync_bench!(ctr::Ctr128<aes::Aes128>);
+cipher::stream_cipher_sync_bench!(ctr::Ctr128BE<aes::Aes128>);
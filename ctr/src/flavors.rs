@@ -5,30 +5,200 @@ use cipher::{
     SeekNum,
 };
 
+/// Implement [`CtrFlavor`] for a counter type over the given block sizes.
+///
+/// The block is split into words of the counter's size. The counter is added
+/// to the last word (`be`) or the first word (`le`) of the nonce, and the
+/// remaining words of the nonce are copied as-is unless the overflow policy
+/// carries into them.
+macro_rules! impl_ctr_flavor {
+    ($endian:ident, $name:ident, $int:ty, $($block_size:ty => $size:ty),+) => {
+        $(
+            impl<P: OverflowPolicy> CtrFlavor<$block_size> for $name<P> {
+                type Size = $size;
+                type Backend = $int;
+
+                #[inline]
+                fn generate_block(
+                    &self,
+                    nonce: &GenericArray<Self, Self::Size>,
+                ) -> GenericArray<u8, $block_size> {
+                    let ctr_idx = impl_ctr_flavor!(@ctr_idx $endian, $size);
+                    let mut carry = false;
+                    let mut res = GenericArray::<u8, $block_size>::default();
+                    let chunks = res.chunks_exact_mut(core::mem::size_of::<$int>());
+                    for (i, (chunk, word)) in chunks.zip(nonce.iter()).enumerate() {
+                        if i == ctr_idx {
+                            let (ctr, overflow) = word.0.overflowing_add(self.0);
+                            carry = overflow;
+                            chunk.copy_from_slice(&impl_ctr_flavor!(@to_bytes $endian, ctr));
+                        } else {
+                            chunk.copy_from_slice(&word.0.to_ne_bytes());
+                        }
+                    }
+                    if P::CARRY && carry {
+                        let n = core::mem::size_of::<$int>();
+                        impl_ctr_flavor!(@carry $endian, res, n, ctr_idx);
+                    }
+                    res
+                }
+
+                #[inline]
+                fn load(block: &GenericArray<u8, $block_size>) -> GenericArray<Self, Self::Size> {
+                    let ctr_idx = impl_ctr_flavor!(@ctr_idx $endian, $size);
+                    let mut res = GenericArray::<Self, Self::Size>::default();
+                    let chunks = block.chunks_exact(core::mem::size_of::<$int>());
+                    for (i, (word, chunk)) in res.iter_mut().zip(chunks).enumerate() {
+                        let bytes = chunk.try_into().unwrap();
+                        *word = if i == ctr_idx {
+                            Self(impl_ctr_flavor!(@from_bytes $endian, $int, bytes), PhantomData)
+                        } else {
+                            Self(<$int>::from_ne_bytes(bytes), PhantomData)
+                        };
+                    }
+                    res
+                }
+
+                #[inline]
+                fn load_with_counter(
+                    block: &GenericArray<u8, $block_size>,
+                    initial_counter: Self::Backend,
+                ) -> Option<GenericArray<Self, Self::Size>> {
+                    let mut nonce = <Self as CtrFlavor<$block_size>>::load(block);
+                    nonce[impl_ctr_flavor!(@ctr_idx $endian, $size)] = Self(initial_counter, PhantomData);
+                    Some(nonce)
+                }
+
+                #[inline]
+                fn checked_add(&self, rhs: usize) -> Option<Self> {
+                    rhs.try_into()
+                        .ok()
+                        .and_then(|rhs| self.0.checked_add(rhs))
+                        .map(|v| Self(v, PhantomData))
+                }
+
+                #[inline]
+                fn can_generate(
+                    &self,
+                    nonce: &GenericArray<Self, Self::Size>,
+                    offset: usize,
+                ) -> bool {
+                    if !P::ERROR {
+                        return true;
+                    }
+                    let ctr_idx = impl_ctr_flavor!(@ctr_idx $endian, $size);
+                    offset
+                        .try_into()
+                        .ok()
+                        .and_then(|offset| self.0.checked_add(offset))
+                        .and_then(|ctr| nonce[ctr_idx].0.checked_add(ctr))
+                        .is_some()
+                }
+
+                #[inline]
+                fn increment(&mut self) {
+                    self.0 = self.0.wrapping_add(1);
+                }
+
+                #[inline]
+                fn to_backend(&self) -> Self::Backend {
+                    self.0
+                }
+
+                #[inline]
+                fn from_backend(v: Self::Backend) -> Self {
+                    Self(v, PhantomData)
+                }
+            }
+        )+
+    };
+    (@ctr_idx be, $size:ty) => { <$size as Unsigned>::USIZE - 1 };
+    (@ctr_idx le, $size:ty) => { 0 };
+    (@to_bytes be, $ctr:expr) => { $ctr.to_be_bytes() };
+    (@to_bytes le, $ctr:expr) => { $ctr.to_le_bytes() };
+    (@from_bytes be, $int:ty, $bytes:expr) => { <$int>::from_be_bytes($bytes) };
+    (@from_bytes le, $int:ty, $bytes:expr) => { <$int>::from_le_bytes($bytes) };
+    // increment the nonce words preceding the counter as a big endian number
+    (@carry be, $res:ident, $n:ident, $ctr_idx:ident) => {
+        for b in $res[..$ctr_idx * $n].iter_mut().rev() {
+            *b = b.wrapping_add(1);
+            if *b != 0 {
+                break;
+            }
+        }
+    };
+    // increment the nonce words following the counter as a little endian number
+    (@carry le, $res:ident, $n:ident, $ctr_idx:ident) => {
+        for b in $res[($ctr_idx + 1) * $n..].iter_mut() {
+            *b = b.wrapping_add(1);
+            if *b != 0 {
+                break;
+            }
+        }
+    };
+}
+
 mod ctr128;
 mod ctr32;
 mod ctr64;
+mod ctr_bits;
 
 pub use ctr128::*;
 pub use ctr32::*;
 pub use ctr64::*;
+pub use ctr_bits::*;
 
 /// Trait implemented by different counter types used in the CTR mode.
-pub trait CtrFlavor: Default + Clone {
-    /// Size of the 128-bit block in counter types.
+///
+/// The `BlockSize` parameter is the block size of the underlying block
+/// cipher. Flavors are implemented for every block size which is a multiple
+/// of the counter size, i.e. 8, 16 and 32 bytes where applicable.
+pub trait CtrFlavor<BlockSize: ArrayLength<u8> = U16>: Default + Clone {
+    /// Number of counter-sized words in a block.
     type Size: ArrayLength<Self>;
     /// Backend numeric type
     type Backend: SeekNum;
 
     /// Generate block for given `nonce` value.
-    fn generate_block(&self, nonce: &GenericArray<Self, Self::Size>) -> GenericArray<u8, U16>;
+    fn generate_block(&self, nonce: &GenericArray<Self, Self::Size>)
+        -> GenericArray<u8, BlockSize>;
 
     /// Load nonce value from bytes.
-    fn load(block: &GenericArray<u8, U16>) -> GenericArray<Self, Self::Size>;
+    fn load(block: &GenericArray<u8, BlockSize>) -> GenericArray<Self, Self::Size>;
+
+    /// Load nonce value from the nonce portion of `block`, with the counter
+    /// portion replaced by `initial_counter`.
+    ///
+    /// The standard flavors use the whole counter word, so every
+    /// `initial_counter` value fits and they always return `Some`. Flavors
+    /// with a narrower counter field (e.g. [`CtrBitsBE`]) return `None`
+    /// if `initial_counter` has bits set outside of that field.
+    ///
+    /// Defaults to `None`, i.e. the flavor does not support an explicit
+    /// initial counter.
+    #[inline]
+    fn load_with_counter(
+        block: &GenericArray<u8, BlockSize>,
+        initial_counter: Self::Backend,
+    ) -> Option<GenericArray<Self, Self::Size>> {
+        let _ = (block, initial_counter);
+        None
+    }
 
     /// Checked addition.
     fn checked_add(&self, rhs: usize) -> Option<Self>;
 
+    /// Whether the block `offset` blocks after this counter value can be
+    /// generated for `nonce` without the counter portion wrapping around.
+    ///
+    /// Defaults to `true`, i.e. the range is limited only by
+    /// [`CtrFlavor::checked_add`].
+    #[inline]
+    fn can_generate(&self, nonce: &GenericArray<Self, Self::Size>, offset: usize) -> bool {
+        let _ = (nonce, offset);
+        true
+    }
+
     /// Wrapped increment.
     fn increment(&mut self);
 
@@ -1,93 +1,28 @@
 //! 128-bit counter falvors.
 use super::CtrFlavor;
+use crate::policy::{OverflowPolicy, Wrap};
 use cipher::generic_array::{
-    typenum::{U1, U16},
+    typenum::{Unsigned, U1, U16, U2, U32},
     GenericArray,
 };
-use core::convert::TryInto;
+use core::{convert::TryInto, marker::PhantomData};
 
 /// 128-bit big endian counter flavor.
-#[derive(Default, Clone)]
+///
+/// The behavior on overflow of the counter portion of the block is selected
+/// by the [`OverflowPolicy`] `P`.
+#[derive(Default, Copy, Clone, Debug)]
 #[repr(transparent)]
-pub struct Ctr128BE(u128);
+pub struct Ctr128BE<P = Wrap>(u128, PhantomData<P>);
 
-impl CtrFlavor for Ctr128BE {
-    type Size = U1;
-    type Backend = u128;
-
-    #[inline]
-    fn generate_block(&self, nonce: &GenericArray<Self, Self::Size>) -> GenericArray<u8, U16> {
-        self.0.wrapping_add(nonce[0].0).to_be_bytes().into()
-    }
-
-    #[inline]
-    fn load(block: &GenericArray<u8, U16>) -> GenericArray<Self, Self::Size> {
-        [Self(u128::from_be_bytes(block[..].try_into().unwrap()))].into()
-    }
-
-    #[inline]
-    fn checked_add(&self, rhs: usize) -> Option<Self> {
-        rhs.try_into()
-            .ok()
-            .and_then(|rhs| self.0.checked_add(rhs))
-            .map(Self)
-    }
-
-    #[inline]
-    fn increment(&mut self) {
-        self.0 = self.0.wrapping_add(1);
-    }
-
-    #[inline]
-    fn to_backend(&self) -> Self::Backend {
-        self.0
-    }
-
-    #[inline]
-    fn from_backend(v: Self::Backend) -> Self {
-        Self(v)
-    }
-}
+impl_ctr_flavor!(be, Ctr128BE, u128, U16 => U1, U32 => U2);
 
 /// 128-bit little endian counter flavor.
-#[derive(Default, Clone)]
+///
+/// The behavior on overflow of the counter portion of the block is selected
+/// by the [`OverflowPolicy`] `P`.
+#[derive(Default, Copy, Clone, Debug)]
 #[repr(transparent)]
-pub struct Ctr128LE(u128);
-
-impl CtrFlavor for Ctr128LE {
-    type Size = U1;
-    type Backend = u128;
-
-    #[inline]
-    fn generate_block(&self, nonce: &GenericArray<Self, Self::Size>) -> GenericArray<u8, U16> {
-        self.0.wrapping_add(nonce[0].0).to_be_bytes().into()
-    }
-
-    #[inline]
-    fn load(block: &GenericArray<u8, U16>) -> GenericArray<Self, Self::Size> {
-        [Self(u128::from_le_bytes(block[..].try_into().unwrap()))].into()
-    }
-
-    #[inline]
-    fn checked_add(&self, rhs: usize) -> Option<Self> {
-        rhs.try_into()
-            .ok()
-            .and_then(|rhs| self.0.checked_add(rhs))
-            .map(Self)
-    }
-
-    #[inline]
-    fn increment(&mut self) {
-        self.0 = self.0.wrapping_add(1);
-    }
-
-    #[inline]
-    fn to_backend(&self) -> Self::Backend {
-        self.0
-    }
+pub struct Ctr128LE<P = Wrap>(u128, PhantomData<P>);
 
-    #[inline]
-    fn from_backend(v: Self::Backend) -> Self {
-        Self(v)
-    }
-}
+impl_ctr_flavor!(le, Ctr128LE, u128, U16 => U1, U32 => U2);
@@ -0,0 +1,165 @@
+//! Big endian counter flavor with an arbitrary nonce/counter split.
+use super::CtrFlavor;
+use cipher::generic_array::{
+    typenum::{IsLessOrEqual, NonZero, True, Unsigned, U1, U128, U16, U2, U32, U64, U8},
+    GenericArray,
+};
+use core::{convert::TryInto, marker::PhantomData};
+
+/// Big endian counter flavor which uses the `M` least significant bits of the
+/// block as the counter and the remaining bits as the nonce, as described in
+/// [NIST SP 800-38A] Appendix B.1.
+///
+/// Unlike the other flavors the counter never wraps around: processing data
+/// past the largest value of the `M`-bit counter portion results in
+/// [`LoopError`][cipher::errors::LoopError]. The initial counter value is
+/// taken from the counter portion of the IV, or can be given separately with
+/// [`Ctr::from_block_cipher_with_counter`][crate::Ctr::from_block_cipher_with_counter].
+///
+/// `M` must be at most 64 for 64-bit block ciphers and at most 128 otherwise.
+///
+/// [NIST SP 800-38A]: https://csrc.nist.gov/publications/detail/sp/800-38a/final
+#[derive(Default, Copy, Clone, Debug)]
+pub struct CtrBitsBE<M>(u128, PhantomData<M>);
+
+impl<M: Unsigned> CtrBitsBE<M> {
+    /// Largest value of the counter portion
+    #[inline(always)]
+    fn mask() -> u128 {
+        !0u128 >> (128 - M::U32)
+    }
+
+    /// Apply the counter to the last word of the nonce
+    #[inline(always)]
+    fn apply(&self, word: u128) -> u128 {
+        let mask = Self::mask();
+        (word & !mask) | (word.wrapping_add(self.0) & mask)
+    }
+
+    /// Replace the counter portion of the last word of the nonce
+    #[inline(always)]
+    fn with_field(word: u128, initial_counter: u128) -> Option<u128> {
+        let mask = Self::mask();
+        if initial_counter > mask {
+            None
+        } else {
+            Some((word & !mask) | initial_counter)
+        }
+    }
+}
+
+macro_rules! impl_ctr_bits {
+    ($block_size:ty, $size:ty, $max_bits:ty, $load:expr, $store:expr) => {
+        impl<M> CtrFlavor<$block_size> for CtrBitsBE<M>
+        where
+            M: Unsigned + NonZero + IsLessOrEqual<$max_bits, Output = True> + Default + Clone,
+        {
+            type Size = $size;
+            type Backend = u128;
+
+            #[inline]
+            fn generate_block(
+                &self,
+                nonce: &GenericArray<Self, Self::Size>,
+            ) -> GenericArray<u8, $block_size> {
+                let mut words: GenericArray<u128, $size> = nonce.iter().map(|w| w.0).collect();
+                let last = words.len() - 1;
+                words[last] = self.apply(words[last]);
+                let mut res = GenericArray::<u8, $block_size>::default();
+                $store(&words, &mut res);
+                res
+            }
+
+            #[inline]
+            fn load(block: &GenericArray<u8, $block_size>) -> GenericArray<Self, Self::Size> {
+                let words: GenericArray<u128, $size> = $load(block);
+                words.iter().map(|&w| Self(w, PhantomData)).collect()
+            }
+
+            #[inline]
+            fn load_with_counter(
+                block: &GenericArray<u8, $block_size>,
+                initial_counter: u128,
+            ) -> Option<GenericArray<Self, Self::Size>> {
+                let mut nonce = <Self as CtrFlavor<$block_size>>::load(block);
+                let last = nonce.len() - 1;
+                nonce[last].0 = Self::with_field(nonce[last].0, initial_counter)?;
+                Some(nonce)
+            }
+
+            #[inline]
+            fn checked_add(&self, rhs: usize) -> Option<Self> {
+                rhs.try_into()
+                    .ok()
+                    .and_then(|rhs| self.0.checked_add(rhs))
+                    .map(|v| Self(v, PhantomData))
+            }
+
+            #[inline]
+            fn can_generate(&self, nonce: &GenericArray<Self, Self::Size>, offset: usize) -> bool {
+                let field = nonce[nonce.len() - 1].0 & Self::mask();
+                let offset: Option<u128> = offset.try_into().ok();
+                offset
+                    .and_then(|offset| self.0.checked_add(offset))
+                    .and_then(|ctr| ctr.checked_add(field))
+                    .map_or(false, |v| v <= Self::mask())
+            }
+
+            #[inline]
+            fn increment(&mut self) {
+                self.0 = self.0.wrapping_add(1);
+            }
+
+            #[inline]
+            fn to_backend(&self) -> Self::Backend {
+                self.0
+            }
+
+            #[inline]
+            fn from_backend(v: Self::Backend) -> Self {
+                Self(v, PhantomData)
+            }
+        }
+    };
+}
+
+impl_ctr_bits!(
+    U8,
+    U1,
+    U64,
+    |block: &GenericArray<u8, U8>| {
+        let word = u64::from_be_bytes(block.as_slice().try_into().unwrap());
+        GenericArray::from([word as u128])
+    },
+    |words: &GenericArray<u128, U1>, res: &mut GenericArray<u8, U8>| {
+        res.copy_from_slice(&(words[0] as u64).to_be_bytes())
+    }
+);
+
+impl_ctr_bits!(
+    U16,
+    U1,
+    U128,
+    |block: &GenericArray<u8, U16>| {
+        GenericArray::from([u128::from_be_bytes(block.as_slice().try_into().unwrap())])
+    },
+    |words: &GenericArray<u128, U1>, res: &mut GenericArray<u8, U16>| {
+        res.copy_from_slice(&words[0].to_be_bytes())
+    }
+);
+
+impl_ctr_bits!(
+    U32,
+    U2,
+    U128,
+    |block: &GenericArray<u8, U32>| {
+        GenericArray::from([
+            u128::from_be_bytes(block[..16].try_into().unwrap()),
+            u128::from_be_bytes(block[16..].try_into().unwrap()),
+        ])
+    },
+    |words: &GenericArray<u128, U2>, res: &mut GenericArray<u8, U32>| {
+        res[..16].copy_from_slice(&words[0].to_be_bytes());
+        res[16..].copy_from_slice(&words[1].to_be_bytes());
+    }
+);
@@ -3,6 +3,10 @@
 //! Mode functionality is accessed using traits from re-exported
 //! [`cipher`](https://docs.rs/cipher) crate.
 //!
+//! The counter flavors support block ciphers with 64, 128 and 256-bit
+//! blocks, e.g. `Ctr64BE<magma::Magma>` for the 64-bit block Magma cipher.
+//! 128-bit counters require a block of at least 128 bits.
+//!
 //! # ⚠️ Security Warning: [Hazmat!]
 //!
 //! This crate does not ensure ciphertexts are authentic! Thus ciphertext integrity
@@ -34,6 +38,16 @@
 //! assert_eq!(data, [1, 2, 3, 4, 5, 6, 7]);
 //! ```
 //!
+//! The `rayon` feature provides a `par_apply_keystream` method which
+//! generates the keystream for large inputs on multiple threads, and the
+//! `state` feature provides `to_bytes` and `from_bytes` methods which save
+//! and restore the nonce and keystream position of a cipher.
+//!
+//! The `io` feature re-exports the `StreamReader` and `StreamWriter` adapters
+//! of the [`cipher-io`](https://docs.rs/cipher-io) crate, which apply the
+//! keystream to the data read from a `std::io::Read` or written to a
+//! `std::io::Write` implementation.
+//!
 //! [Hazmat!]: https://github.com/RustCrypto/meta/blob/master/HAZMAT.md
 
 #![no_std]
@@ -44,45 +58,78 @@
 #![warn(missing_docs, rust_2018_idioms)]
 
 pub use cipher;
+
 use cipher::{
     errors::{LoopError, OverflowError},
-    generic_array::{
-        typenum::{Unsigned, U16},
-        ArrayLength, GenericArray,
-    },
+    generic_array::{typenum::Unsigned, GenericArray},
     Block, BlockCipher, BlockEncrypt, FromBlockCipher, ParBlocks, SeekNum, StreamCipher,
     StreamCipherSeek,
 };
+use core::convert::TryInto;
 use core::fmt;
-use core::ops::Div;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+#[cfg(feature = "io")]
+pub use cipher_io::{StreamReader, StreamWriter};
+
+#[cfg(feature = "state")]
+pub use cipher_state::StateError;
+
+#[cfg(feature = "state")]
+use cipher::{BlockCipherKey, NewBlockCipher};
+#[cfg(feature = "state")]
+use cipher_state::State;
+#[cfg(feature = "state")]
+use core::convert::TryFrom;
+
+use cipher::generic_array::typenum::U8;
 
 pub mod flavors;
+pub mod policy;
 use flavors::CtrFlavor;
+use policy::Wrap;
 
 /// CTR mode with 128-bit big endian counter.
-pub type Ctr128BE<B> = Ctr<B, flavors::Ctr128BE>;
+pub type Ctr128BE<B, P = Wrap> = Ctr<B, flavors::Ctr128BE<P>>;
 /// CTR mode with 128-bit little endian counter.
-pub type Ctr128LE<B> = Ctr<B, flavors::Ctr128LE>;
+pub type Ctr128LE<B, P = Wrap> = Ctr<B, flavors::Ctr128LE<P>>;
 /// CTR mode with 64-bit big endian counter.
-pub type Ctr64BE<B> = Ctr<B, flavors::Ctr64BE>;
+pub type Ctr64BE<B, P = Wrap> = Ctr<B, flavors::Ctr64BE<P>>;
 /// CTR mode with 64-bit little endian counter.
-pub type Ctr64LE<B> = Ctr<B, flavors::Ctr64LE>;
+pub type Ctr64LE<B, P = Wrap> = Ctr<B, flavors::Ctr64LE<P>>;
 /// CTR mode with 32-bit big endian counter.
-pub type Ctr32BE<B> = Ctr<B, flavors::Ctr32BE>;
+pub type Ctr32BE<B, P = Wrap> = Ctr<B, flavors::Ctr32BE<P>>;
 /// CTR mode with 32-bit little endian counter.
-pub type Ctr32LE<B> = Ctr<B, flavors::Ctr32LE>;
+pub type Ctr32LE<B, P = Wrap> = Ctr<B, flavors::Ctr32LE<P>>;
+/// CTR mode with `M`-bit big endian counter which does not wrap around.
+pub type CtrBitsBE<B, M> = Ctr<B, flavors::CtrBitsBE<M>>;
+
+/// Number of blocks of keystream generated per iteration of the pipeline in
+/// [`StreamCipher::try_apply_keystream`], used for block ciphers which don't
+/// process blocks in parallel (`BlockCipher::ParBlocks` equal to 1).
+type PipelineBlocks = U8;
+
+/// Buffer of counter blocks which are encrypted in-place into keystream.
+type Pipeline<B> = GenericArray<Block<B>, PipelineBlocks>;
+
+/// Number of blocks processed by each task in [`Ctr::par_apply_keystream`].
+#[cfg(feature = "rayon")]
+const PAR_CHUNK_BLOCKS: usize = 4096;
+
+/// Nonce split into counter-sized words by the flavor `F`.
+type Nonce<B, F> = GenericArray<F, <F as CtrFlavor<<B as BlockCipher>::BlockSize>>::Size>;
 
 /// Generic CTR block mode isntance.
 #[derive(Clone)]
 pub struct Ctr<B, F>
 where
-    B: BlockEncrypt + BlockCipher<BlockSize = U16>,
-    B::ParBlocks: ArrayLength<GenericArray<u8, U16>>,
-    B::BlockSize: Div<F::Size>,
-    F: CtrFlavor,
+    B: BlockEncrypt + BlockCipher,
+    F: CtrFlavor<B::BlockSize>,
 {
     cipher: B,
-    nonce: GenericArray<F, F::Size>,
+    nonce: Nonce<B, F>,
     counter: F,
     buffer: Block<B>,
     buf_pos: u8,
@@ -90,68 +137,104 @@ where
 
 impl<B, F> Ctr<B, F>
 where
-    B: BlockEncrypt + BlockCipher<BlockSize = U16>,
-    B::ParBlocks: ArrayLength<GenericArray<u8, U16>>,
-    B::BlockSize: Div<F::Size>,
-    F: CtrFlavor,
+    B: BlockEncrypt + BlockCipher,
+    F: CtrFlavor<B::BlockSize>,
 {
     fn check_data_len(&self, data: &[u8]) -> Result<(), LoopError> {
         let bs = B::BlockSize::USIZE;
         let leftover_bytes = bs - self.buf_pos as usize;
-        if data.len() < leftover_bytes {
-            return Ok(());
+        if data.len() >= leftover_bytes {
+            let blocks = 1 + (data.len() - leftover_bytes) / bs;
+            self.counter.checked_add(blocks).ok_or(LoopError)?;
         }
-        let blocks = 1 + (data.len() - leftover_bytes) / bs;
-        self.counter
-            .checked_add(blocks)
-            .ok_or(LoopError)
-            .map(|_| ())
-    }
 
-    /// Seek to the given block
-    // TODO: replace with a trait-based method
-    pub fn seek_block(&mut self, block: F::Backend) {
-        self.counter = F::from_backend(block);
+        // offset of the last block the data reaches into, relative to the
+        // current counter value
+        let last = match self.buf_pos {
+            _ if data.is_empty() => return Ok(()),
+            0 => (data.len() - 1) / bs,
+            _ if data.len() <= leftover_bytes => return Ok(()),
+            _ => 1 + (data.len() - leftover_bytes - 1) / bs,
+        };
+        if self.counter.can_generate(&self.nonce, last) {
+            Ok(())
+        } else {
+            Err(LoopError)
+        }
     }
 
-    /// Return number of the current block
-    // TODO: replace with a trait-based method
-    pub fn current_block(&self) -> F::Backend {
-        self.counter.to_backend()
-    }
-}
+    /// Combine whole blocks of `data` with the keystream using `op`, starting
+    /// at `counter` and leaving `counter` at the block following them.
+    ///
+    /// Block ciphers which process blocks in parallel get `ParBlocks` counter
+    /// blocks at a time. Others go through the pipeline: fill a buffer with
+    /// consecutive counter blocks, encrypt them all at once, then combine the
+    /// resulting keystream with the data.
+    fn apply_blocks(&self, counter: &mut F, data: &mut [u8], op: impl Fn(&mut [u8], &[u8])) {
+        let bs = B::BlockSize::USIZE;
+        debug_assert_eq!(data.len() % bs, 0);
 
-impl<B, F> FromBlockCipher for Ctr<B, F>
-where
-    B: BlockEncrypt + BlockCipher<BlockSize = U16>,
-    B::ParBlocks: ArrayLength<GenericArray<u8, U16>>,
-    B::BlockSize: Div<F::Size>,
-    F: CtrFlavor,
-{
-    type BlockCipher = B;
-    type NonceSize = B::BlockSize;
+        let pb = B::ParBlocks::USIZE;
+        if pb > 1 {
+            let mut blocks: ParBlocks<B> = Default::default();
+            let mut chunks = data.chunks_exact_mut(bs * pb);
+            for chunk in &mut chunks {
+                for block in blocks.iter_mut() {
+                    *block = counter.generate_block(&self.nonce);
+                    counter.increment();
+                }
+                self.cipher.encrypt_par_blocks(&mut blocks);
+                for (c, block) in chunk.chunks_exact_mut(bs).zip(blocks.iter()) {
+                    op(c, block);
+                }
+            }
+            for c in chunks.into_remainder().chunks_exact_mut(bs) {
+                let mut block = counter.generate_block(&self.nonce);
+                counter.increment();
+                self.cipher.encrypt_block(&mut block);
+                op(c, &block);
+            }
+            return;
+        }
 
-    #[inline]
-    fn from_block_cipher(cipher: B, nonce: &Block<B>) -> Self {
-        let nonce = F::load(nonce);
-        Self {
-            cipher,
-            buffer: Default::default(),
-            nonce,
-            counter: Default::default(),
-            buf_pos: 0,
+        let mut blocks: Pipeline<B> = Default::default();
+        let mut chunks = data.chunks_exact_mut(bs * PipelineBlocks::USIZE);
+        for chunk in &mut chunks {
+            self.apply_pipeline(counter, &mut blocks, chunk, &op);
         }
+        let rem = chunks.into_remainder();
+        self.apply_pipeline(counter, &mut blocks[..rem.len() / bs], rem, &op);
     }
-}
 
-impl<B, F> StreamCipher for Ctr<B, F>
-where
-    B: BlockEncrypt + BlockCipher<BlockSize = U16>,
-    B::ParBlocks: ArrayLength<GenericArray<u8, U16>>,
-    B::BlockSize: Div<F::Size>,
-    F: CtrFlavor,
-{
-    fn try_apply_keystream(&mut self, mut data: &mut [u8]) -> Result<(), LoopError> {
+    /// Encrypt the counter blocks following `counter` into `blocks` and
+    /// combine them with `data`, which is exactly as long as `blocks`.
+    #[inline(always)]
+    fn apply_pipeline(
+        &self,
+        counter: &mut F,
+        blocks: &mut [Block<B>],
+        data: &mut [u8],
+        op: impl Fn(&mut [u8], &[u8]),
+    ) {
+        for block in blocks.iter_mut() {
+            *block = counter.generate_block(&self.nonce);
+            counter.increment();
+        }
+        self.cipher.encrypt_blocks(blocks);
+        for (c, block) in data
+            .chunks_exact_mut(B::BlockSize::USIZE)
+            .zip(blocks.iter())
+        {
+            op(c, block);
+        }
+    }
+
+    /// Combine `data` with the keystream using `op`, e.g. XOR.
+    fn process(
+        &mut self,
+        mut data: &mut [u8],
+        op: impl Fn(&mut [u8], &[u8]),
+    ) -> Result<(), LoopError> {
         self.check_data_len(data)?;
         let bs = B::BlockSize::USIZE;
         let pos = self.buf_pos as usize;
@@ -161,68 +244,282 @@ where
         if pos != 0 {
             if data.len() < bs - pos {
                 let n = pos + data.len();
-                xor(data, &self.buffer[pos..n]);
+                op(data, &self.buffer[pos..n]);
                 self.buf_pos = n as u8;
                 return Ok(());
             } else {
                 let (l, r) = data.split_at_mut(bs - pos);
                 data = r;
-                xor(l, &self.buffer[pos..]);
+                op(l, &self.buffer[pos..]);
                 counter.increment();
             }
         }
 
-        // Process blocks in parallel if cipher supports it
-        let pb = B::ParBlocks::USIZE;
-        if pb != 1 {
-            let mut chunks = data.chunks_exact_mut(bs * pb);
-            let mut blocks: ParBlocks<B> = Default::default();
-            for chunk in &mut chunks {
-                for b in blocks.iter_mut() {
-                    *b = counter.generate_block(&self.nonce);
-                    counter.increment();
-                }
-
-                self.cipher.encrypt_par_blocks(&mut blocks);
-                xor(chunk, to_slice::<B>(&blocks));
-            }
-            data = chunks.into_remainder();
-        }
-
-        let mut chunks = data.chunks_exact_mut(bs);
-        for chunk in &mut chunks {
-            let mut block = counter.generate_block(&self.nonce);
-            counter.increment();
-            self.cipher.encrypt_block(&mut block);
-            xor(chunk, &block);
+        let full_blocks = data.len() / bs;
+        if full_blocks != 0 {
+            let (body, rem) = data.split_at_mut(full_blocks * bs);
+            self.apply_blocks(&mut counter, body, &op);
+            data = rem;
         }
 
-        let rem = chunks.into_remainder();
+        let rem = data;
         if !rem.is_empty() {
             self.buffer = counter.generate_block(&self.nonce);
             self.cipher.encrypt_block(&mut self.buffer);
-            xor(rem, &self.buffer[..rem.len()]);
+            op(rem, &self.buffer[..rem.len()]);
         }
         self.buf_pos = rem.len() as u8;
         self.counter = counter;
         Ok(())
     }
+
+    /// Write keystream to `out`, overwriting its contents.
+    ///
+    /// The output and the position of the cipher afterwards are the same as
+    /// for [`StreamCipher::apply_keystream`] on a zeroed buffer, without
+    /// paying for the XOR.
+    ///
+    /// # Panics
+    ///
+    /// If the end of the keystream is reached with the given length.
+    pub fn write_keystream(&mut self, out: &mut [u8]) {
+        self.try_write_keystream(out).unwrap();
+    }
+
+    /// Write keystream to `out`, see [`Ctr::write_keystream`].
+    ///
+    /// Returns [`LoopError`] and leaves the cipher unchanged if the end of
+    /// the keystream is reached with the given length.
+    pub fn try_write_keystream(&mut self, out: &mut [u8]) -> Result<(), LoopError> {
+        self.process(out, copy)
+    }
+
+    /// Create a new CTR mode instance from the nonce portion of `nonce` and a
+    /// separate `initial_counter` value, for protocols which define their own
+    /// nonce||counter layout and starting counter (e.g. GCM, SRTP, ESP).
+    ///
+    /// The bits of `nonce` which fall into the flavor's counter portion are
+    /// ignored. Keystream position 0 corresponds to `initial_counter`.
+    ///
+    /// Returns [`LoopError`] if `initial_counter` does not fit in the counter
+    /// portion of the flavor, which can only happen with flavors whose counter
+    /// is narrower than their backend type, i.e. [`flavors::CtrBitsBE`], or if
+    /// the flavor doesn't implement [`CtrFlavor::load_with_counter`].
+    pub fn from_block_cipher_with_counter(
+        cipher: B,
+        nonce: &Block<B>,
+        initial_counter: <F as CtrFlavor<B::BlockSize>>::Backend,
+    ) -> Result<Self, LoopError> {
+        let nonce = F::load_with_counter(nonce, initial_counter).ok_or(LoopError)?;
+        Ok(Self {
+            cipher,
+            buffer: Default::default(),
+            nonce,
+            counter: Default::default(),
+            buf_pos: 0,
+        })
+    }
+
+    /// Seek to the given block
+    // TODO: replace with a trait-based method
+    pub fn seek_block(&mut self, block: <F as CtrFlavor<B::BlockSize>>::Backend) {
+        self.counter = F::from_backend(block);
+    }
+
+    /// Return number of the current block
+    // TODO: replace with a trait-based method
+    pub fn current_block(&self) -> <F as CtrFlavor<B::BlockSize>>::Backend {
+        self.counter.to_backend()
+    }
+}
+
+#[cfg(feature = "state")]
+impl<B, F> Ctr<B, F>
+where
+    B: BlockEncrypt + BlockCipher + NewBlockCipher,
+    F: CtrFlavor<B::BlockSize>,
+{
+    /// Length of the state serialized by [`Ctr::to_bytes`], with or without
+    /// the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(B::KeySize::USIZE, Self::state_body_len(), with_key)
+    }
+
+    /// Serialize the nonce and keystream position into `buf`, returning the
+    /// written prefix of it.
+    ///
+    /// The block cipher does not expose its key, so it's included only if
+    /// passed as `key`, which must be the key the block cipher was
+    /// initialized with. A state including the key decrypts everything
+    /// encrypted under that key and nonce.
+    ///
+    /// The format is a version byte, a flags byte, the key if included, the
+    /// initial counter block, then the number of the current block relative
+    /// to it as a little endian `u128` and the byte position within that
+    /// block.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&BlockCipherKey<B>>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let bs = B::BlockSize::USIZE;
+        let block: u128 = self
+            .counter
+            .to_backend()
+            .try_into()
+            .map_err(|_| StateError)?;
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write(buf, key, 0, Self::state_body_len(), |body| {
+            body[..bs].copy_from_slice(&F::default().generate_block(&self.nonce));
+            body[bs..bs + 16].copy_from_slice(&block.to_le_bytes());
+            body[bs + 16] = self.buf_pos;
+            Ok(())
+        })
+    }
+
+    /// Restore an instance from a state serialized by [`Ctr::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    pub fn from_bytes(bytes: &[u8], key: Option<&BlockCipherKey<B>>) -> Result<Self, StateError> {
+        let bs = B::BlockSize::USIZE;
+        let state = State::read(bytes, B::KeySize::USIZE, Self::state_body_len(), 0)?;
+        let body = state.body();
+
+        let block = u128::from_le_bytes(body[bs..bs + 16].try_into().unwrap());
+        let block =
+            <F as CtrFlavor<B::BlockSize>>::Backend::try_from(block).map_err(|_| StateError)?;
+        let counter = F::from_backend(block);
+        let buf_pos = body[bs + 16];
+        if buf_pos as usize >= bs {
+            return Err(StateError);
+        }
+
+        let cipher =
+            B::new_from_slice(state.key(key.map(|k| k.as_slice()))?).map_err(|_| StateError)?;
+        let mut res = Self::from_block_cipher(cipher, Block::<B>::from_slice(&body[..bs]));
+        if buf_pos != 0 {
+            if !counter.can_generate(&res.nonce, 0) {
+                return Err(StateError);
+            }
+            res.buffer = counter.generate_block(&res.nonce);
+            res.cipher.encrypt_block(&mut res.buffer);
+        }
+        res.counter = counter;
+        res.buf_pos = buf_pos;
+        Ok(res)
+    }
+
+    /// Length of the serialized state following the key: the initial counter
+    /// block, block number and byte position
+    fn state_body_len() -> usize {
+        B::BlockSize::USIZE + 16 + 1
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<B, F> Ctr<B, F>
+where
+    B: BlockEncrypt + BlockCipher + Sync,
+    F: CtrFlavor<B::BlockSize> + Sync,
+{
+    /// Apply keystream to `data` using the [`rayon`] thread pool.
+    ///
+    /// The output and the position of the cipher afterwards are the same as
+    /// for [`StreamCipher::apply_keystream`]. Only the whole blocks of
+    /// keystream following the current position are generated in parallel,
+    /// so this pays off for large inputs.
+    ///
+    /// # Panics
+    ///
+    /// If the end of the keystream is reached with the given data length.
+    pub fn par_apply_keystream(&mut self, data: &mut [u8]) {
+        self.try_par_apply_keystream(data).unwrap();
+    }
+
+    /// Apply keystream to `data` using the [`rayon`] thread pool, see
+    /// [`Ctr::par_apply_keystream`].
+    ///
+    /// Returns [`LoopError`] and leaves the cipher unchanged if the end of
+    /// the keystream is reached with the given data length.
+    pub fn try_par_apply_keystream(&mut self, data: &mut [u8]) -> Result<(), LoopError> {
+        self.check_data_len(data)?;
+        let bs = B::BlockSize::USIZE;
+
+        // use up the leftover keystream first to get to a block boundary
+        let head = match self.buf_pos as usize {
+            0 => 0,
+            pos => core::cmp::min(bs - pos, data.len()),
+        };
+        let (head, data) = data.split_at_mut(head);
+        self.try_apply_keystream(head)?;
+
+        let body_len = data.len() - data.len() % bs;
+        let (body, tail) = data.split_at_mut(body_len);
+        let this = &*self;
+        body.par_chunks_mut(bs * PAR_CHUNK_BLOCKS)
+            .enumerate()
+            .for_each(|(i, chunk)| {
+                // the data length was checked above, so this can't overflow
+                let mut counter = this.counter.checked_add(i * PAR_CHUNK_BLOCKS).unwrap();
+                this.apply_blocks(&mut counter, chunk, xor);
+            });
+        self.counter = self.counter.checked_add(body_len / bs).unwrap();
+
+        if !tail.is_empty() {
+            self.try_apply_keystream(tail)?;
+        }
+        Ok(())
+    }
+}
+
+impl<B, F> FromBlockCipher for Ctr<B, F>
+where
+    B: BlockEncrypt + BlockCipher,
+    F: CtrFlavor<B::BlockSize>,
+{
+    type BlockCipher = B;
+    type NonceSize = B::BlockSize;
+
+    #[inline]
+    fn from_block_cipher(cipher: B, nonce: &Block<B>) -> Self {
+        let nonce = F::load(nonce);
+        Self {
+            cipher,
+            buffer: Default::default(),
+            nonce,
+            counter: Default::default(),
+            buf_pos: 0,
+        }
+    }
+}
+
+impl<B, F> StreamCipher for Ctr<B, F>
+where
+    B: BlockEncrypt + BlockCipher,
+    F: CtrFlavor<B::BlockSize>,
+{
+    fn try_apply_keystream(&mut self, data: &mut [u8]) -> Result<(), LoopError> {
+        self.process(data, xor)
+    }
 }
 
 impl<B, F> StreamCipherSeek for Ctr<B, F>
 where
-    B: BlockEncrypt + BlockCipher<BlockSize = U16>,
-    B::ParBlocks: ArrayLength<GenericArray<u8, U16>>,
-    B::BlockSize: Div<F::Size>,
-    F: CtrFlavor,
+    B: BlockEncrypt + BlockCipher,
+    F: CtrFlavor<B::BlockSize>,
 {
     fn try_current_pos<T: SeekNum>(&self) -> Result<T, OverflowError> {
         T::from_block_byte(self.counter.to_backend(), self.buf_pos, B::BlockSize::U8)
     }
 
     fn try_seek<S: SeekNum>(&mut self, pos: S) -> Result<(), LoopError> {
-        let res: (F::Backend, u8) = pos.to_block_byte(B::BlockSize::U8)?;
-        self.counter = F::from_backend(res.0);
+        let res: (<F as CtrFlavor<B::BlockSize>>::Backend, u8) =
+            pos.to_block_byte(B::BlockSize::U8)?;
+        let counter = F::from_backend(res.0);
+        if res.1 != 0 && !counter.can_generate(&self.nonce, 0) {
+            return Err(LoopError);
+        }
+        self.counter = counter;
         self.buf_pos = res.1;
         if self.buf_pos != 0 {
             let mut block = self.counter.generate_block(&self.nonce);
@@ -235,26 +532,33 @@ where
 
 impl<B, F> fmt::Debug for Ctr<B, F>
 where
-    B: BlockEncrypt + BlockCipher<BlockSize = U16> + fmt::Debug,
-    B::ParBlocks: ArrayLength<GenericArray<u8, U16>>,
-    B::BlockSize: Div<F::Size>,
-    F: CtrFlavor + fmt::Debug,
+    B: BlockEncrypt + BlockCipher + fmt::Debug,
+    F: CtrFlavor<B::BlockSize> + fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         write!(f, "Ctr-{:?}-{:?}", self.counter, self.cipher)
     }
 }
 
+/// XOR `key` into `buf` a machine word at a time
 #[inline(always)]
 fn xor(buf: &mut [u8], key: &[u8]) {
     debug_assert_eq!(buf.len(), key.len());
-    for (a, b) in buf.iter_mut().zip(key) {
+    let mut buf_words = buf.chunks_exact_mut(8);
+    let mut key_words = key.chunks_exact(8);
+    for (a, b) in (&mut buf_words).zip(&mut key_words) {
+        let a_word = u64::from_ne_bytes((&*a).try_into().unwrap());
+        let b_word = u64::from_ne_bytes(b.try_into().unwrap());
+        a.copy_from_slice(&(a_word ^ b_word).to_ne_bytes());
+    }
+
+    let rem = buf_words.into_remainder();
+    for (a, b) in rem.iter_mut().zip(key_words.remainder()) {
         *a ^= *b;
     }
 }
 
 #[inline(always)]
-fn to_slice<C: BlockEncrypt>(blocks: &ParBlocks<C>) -> &[u8] {
-    let blocks_len = C::BlockSize::to_usize() * C::ParBlocks::to_usize();
-    unsafe { core::slice::from_raw_parts(blocks.as_ptr() as *const u8, blocks_len) }
+fn copy(buf: &mut [u8], key: &[u8]) {
+    buf.copy_from_slice(key);
 }
@@ -0,0 +1,54 @@
+//! Policies for the behavior of counter flavors when the counter portion of
+//! the block overflows.
+//!
+//! The counter flavors add the counter to the counter portion of the nonce
+//! (e.g. the last 32 bits of the block for [`Ctr32BE`][crate::flavors::Ctr32BE]).
+//! Protocols disagree on what should happen when this addition overflows:
+//!
+//! - [`Wrap`] (the default): the counter portion wraps around and the rest of
+//!   the nonce is left unchanged, as with GCM's `inc32`.
+//! - [`Error`]: processing data which would make the counter portion wrap
+//!   results in [`LoopError`][cipher::errors::LoopError], as required by
+//!   NIST SP 800-38A.
+//! - [`CarryIntoNonce`]: the overflow is carried into the rest of the nonce,
+//!   i.e. the whole block acts as a single counter, as done by OpenSSL.
+//!
+//! In all cases at most `2^N` blocks of keystream can be produced for an
+//! `N`-bit counter flavor before [`LoopError`][cipher::errors::LoopError]
+//! is returned.
+
+/// Behavior of a counter flavor when the counter portion of the block
+/// overflows.
+pub trait OverflowPolicy: Default + Copy + Clone {
+    /// Carry the overflow into the nonce portion of the block.
+    const CARRY: bool;
+    /// Report the overflow as an error.
+    const ERROR: bool;
+}
+
+/// Wrap the counter portion around, leaving the rest of the nonce unchanged.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Wrap;
+
+impl OverflowPolicy for Wrap {
+    const CARRY: bool = false;
+    const ERROR: bool = false;
+}
+
+/// Return an error instead of wrapping the counter portion around.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Error;
+
+impl OverflowPolicy for Error {
+    const CARRY: bool = false;
+    const ERROR: bool = true;
+}
+
+/// Carry the overflow of the counter portion into the rest of the nonce.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct CarryIntoNonce;
+
+impl OverflowPolicy for CarryIntoNonce {
+    const CARRY: bool = true;
+    const ERROR: bool = false;
+}
@@ -0,0 +1,129 @@
+//! CTR mode over block ciphers with block sizes other than 128 bits
+
+use cipher::{
+    consts::{U1, U32},
+    generic_array::GenericArray,
+    BlockCipher, BlockEncrypt, FromBlockCipher, NewBlockCipher, StreamCipher, StreamCipherSeek,
+};
+use hex_literal::hex;
+
+/// GOST R 34.13-2015, Appendix A.2.2: Magma (64-bit block) in CTR mode
+#[test]
+fn magma_ctr64() {
+    type MagmaCtr = ctr::Ctr64BE<magma::Magma>;
+
+    let key = hex!("ffeeddccbbaa99887766554433221100f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+    let nonce = hex!("1234567800000000");
+    let pt = hex!("92def06b3c130a59 db54c704f8189d20 4a98fb2e67a8024c 8912409b17b57e41");
+    let ct = hex!("4e98110c97b7b93c 3e250d93d6e85d69 136d868807b2dbef 568eb680ab52a12d");
+
+    let magma = magma::Magma::new(&key.into());
+    let mut cipher = MagmaCtr::from_block_cipher(magma, &nonce.into());
+    let mut buf = pt;
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, ct);
+
+    // byte positions are in units of the 8-byte block
+    cipher.seek(13u32);
+    let mut buf = ct;
+    cipher.apply_keystream(&mut buf[13..]);
+    assert_eq!(&buf[13..], &pt[13..]);
+    assert_eq!(cipher.current_block(), 4);
+}
+
+/// Toy 256-bit block cipher: XOR with the key, then reverse the bytes
+#[derive(Clone)]
+struct Toy256([u8; 32]);
+
+impl NewBlockCipher for Toy256 {
+    type KeySize = U32;
+
+    fn new(key: &GenericArray<u8, U32>) -> Self {
+        let mut k = [0u8; 32];
+        k.copy_from_slice(key);
+        Self(k)
+    }
+}
+
+impl BlockCipher for Toy256 {
+    type BlockSize = U32;
+    type ParBlocks = U1;
+}
+
+impl BlockEncrypt for Toy256 {
+    fn encrypt_block(&self, block: &mut GenericArray<u8, U32>) {
+        for (b, k) in block.iter_mut().zip(self.0.iter()) {
+            *b ^= k;
+        }
+        block.reverse();
+    }
+}
+
+const KEY: [u8; 32] = hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+const NONCE: [u8; 32] = hex!("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff0000000000000000fffffffffffffffe");
+
+/// Reference keystream with a counter of type `$int` in the last (`be`) or
+/// first (`le`) word of the 256-bit block
+macro_rules! reference_keystream {
+    ($int:ty, $from:ident, $to:ident, $idx:expr) => {{
+        let cipher = Toy256::new(&KEY.into());
+        let n = core::mem::size_of::<$int>();
+        let mut word = [0u8; core::mem::size_of::<$int>()];
+        word.copy_from_slice(&NONCE[$idx * n..($idx + 1) * n]);
+        let ctr = <$int>::$from(word);
+
+        let mut keystream = [0u8; 32 * 5 + 3];
+        for (i, chunk) in keystream.chunks_mut(32).enumerate() {
+            let mut block = GenericArray::clone_from_slice(&NONCE);
+            block[$idx * n..($idx + 1) * n].copy_from_slice(&ctr.wrapping_add(i as $int).$to());
+            cipher.encrypt_block(&mut block);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        keystream
+    }};
+}
+
+macro_rules! toy256_test {
+    ($name:ident, $ctr:ident, $int:ty, $from:ident, $to:ident, $idx:expr) => {
+        #[test]
+        fn $name() {
+            let expected = reference_keystream!($int, $from, $to, $idx);
+
+            for split in 0..expected.len() {
+                let mut cipher =
+                    ctr::$ctr::<Toy256>::from_block_cipher(Toy256::new(&KEY.into()), &NONCE.into());
+                let mut buf = [0u8; 32 * 5 + 3];
+                let (l, r) = buf.split_at_mut(split);
+                cipher.apply_keystream(l);
+                cipher.apply_keystream(r);
+                assert_eq!(&buf[..], &expected[..]);
+
+                cipher.seek(split as u32);
+                let mut buf = [0u8; 32 * 5 + 3];
+                cipher.apply_keystream(&mut buf[split..]);
+                assert_eq!(&buf[split..], &expected[split..]);
+            }
+        }
+    };
+}
+
+toy256_test!(toy256_ctr32be, Ctr32BE, u32, from_be_bytes, to_be_bytes, 7);
+toy256_test!(toy256_ctr32le, Ctr32LE, u32, from_le_bytes, to_le_bytes, 0);
+toy256_test!(toy256_ctr64be, Ctr64BE, u64, from_be_bytes, to_be_bytes, 3);
+toy256_test!(toy256_ctr64le, Ctr64LE, u64, from_le_bytes, to_le_bytes, 0);
+toy256_test!(
+    toy256_ctr128be,
+    Ctr128BE,
+    u128,
+    from_be_bytes,
+    to_be_bytes,
+    1
+);
+toy256_test!(
+    toy256_ctr128le,
+    Ctr128LE,
+    u128,
+    from_le_bytes,
+    to_le_bytes,
+    0
+);
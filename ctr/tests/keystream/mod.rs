@@ -0,0 +1,68 @@
+//! Writing the raw keystream without XOR
+
+use aes::{Aes128, NewBlockCipher};
+use cipher::{consts::U8, NewCipher, StreamCipher, StreamCipherSeek};
+
+const KEY: [u8; 16] = *b"very secret key.";
+const NONCE: [u8; 16] = [0xfe; 16];
+
+/// Chunk sizes around block and pipeline boundaries
+const CHUNKS: [usize; 9] = [1, 15, 16, 17, 7, 128, 300, 3, 129];
+
+/// Writing keystream interleaved with applying it must match applying it to
+/// zeroes, and track the position the same way
+#[test]
+fn matches_apply_keystream() {
+    let new = || ctr::Ctr64LE::<Aes128>::new(&KEY.into(), &NONCE.into());
+    let len = CHUNKS.iter().sum::<usize>();
+    let mut expected = vec![0u8; len];
+    new().apply_keystream(&mut expected);
+
+    let mut cipher = new();
+    let mut buf = vec![0xffu8; len];
+    let mut pos = 0;
+    for (i, &n) in CHUNKS.iter().enumerate() {
+        let chunk = &mut buf[pos..pos + n];
+        if i % 2 == 0 {
+            cipher.write_keystream(chunk);
+        } else {
+            chunk.iter_mut().for_each(|b| *b = 0);
+            cipher.apply_keystream(chunk);
+        }
+        pos += n;
+        assert_eq!(cipher.current_pos::<usize>(), pos);
+    }
+    assert!(buf == expected);
+
+    cipher.seek(100u32);
+    cipher.write_keystream(&mut buf[..50]);
+    assert_eq!(&buf[..50], &expected[100..150]);
+}
+
+#[test]
+fn end_of_keystream() {
+    let new = |initial_counter| {
+        let aes = Aes128::new(&KEY.into());
+        ctr::CtrBitsBE::<Aes128, U8>::from_block_cipher_with_counter(
+            aes,
+            &NONCE.into(),
+            initial_counter,
+        )
+        .unwrap()
+    };
+
+    let mut expected = [0u8; 48];
+    new(0xfd).apply_keystream(&mut expected);
+
+    // counter values 0xfd, 0xfe and 0xff are usable
+    let mut cipher = new(0xfd);
+    let mut buf = [0xffu8; 49];
+    assert!(cipher.try_write_keystream(&mut buf).is_err());
+    assert_eq!(buf, [0xff; 49]);
+    assert_eq!(cipher.current_pos::<u64>(), 0);
+
+    cipher.try_write_keystream(&mut buf[..20]).unwrap();
+    cipher.try_write_keystream(&mut buf[20..48]).unwrap();
+    assert_eq!(&buf[..48], &expected[..]);
+    assert!(cipher.try_write_keystream(&mut [0u8; 1]).is_err());
+}
@@ -1,4 +1,13 @@
 //! Counter Mode Tests
 
+mod block_size;
 mod ctr128;
 mod ctr32;
+mod keystream;
+#[cfg(feature = "rayon")]
+mod par;
+mod partial_nonce;
+mod pipeline;
+mod policy;
+#[cfg(feature = "state")]
+mod state;
@@ -0,0 +1,91 @@
+//! Parallel keystream application must match the serial one, including the
+//! position of the cipher afterwards
+
+use aes::{Aes128, NewBlockCipher};
+use cipher::{consts::U16, BlockCipher, NewCipher, StreamCipher, StreamCipherSeek};
+use ctr::{flavors::CtrFlavor, Ctr};
+
+const KEY: [u8; 16] = *b"very secret key.";
+const NONCE: [u8; 16] = [0xff; 16];
+
+/// One chunk processed by each task is 4096 blocks
+const CHUNK: usize = 4096 * 16;
+const LEN: usize = 2 * CHUNK + 1000;
+
+fn check<F>(new: impl Fn() -> Ctr<Aes128, F>)
+where
+    F: CtrFlavor<<Aes128 as BlockCipher>::BlockSize> + Sync,
+{
+    let mut expected = vec![0u8; LEN + 100];
+    new().apply_keystream(&mut expected);
+
+    for &(start, len) in &[
+        (0, 0),
+        (5, 7),
+        (0, LEN),
+        (1, LEN - 1),
+        (16, CHUNK),
+        (17, CHUNK + 15),
+    ] {
+        let mut cipher = new();
+        cipher.seek(start as u64);
+        let mut buf = vec![0u8; len + 100];
+        cipher.par_apply_keystream(&mut buf[..len]);
+        assert_eq!(cipher.current_pos::<usize>(), start + len);
+        cipher.apply_keystream(&mut buf[len..]);
+        assert!(buf[..] == expected[start..start + len + 100]);
+    }
+}
+
+#[test]
+fn ctr128() {
+    check(|| ctr::Ctr128BE::<Aes128>::new(&KEY.into(), &NONCE.into()));
+}
+
+#[test]
+fn ctr32_wrapping() {
+    check(|| ctr::Ctr32LE::<Aes128>::new(&KEY.into(), &NONCE.into()));
+}
+
+#[test]
+fn end_of_keystream() {
+    let new = || {
+        let aes = Aes128::new(&KEY.into());
+        ctr::CtrBitsBE::<Aes128, U16>::from_block_cipher_with_counter(aes, &NONCE.into(), 0)
+            .unwrap()
+    };
+    // the 16-bit counter allows 65536 blocks
+    let start = (1 << 16) * 16 - LEN;
+
+    let mut cipher = new();
+    cipher.seek(start as u64);
+    assert!(cipher.try_par_apply_keystream(&mut [0u8; LEN + 1]).is_err());
+    assert_eq!(cipher.current_pos::<usize>(), start);
+
+    let mut buf = vec![0u8; LEN];
+    cipher.try_par_apply_keystream(&mut buf).unwrap();
+    assert!(cipher.try_par_apply_keystream(&mut [0u8; 1]).is_err());
+
+    let mut expected = vec![0u8; LEN];
+    let mut cipher = new();
+    cipher.seek(start as u64);
+    cipher.apply_keystream(&mut expected);
+    assert!(buf == expected);
+}
+
+/// 64-bit blocks
+#[test]
+fn magma_ctr64() {
+    let key = [0x42; 32];
+    let nonce = [0x24; 8];
+    let new = || ctr::Ctr64BE::<magma::Magma>::new(&key.into(), &nonce.into());
+    let mut expected = vec![0u8; LEN];
+    new().apply_keystream(&mut expected);
+
+    let mut cipher = new();
+    let mut buf = vec![0u8; LEN];
+    cipher.par_apply_keystream(&mut buf[..3]);
+    cipher.par_apply_keystream(&mut buf[3..]);
+    assert_eq!(cipher.current_pos::<usize>(), LEN);
+    assert!(buf == expected);
+}
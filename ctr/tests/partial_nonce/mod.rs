@@ -0,0 +1,159 @@
+//! NIST SP 800-38A partial-nonce CTR with an explicit nonce/counter split
+
+use aes::{Aes128, BlockEncrypt, NewBlockCipher};
+use cipher::{
+    consts::{U12, U128, U32, U8},
+    generic_array::GenericArray,
+    NewCipher, StreamCipher, StreamCipherSeek,
+};
+use hex_literal::hex;
+
+const KEY: [u8; 16] = hex!("2b7e151628aed2a6abf7158809cf4f3c");
+const IV: [u8; 16] = hex!("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+const PT: [u8; 64] = hex!(
+    "6bc1bee22e409f96e93d7e117393172a ae2d8a571e03ac9c9eb76fac45af8e51
+     30c81c46a35ce411e5fbc1191a0a52ef f69f2445df4f9b17ad2b417be66c3710"
+);
+const CT: [u8; 64] = hex!(
+    "874d6191b620e3261bef6864990db6ce 9806f66b7970fdff8617187bb9fffdff
+     5ae4df3edbd5d35e5b4f09020db03eab 1e031dda2fbe03d1792170a0f3009cee"
+);
+
+/// SP 800-38A F.5.1 with the counter split at 32 and 128 bits
+#[test]
+fn sp800_38a_iv() {
+    let mut buf = PT;
+    let mut cipher = ctr::CtrBitsBE::<Aes128, U32>::new(&KEY.into(), &IV.into());
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, CT);
+
+    let mut buf = PT;
+    let mut cipher = ctr::CtrBitsBE::<Aes128, U128>::new(&KEY.into(), &IV.into());
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, CT);
+}
+
+/// The counter portion of the nonce is ignored in favor of `initial_counter`
+#[test]
+fn sp800_38a_separate_counter() {
+    let nonce = hex!("f0f1f2f3f4f5f6f7f8f9fafb00000000");
+
+    let mut buf = PT;
+    let aes = Aes128::new(&KEY.into());
+    let mut cipher = ctr::CtrBitsBE::<Aes128, U32>::from_block_cipher_with_counter(
+        aes,
+        &nonce.into(),
+        0xfcfdfeff,
+    )
+    .unwrap();
+    assert_eq!(cipher.current_pos::<u64>(), 0);
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, CT);
+
+    let mut buf = PT;
+    let aes = Aes128::new(&KEY.into());
+    let mut cipher =
+        ctr::Ctr32BE::<Aes128>::from_block_cipher_with_counter(aes, &IV.into(), 0xfcfdfeff)
+            .unwrap();
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, CT);
+}
+
+/// GCM test case 2: the payload is encrypted starting from `inc32(J0)`
+#[test]
+fn gcm_inner_ctr() {
+    let iv = hex!("000000000000000000000000ffffffff");
+    let aes = Aes128::new(&Default::default());
+    let mut cipher =
+        ctr::CtrBitsBE::<Aes128, U32>::from_block_cipher_with_counter(aes, &iv.into(), 2).unwrap();
+    let mut buf = [0u8; 16];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, hex!("0388dace60b6a392f328c2b971b2fe78"));
+}
+
+/// GOST R 34.13-2015, Appendix A.2.2: 32-bit nonce and 32-bit counter
+#[test]
+fn magma_ctr() {
+    let key = hex!("ffeeddccbbaa99887766554433221100f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+    let pt = hex!("92def06b3c130a59 db54c704f8189d20 4a98fb2e67a8024c 8912409b17b57e41");
+    let ct = hex!("4e98110c97b7b93c 3e250d93d6e85d69 136d868807b2dbef 568eb680ab52a12d");
+
+    let magma = magma::Magma::new(&key.into());
+    let nonce = hex!("12345678ffffffff");
+    let mut cipher = ctr::CtrBitsBE::<magma::Magma, U32>::from_block_cipher_with_counter(
+        magma,
+        &nonce.into(),
+        0,
+    )
+    .unwrap();
+    let mut buf = pt;
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(buf, ct);
+}
+
+/// A counter split which is not a multiple of 8 bits leaves the nonce bits
+/// sharing its byte intact
+#[test]
+fn unaligned_split() {
+    let nonce = hex!("00112233445566778899aabbccddefff");
+    let aes = Aes128::new(&KEY.into());
+    let mut cipher = ctr::CtrBitsBE::<Aes128, U12>::from_block_cipher_with_counter(
+        aes.clone(),
+        &nonce.into(),
+        0xffe,
+    )
+    .unwrap();
+    let mut buf = [0u8; 32];
+    cipher.apply_keystream(&mut buf);
+
+    let mut expected = [0u8; 32];
+    for (i, (chunk, ctr)) in expected.chunks_mut(16).zip(&[0xffeu16, 0xfff]).enumerate() {
+        let mut block = GenericArray::clone_from_slice(&nonce);
+        block[14] = 0xe0 | (ctr >> 8) as u8;
+        block[15] = *ctr as u8;
+        assert_eq!(block[..14], nonce[..14], "block {}", i);
+        aes.encrypt_block(&mut block);
+        chunk.copy_from_slice(&block);
+    }
+    assert_eq!(buf, expected);
+}
+
+#[test]
+fn counter_does_not_wrap() {
+    let nonce = hex!("00112233445566778899aabbccddee00");
+    let new = |initial_counter| {
+        let aes = Aes128::new(&KEY.into());
+        ctr::CtrBitsBE::<Aes128, U8>::from_block_cipher_with_counter(
+            aes,
+            &nonce.into(),
+            initial_counter,
+        )
+    };
+
+    assert!(new(0x100).is_err());
+
+    // counter values 0xfd, 0xfe and 0xff are usable
+    let mut cipher = new(0xfd).unwrap();
+    assert!(cipher.try_apply_keystream(&mut [0u8; 48]).is_ok());
+    assert!(cipher.try_apply_keystream(&mut [0u8; 1]).is_err());
+
+    let mut cipher = new(0xfd).unwrap();
+    assert!(cipher.try_apply_keystream(&mut [0u8; 49]).is_err());
+    assert!(cipher.try_apply_keystream(&mut [0u8; 40]).is_ok());
+    assert!(cipher.try_apply_keystream(&mut [0u8; 8]).is_ok());
+    assert!(cipher.try_apply_keystream(&mut [0u8; 1]).is_err());
+
+    let mut cipher = new(0).unwrap();
+    assert!(cipher.try_seek(255 * 16u32 + 15).is_ok());
+    assert!(cipher.try_seek(256 * 16u32).is_ok());
+    assert!(cipher.try_seek(256 * 16u32 + 1).is_err());
+    assert!(cipher.try_apply_keystream(&mut [0u8; 1]).is_err());
+}
+
+#[test]
+fn full_word_counter_always_fits() {
+    let aes = Aes128::new(&KEY.into());
+    let mut cipher =
+        ctr::Ctr32BE::<Aes128>::from_block_cipher_with_counter(aes, &IV.into(), u32::MAX).unwrap();
+    assert!(cipher.try_apply_keystream(&mut [0u8; 16]).is_ok());
+}
@@ -0,0 +1,74 @@
+//! The multi-block pipeline must produce the same keystream as encrypting
+//! one counter block at a time, for any length and split point
+
+use aes::{Aes128, BlockEncrypt, NewBlockCipher};
+use cipher::{generic_array::GenericArray, FromBlockCipher, StreamCipher};
+use hex_literal::hex;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+type Aes128Ctr32 = ctr::Ctr32BE<Aes128>;
+
+const KEY: [u8; 16] = hex!("000102030405060708090A0B0C0D0E0F");
+const NONCE: [u8; 16] = hex!("222222222222222222222222FFFFFFF0");
+
+const LEN: usize = 16 * 40 + 7;
+
+/// Reference keystream with a 32-bit big endian counter in the last 4 bytes
+fn reference_keystream() -> [u8; LEN] {
+    let cipher = Aes128::new(&KEY.into());
+    let ctr = u32::from_be_bytes([NONCE[12], NONCE[13], NONCE[14], NONCE[15]]);
+
+    let mut keystream = [0u8; LEN];
+    for (i, chunk) in keystream.chunks_mut(16).enumerate() {
+        let mut block = GenericArray::clone_from_slice(&NONCE);
+        block[12..].copy_from_slice(&ctr.wrapping_add(i as u32).to_be_bytes());
+        cipher.encrypt_block(&mut block);
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+    keystream
+}
+
+#[test]
+fn pipeline_matches_reference() {
+    let expected = reference_keystream();
+
+    for len in 0..LEN {
+        let mut ctr = Aes128Ctr32::from_block_cipher(Aes128::new(&KEY.into()), &NONCE.into());
+        let mut buf = [0u8; LEN];
+        ctr.apply_keystream(&mut buf[..len]);
+        assert_eq!(&buf[..len], &expected[..len]);
+    }
+}
+
+#[test]
+fn pipeline_split_points() {
+    let expected = reference_keystream();
+
+    for split in 0..LEN {
+        let mut ctr = Aes128Ctr32::from_block_cipher(Aes128::new(&KEY.into()), &NONCE.into());
+        let mut buf = [0u8; LEN];
+        let (l, r) = buf.split_at_mut(split);
+        ctr.apply_keystream(l);
+        ctr.apply_keystream(r);
+        assert_eq!(&buf[..], &expected[..]);
+    }
+}
+
+#[test]
+fn pipeline_128bit_carry() {
+    // the low 64 bits of the counter overflow part way through the pipeline
+    let nonce = hex!("0000000000000001FFFFFFFFFFFFFFFC");
+    let cipher = Aes128::new(&KEY.into());
+    let mut expected = [0u8; 16 * 8];
+    for (i, chunk) in expected.chunks_mut(16).enumerate() {
+        let ctr = u128::from_be_bytes(nonce) + i as u128;
+        let mut block = GenericArray::from(ctr.to_be_bytes());
+        cipher.encrypt_block(&mut block);
+        chunk.copy_from_slice(&block);
+    }
+
+    let mut ctr = Aes128Ctr::from_block_cipher(Aes128::new(&KEY.into()), &nonce.into());
+    let mut buf = [0u8; 16 * 8];
+    ctr.apply_keystream(&mut buf);
+    assert_eq!(&buf[..], &expected[..]);
+}
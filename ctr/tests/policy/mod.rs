@@ -0,0 +1,125 @@
+//! Overflow policies of the counter flavors: the counter portion of the IV
+//! starts 2 blocks before overflowing
+
+use aes::{Aes128, BlockEncrypt, NewBlockCipher};
+use cipher::{generic_array::GenericArray, NewCipher, StreamCipher, StreamCipherSeek};
+use ctr::policy::{CarryIntoNonce, Error, Wrap};
+use hex_literal::hex;
+
+const KEY: [u8; 16] = hex!("000102030405060708090A0B0C0D0E0F");
+const BASE: [u8; 16] = hex!("00112233445566778899aabbccddeeff");
+
+/// IV whose `n`-byte counter portion is 2 less than its maximum and whose
+/// adjacent nonce byte is `0xff`, so that a carry propagates past it
+fn iv(big_endian: bool, n: usize) -> [u8; 16] {
+    let mut iv = BASE;
+    if big_endian {
+        iv[16 - n..].iter_mut().for_each(|b| *b = 0xff);
+        iv[15] = 0xfe;
+        if n < 16 {
+            iv[15 - n] = 0xff;
+        }
+    } else {
+        iv[..n].iter_mut().for_each(|b| *b = 0xff);
+        iv[0] = 0xfe;
+        if n < 16 {
+            iv[n] = 0xff;
+        }
+    }
+    iv
+}
+
+/// Reference keystream for 3 blocks, modelling the block as a single 128-bit
+/// integer of which the low `n` bytes are the counter portion
+fn reference_keystream(big_endian: bool, n: usize, carry: bool) -> [u8; 48] {
+    let iv = iv(big_endian, n);
+    let value = if big_endian {
+        u128::from_be_bytes(iv)
+    } else {
+        u128::from_le_bytes(iv)
+    };
+    let mask = !0u128 >> (128 - 8 * n);
+
+    let cipher = Aes128::new(&KEY.into());
+    let mut keystream = [0u8; 48];
+    for (i, chunk) in keystream.chunks_mut(16).enumerate() {
+        let v = if carry {
+            value.wrapping_add(i as u128)
+        } else {
+            (value & !mask) | (value.wrapping_add(i as u128) & mask)
+        };
+        let bytes = if big_endian {
+            v.to_be_bytes()
+        } else {
+            v.to_le_bytes()
+        };
+        let mut block = GenericArray::clone_from_slice(&bytes);
+        cipher.encrypt_block(&mut block);
+        chunk.copy_from_slice(&block);
+    }
+    keystream
+}
+
+macro_rules! policy_tests {
+    ($name:ident, $ctr:ident, $big_endian:expr, $n:expr) => {
+        mod $name {
+            use super::*;
+
+            #[test]
+            fn wrap() {
+                let iv = iv($big_endian, $n);
+                let mut cipher = ctr::$ctr::<Aes128, Wrap>::new(&KEY.into(), &iv.into());
+                let mut buf = [0u8; 48];
+                cipher.apply_keystream(&mut buf);
+                assert_eq!(buf, reference_keystream($big_endian, $n, false));
+
+                // `Wrap` is the default policy
+                let mut cipher = ctr::$ctr::<Aes128>::new(&KEY.into(), &iv.into());
+                let mut buf2 = [0u8; 48];
+                cipher.apply_keystream(&mut buf2);
+                assert_eq!(buf, buf2);
+            }
+
+            #[test]
+            fn carry_into_nonce() {
+                let iv = iv($big_endian, $n);
+                let mut cipher = ctr::$ctr::<Aes128, CarryIntoNonce>::new(&KEY.into(), &iv.into());
+                let expected = reference_keystream($big_endian, $n, true);
+                for split in 0..48 {
+                    let mut buf = [0u8; 48];
+                    cipher.seek(0u32);
+                    let (l, r) = buf.split_at_mut(split);
+                    cipher.apply_keystream(l);
+                    cipher.apply_keystream(r);
+                    assert_eq!(buf[..], expected[..]);
+                }
+            }
+
+            #[test]
+            fn error() {
+                let iv = iv($big_endian, $n);
+                let expected = reference_keystream($big_endian, $n, false);
+
+                let mut cipher = ctr::$ctr::<Aes128, Error>::new(&KEY.into(), &iv.into());
+                let mut buf = [0u8; 32];
+                cipher.apply_keystream(&mut buf[..20]);
+                cipher.apply_keystream(&mut buf[20..]);
+                assert_eq!(buf[..], expected[..32]);
+                assert!(cipher.try_apply_keystream(&mut [0u8; 1]).is_err());
+
+                let mut cipher = ctr::$ctr::<Aes128, Error>::new(&KEY.into(), &iv.into());
+                assert!(cipher.try_apply_keystream(&mut [0u8; 33]).is_err());
+                assert!(cipher.try_seek(33u32).is_err());
+                assert!(cipher.try_seek(32u32).is_ok());
+                assert!(cipher.try_apply_keystream(&mut [0u8; 1]).is_err());
+            }
+        }
+    };
+}
+
+policy_tests!(ctr32be, Ctr32BE, true, 4);
+policy_tests!(ctr32le, Ctr32LE, false, 4);
+policy_tests!(ctr64be, Ctr64BE, true, 8);
+policy_tests!(ctr64le, Ctr64LE, false, 8);
+policy_tests!(ctr128be, Ctr128BE, true, 16);
+policy_tests!(ctr128le, Ctr128LE, false, 16);
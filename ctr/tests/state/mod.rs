@@ -0,0 +1,112 @@
+//! Serialized CTR states
+
+use aes::{Aes128, NewBlockCipher};
+use cipher::{
+    consts::{U32, U8},
+    generic_array::typenum::Unsigned,
+    BlockCipher, NewCipher, StreamCipher, StreamCipherSeek,
+};
+use ctr::policy::CarryIntoNonce;
+
+const KEY: [u8; 16] = *b"very secret key.";
+const NONCE: [u8; 16] = [0xff; 16];
+
+cipher_state::stream_state_test!(ctr128_be, ctr::Ctr128BE<Aes128>);
+cipher_state::stream_state_test!(ctr128_le, ctr::Ctr128LE<Aes128>);
+cipher_state::stream_state_test!(ctr64_be, ctr::Ctr64BE<Aes128>);
+cipher_state::stream_state_test!(ctr64_le, ctr::Ctr64LE<Aes128>);
+cipher_state::stream_state_test!(ctr32_be, ctr::Ctr32BE<Aes128>);
+cipher_state::stream_state_test!(ctr32_le, ctr::Ctr32LE<Aes128>);
+cipher_state::stream_state_test!(ctr32_be_carry, ctr::Ctr32BE<Aes128, CarryIntoNonce>);
+cipher_state::stream_state_test!(ctr64_le_carry, ctr::Ctr64LE<Aes128, CarryIntoNonce>);
+cipher_state::stream_state_test!(magma_ctr64, ctr::Ctr64BE<magma::Magma>);
+
+/// Seeking after restoring a state is relative to the initial counter
+/// block, including one with a separate starting counter
+#[test]
+fn seek_after_restore() {
+    let key = KEY.into();
+    let new = || {
+        ctr::CtrBitsBE::<Aes128, U32>::from_block_cipher_with_counter(
+            Aes128::new(&key),
+            &NONCE.into(),
+            7,
+        )
+        .unwrap()
+    };
+    let mut expected = [0u8; 100];
+    new().apply_keystream(&mut expected);
+
+    let mut cipher = new();
+    cipher.apply_keystream(&mut [0u8; 17]);
+    let mut buf = [0u8; 64];
+    let state = cipher.to_bytes(None, &mut buf).unwrap();
+    let mut restored = ctr::CtrBitsBE::<Aes128, U32>::from_bytes(state, Some(&key)).unwrap();
+    assert_eq!(restored.current_pos::<usize>(), 17);
+
+    let mut out = [0u8; 100];
+    restored.apply_keystream(&mut out[17..]);
+    assert_eq!(&out[17..], &expected[17..]);
+
+    let mut out = [0u8; 100];
+    restored.seek(0u8);
+    restored.apply_keystream(&mut out);
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn large_counter() {
+    let mut cipher = ctr::Ctr128BE::<Aes128>::new(&KEY.into(), &NONCE.into());
+    cipher.seek_block(!0u128 - 4);
+    cipher.apply_keystream(&mut [0u8; 5]);
+
+    let mut buf = [0u8; 64];
+    let state = cipher.to_bytes(None, &mut buf).unwrap();
+    let mut restored = ctr::Ctr128BE::<Aes128>::from_bytes(state, Some(&KEY.into())).unwrap();
+    assert_eq!(restored.current_block(), !0u128 - 4);
+
+    let mut expected = [0u8; 32];
+    cipher.apply_keystream(&mut expected);
+    let mut out = [0u8; 32];
+    restored.apply_keystream(&mut out);
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn rejects_counter_overflow() {
+    type Aes128Ctr = ctr::Ctr64BE<Aes128>;
+    let key = KEY.into();
+    let cipher = Aes128Ctr::new(&key, &NONCE.into());
+    let mut buf = [0u8; 64];
+    let len = cipher.to_bytes(None, &mut buf).unwrap().len();
+
+    // byte position past the end of the block
+    let mut state = buf;
+    state[len - 1] = 16;
+    assert!(Aes128Ctr::from_bytes(&state[..len], Some(&key)).is_err());
+
+    // block number which doesn't fit in a 64-bit counter
+    let mut state = buf;
+    state[len - 2] = 1;
+    assert!(Aes128Ctr::from_bytes(&state[..len], Some(&key)).is_err());
+
+    // `CtrBitsBE` counters don't wrap around
+    let aes = Aes128::new(&key);
+    let mut cipher =
+        ctr::CtrBitsBE::<Aes128, U8>::from_block_cipher_with_counter(aes, &NONCE.into(), 0)
+            .unwrap();
+    cipher.seek(255 * 16 + 1);
+    let len = cipher.to_bytes(None, &mut buf).unwrap().len();
+    let mut state = buf;
+    state[len - 17] = 0;
+    state[len - 16] = 1;
+    assert!(ctr::CtrBitsBE::<Aes128, U8>::from_bytes(&state[..len], Some(&key)).is_err());
+}
+
+#[test]
+fn magma_state_len() {
+    assert_eq!(
+        ctr::Ctr64BE::<magma::Magma>::state_len(true),
+        2 + 32 + <magma::Magma as BlockCipher>::BlockSize::USIZE + 17
+    );
+}
@@ -1,4 +1,12 @@
 //! HC-256 Stream Cipher
+//!
+//! The `state` feature provides `to_bytes` and `from_bytes` methods which
+//! save and restore the IV and keystream position of a cipher.
+//!
+//! The `io` feature re-exports the `StreamReader` and `StreamWriter` adapters
+//! of the [`cipher-io`](https://docs.rs/cipher-io) crate, which apply the
+//! keystream to the data read from a `std::io::Read` or written to a
+//! `std::io::Write` implementation.
 
 #![no_std]
 #![doc(
@@ -11,12 +19,21 @@
 pub use cipher;
 
 use cipher::{
-    consts::U32, errors::LoopError, generic_array::GenericArray, NewCipher, StreamCipher,
+    consts::U32,
+    errors::{LoopError, OverflowError},
+    generic_array::GenericArray,
+    NewCipher, SeekNum, StreamCipher, StreamCipherSeek,
 };
 
-#[cfg(cargo_feature = "zeroize")]
-use std::ops::Drop;
-#[cfg(cargo_feature = "zeroize")]
+#[cfg(feature = "io")]
+pub use cipher_io::{StreamReader, StreamWriter};
+
+#[cfg(feature = "state")]
+pub use cipher_state::StateError;
+
+#[cfg(feature = "state")]
+use cipher_state::{SeekableState, POSITION_LEN};
+#[cfg(feature = "zeroize")]
 use zeroize::Zeroize;
 
 const TABLE_SIZE: usize = 1024;
@@ -26,14 +43,33 @@ const KEY_BITS: usize = 256;
 const KEY_WORDS: usize = KEY_BITS / 32;
 const IV_BITS: usize = 256;
 const IV_WORDS: usize = IV_BITS / 32;
+const IV_BYTES: usize = IV_BITS / 8;
+const WORD_BYTES: u8 = 4;
+
+/// Length of the serialized state following the key: IV, word number and
+/// byte position
+#[cfg(feature = "state")]
+const STATE_BODY_LEN: usize = IV_BYTES + POSITION_LEN;
 
 /// The HC-256 stream cipher
+///
+/// Besides the key-dependent tables it keeps a copy of the IV it was
+/// initialized with, which is only needed by `to_bytes` (`state` feature).
+/// Keep this in mind if the IV is treated as secret: it stays in memory for
+/// as long as the cipher does, and is wiped along with the tables under the
+/// `zeroize` feature.
+#[derive(Clone)]
 pub struct Hc256 {
     ptable: [u32; TABLE_SIZE],
     qtable: [u32; TABLE_SIZE],
     word: u32,
     idx: u32,
     offset: u8,
+    /// Number of keystream words generated since initialization
+    counter: u64,
+    /// IV the cipher was initialized with, saved by `to_bytes`
+    #[cfg_attr(not(feature = "state"), allow(dead_code))]
+    iv: [u8; IV_BYTES],
 }
 
 impl NewCipher for Hc256 {
@@ -51,7 +87,46 @@ impl NewCipher for Hc256 {
 
 impl StreamCipher for Hc256 {
     fn try_apply_keystream(&mut self, data: &mut [u8]) -> Result<(), LoopError> {
-        self.process(data);
+        self.process(data, |b, k| *b ^= k);
+        Ok(())
+    }
+}
+
+impl StreamCipherSeek for Hc256 {
+    fn try_current_pos<T: SeekNum>(&self) -> Result<T, OverflowError> {
+        match self.counter.checked_sub(1) {
+            Some(block) if self.offset < WORD_BYTES => {
+                T::from_block_byte(block, self.offset, WORD_BYTES)
+            }
+            _ => T::from_block_byte(self.counter, 0, WORD_BYTES),
+        }
+    }
+
+    /// Seek to the given keystream position.
+    ///
+    /// HC-256 has no random access to its keystream, so seeking is performed
+    /// by generating and discarding the words in between. Only seeking
+    /// forward is supported: seeking to a position before the current one
+    /// returns [`LoopError`].
+    fn try_seek<T: SeekNum>(&mut self, pos: T) -> Result<(), LoopError> {
+        let (block, byte): (u64, u8) = pos.to_block_byte(WORD_BYTES).map_err(|_| LoopError)?;
+
+        // a partially consumed word has already been generated
+        let (counter, offset) = if byte == 0 {
+            (block, WORD_BYTES)
+        } else {
+            (block.checked_add(1).ok_or(LoopError)?, byte)
+        };
+
+        if (counter, offset) < (self.counter, self.offset) {
+            return Err(LoopError);
+        }
+
+        while self.counter < counter {
+            self.word = self.gen_word();
+        }
+
+        self.offset = offset;
         Ok(())
     }
 }
@@ -64,11 +139,14 @@ impl Hc256 {
             word: 0,
             idx: 0,
             offset: 0,
+            counter: 0,
+            iv: [0; IV_BYTES],
         }
     }
 
     fn init(&mut self, key: &[u8], iv: &[u8]) {
         let mut data = [0; INIT_SIZE];
+        self.iv.copy_from_slice(iv);
 
         for i in 0..KEY_WORDS {
             data[i] = key[4 * i] as u32 & 0xff
@@ -97,18 +175,19 @@ impl Hc256 {
 
         self.idx = 0;
 
-        #[cfg(cargo_feature = "zeroize")]
-        data.zeroize();
+        #[cfg(feature = "zeroize")]
+        data[..].zeroize();
 
         for _ in 0..4096 {
             self.gen_word();
         }
 
         // This forces generation of the first block
-        #[cfg(cargo_feature = "zeroize")]
+        #[cfg(feature = "zeroize")]
         self.word.zeroize();
 
         self.offset = 4;
+        self.counter = 0;
     }
 
     #[inline]
@@ -145,6 +224,7 @@ impl Hc256 {
 
         self.offset = 0;
         self.idx = (self.idx + 1) & (2048 - 1);
+        self.counter = self.counter.wrapping_add(1);
 
         if i < 1024 {
             self.ptable[j] = self.ptable[j]
@@ -167,16 +247,23 @@ impl Hc256 {
         }
     }
 
-    fn process(&mut self, data: &mut [u8]) {
+    /// Combine `data` with the keystream using `op`, e.g. XOR, a byte at a
+    /// time.
+    fn process(&mut self, data: &mut [u8], op: impl Fn(&mut u8, u8)) {
         let mut i = 0;
         let mut word: u32 = self.word;
 
         // First, use the remaining part of the current word.
-        for j in self.offset..4 {
-            data[i] ^= ((word >> (j * 8)) & 0xff) as u8;
+        while self.offset < 4 && i < data.len() {
+            op(&mut data[i], (word >> (self.offset * 8)) as u8);
+            self.offset += 1;
             i += 1;
         }
 
+        if i == data.len() {
+            return;
+        }
+
         let mainlen = (data.len() - i) / 4;
         let leftover = (data.len() - i) % 4;
 
@@ -185,7 +272,7 @@ impl Hc256 {
             word = self.gen_word();
 
             for j in 0..4 {
-                data[i] ^= ((word >> (j * 8)) & 0xff) as u8;
+                op(&mut data[i], (word >> (j * 8)) as u8);
                 i += 1;
             }
         }
@@ -195,7 +282,7 @@ impl Hc256 {
             word = self.gen_word();
 
             for j in 0..leftover {
-                data[i] ^= ((word >> (j * 8)) & 0xff) as u8;
+                op(&mut data[i], (word >> (j * 8)) as u8);
                 i += 1;
             }
 
@@ -208,18 +295,81 @@ impl Hc256 {
     }
 }
 
-#[cfg(cargo_feature = "zeroize")]
+impl Hc256 {
+    /// Write keystream to `out`, overwriting its contents.
+    ///
+    /// The output and the position of the cipher afterwards are the same as
+    /// for [`StreamCipher::apply_keystream`] on a zeroed buffer, without
+    /// paying for the XOR.
+    pub fn write_keystream(&mut self, out: &mut [u8]) {
+        self.process(out, |b, k| *b = k);
+    }
+}
+
+#[cfg(feature = "state")]
+impl Hc256 {
+    /// Length of the state serialized by [`Hc256::to_bytes`], with or
+    /// without the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(KEY_BITS / 8, STATE_BODY_LEN, with_key)
+    }
+
+    /// Serialize the IV and keystream position into `buf`, returning the
+    /// written prefix of it.
+    ///
+    /// The cipher does not retain its key, so it's included only if passed
+    /// as `key`, which must be the key the cipher was initialized with. The
+    /// key and IV recreate the whole keystream, so a state including the key
+    /// has to be protected like the key; without it the state is no more
+    /// secret than the IV.
+    ///
+    /// The format is a version byte, a flags byte, the key if included, the
+    /// IV, then the number of the current keystream word as a little endian
+    /// `u64` and the byte position within that word.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&GenericArray<u8, U32>>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let pos = self.current_pos::<u128>();
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write_seekable(buf, key, &self.iv, pos, WORD_BYTES)
+    }
+
+    /// Restore a cipher from a state serialized by [`Hc256::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    ///
+    /// HC-256 has no random access to its keystream, so the keystream up to
+    /// the saved position is regenerated, as with seeking.
+    pub fn from_bytes(
+        bytes: &[u8],
+        key: Option<&GenericArray<u8, U32>>,
+    ) -> Result<Self, StateError> {
+        let state = SeekableState::read(bytes, KEY_BITS / 8, IV_BYTES, WORD_BYTES)?;
+        let key = state.key(key.map(|k| k.as_slice()))?;
+
+        let mut cipher = Hc256::create();
+        cipher.init(key, state.nonce());
+        cipher.try_seek(state.pos()).map_err(|_| StateError)?;
+        Ok(cipher)
+    }
+}
+
+#[cfg(feature = "zeroize")]
 impl Zeroize for Hc256 {
     fn zeroize(&mut self) {
-        self.ptable.zeroize();
-        self.qtable.zeroize();
+        self.ptable[..].zeroize();
+        self.qtable[..].zeroize();
         self.word.zeroize();
         self.idx.zeroize();
         self.offset.zeroize();
+        self.counter.zeroize();
+        self.iv.zeroize();
     }
 }
 
-#[cfg(cargo_feature = "zeroize")]
+#[cfg(feature = "zeroize")]
 impl Drop for Hc256 {
     fn drop(&mut self) {
         self.zeroize();
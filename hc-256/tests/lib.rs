@@ -1,4 +1,4 @@
-use cipher::{generic_array::GenericArray, NewCipher, StreamCipher};
+use cipher::{generic_array::GenericArray, NewCipher, StreamCipher, StreamCipherSeek};
 use hc_256::Hc256;
 
 #[cfg(test)]
@@ -63,6 +63,23 @@ fn test_key0_iv0() {
     }
 }
 
+#[test]
+fn test_write_keystream() {
+    let mut cipher = Hc256::new(
+        &GenericArray::from(PAPER_KEY0),
+        &GenericArray::from(PAPER_IV0),
+    );
+    let mut buf = [0xff; 64];
+
+    cipher.write_keystream(&mut buf[..1]);
+    buf[1..7].copy_from_slice(&[0; 6]);
+    cipher.apply_keystream(&mut buf[1..7]);
+    cipher.write_keystream(&mut buf[7..]);
+    assert_eq!(cipher.current_pos::<usize>(), 64);
+
+    assert_eq!(&buf[..], &EXPECTED_PAPER_KEY0_IV0[..]);
+}
+
 #[test]
 fn test_key0_iv0_offset_1() {
     let mut cipher = Hc256::new(
@@ -260,3 +277,153 @@ fn test_key0_iv1() {
         assert_eq!(buf[i], EXPECTED_PAPER_KEY0_IV1[i])
     }
 }
+
+#[test]
+fn test_seek() {
+    const MAX_SEEK: usize = 256;
+
+    let key = GenericArray::from(PAPER_KEY1);
+    let iv = GenericArray::from(PAPER_IV1);
+
+    let mut ct = [0u8; MAX_SEEK];
+    Hc256::new(&key, &iv).apply_keystream(&mut ct);
+
+    for n in 0..MAX_SEEK {
+        let mut cipher = Hc256::new(&key, &iv);
+        assert_eq!(cipher.current_pos::<u64>(), 0);
+        cipher.seek(n as u64);
+        assert_eq!(cipher.current_pos::<u64>(), n as u64);
+
+        let mut buf = [0u8; MAX_SEEK];
+        cipher.apply_keystream(&mut buf[n..]);
+        assert_eq!(cipher.current_pos::<u64>(), MAX_SEEK as u64);
+        assert_eq!(&buf[n..], &ct[n..]);
+    }
+}
+
+#[test]
+fn test_seek_from_offset() {
+    let key = GenericArray::from(PAPER_KEY0);
+    let iv = GenericArray::from(PAPER_IV0);
+
+    for start in 0..16 {
+        for target in start..40 {
+            let mut cipher = Hc256::new(&key, &iv);
+            let mut buf = [0u8; 64];
+            cipher.apply_keystream(&mut buf[..start]);
+            assert_eq!(cipher.current_pos::<u64>(), start as u64);
+
+            cipher.seek(target as u64);
+            assert_eq!(cipher.current_pos::<u64>(), target as u64);
+            cipher.apply_keystream(&mut buf[target..]);
+            assert_eq!(&buf[target..], &EXPECTED_PAPER_KEY0_IV0[target..]);
+        }
+    }
+}
+
+#[test]
+fn test_current_pos() {
+    let mut cipher = Hc256::new(
+        &GenericArray::from(PAPER_KEY0),
+        &GenericArray::from(PAPER_IV0),
+    );
+    let mut buf = [0u8; 7];
+    let mut pos = 0;
+
+    for n in 0..buf.len() {
+        cipher.apply_keystream(&mut buf[..n]);
+        pos += n as u64;
+        assert_eq!(cipher.current_pos::<u64>(), pos);
+    }
+}
+
+#[test]
+fn test_seek_backwards() {
+    let mut cipher = Hc256::new(
+        &GenericArray::from(PAPER_KEY0),
+        &GenericArray::from(PAPER_IV0),
+    );
+    cipher.seek(10u64);
+    assert!(cipher.try_seek(9u64).is_err());
+    assert_eq!(cipher.current_pos::<u64>(), 10);
+    assert!(cipher.try_seek(10u64).is_ok());
+
+    let mut buf = [0u8; 54];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(&buf[..], &EXPECTED_PAPER_KEY0_IV0[10..]);
+}
+
+#[test]
+fn test_clone() {
+    let mut cipher = Hc256::new(
+        &GenericArray::from(PAPER_KEY0),
+        &GenericArray::from(PAPER_IV0),
+    );
+    let mut buf = [0u8; 64];
+    cipher.apply_keystream(&mut buf[..13]);
+
+    let mut clone = cipher.clone();
+    assert_eq!(clone.current_pos::<u64>(), 13);
+    clone.apply_keystream(&mut buf[13..]);
+    assert_eq!(&buf[..], &EXPECTED_PAPER_KEY0_IV0[..]);
+
+    // the original cipher is unaffected by the clone
+    let mut buf = [0u8; 51];
+    cipher.apply_keystream(&mut buf);
+    assert_eq!(&buf[..], &EXPECTED_PAPER_KEY0_IV0[13..]);
+}
+
+#[cfg(feature = "state")]
+cipher_state::stream_state_test!(test_state, Hc256);
+
+#[cfg(feature = "zeroize")]
+mod zeroize {
+    use super::{PAPER_IV0, PAPER_KEY0};
+    use cipher::{generic_array::GenericArray, NewCipher, StreamCipher, StreamCipherSeek};
+    use core::{
+        mem::{self, MaybeUninit},
+        ptr, slice,
+    };
+    use hc_256::Hc256;
+    use zeroize::Zeroize;
+
+    /// Check that `cipher` is in the all-zero state, which produces an
+    /// all-zero keystream (the P and Q tables are both zero).
+    fn assert_cleared(mut cipher: Hc256) {
+        let mut buf = [0u8; 4096];
+        cipher.apply_keystream(&mut buf);
+        assert!(buf.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_zeroize() {
+        let mut cipher = Hc256::new(
+            &GenericArray::from(PAPER_KEY0),
+            &GenericArray::from(PAPER_IV0),
+        );
+        let mut buf = [0u8; 64];
+        cipher.apply_keystream(&mut buf);
+        assert!(buf.iter().any(|&b| b != 0));
+
+        cipher.zeroize();
+        assert_eq!(cipher.current_pos::<u64>(), 0);
+        assert_cleared(cipher);
+    }
+
+    #[test]
+    fn test_drop() {
+        let cipher = Hc256::new(
+            &GenericArray::from(PAPER_KEY0),
+            &GenericArray::from(PAPER_IV0),
+        );
+        let mut slot = MaybeUninit::new(cipher);
+
+        // inspect the memory the cipher occupied after it has been dropped,
+        // without turning it back into an `Hc256`
+        let bytes = unsafe {
+            ptr::drop_in_place(slot.as_mut_ptr());
+            slice::from_raw_parts(slot.as_ptr() as *const u8, mem::size_of::<Hc256>())
+        };
+        assert!(bytes.iter().all(|&b| b == 0));
+    }
+}
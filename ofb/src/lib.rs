@@ -42,6 +42,46 @@
 //! assert_eq!(buffer, &ciphertext[..]);
 //! ```
 //!
+//! OFB keystream can only be generated sequentially, so seeking generates and
+//! discards keystream blocks: forward from the current block, or from the
+//! start of the keystream when seeking backwards. Seeking is therefore
+//! O(n) in the distance covered and as slow as encrypting that much data.
+//! To resume processing of a long message at a checkpoint without seeking,
+//! take a snapshot of the state with [`Ofb::state`] and restore it later with
+//! [`Ofb::from_state`]:
+//!
+//! ```
+//! use aes::{Aes128, NewBlockCipher};
+//! use ofb::Ofb;
+//! use ofb::cipher::{FromBlockCipher, NewCipher, StreamCipher, StreamCipherSeek};
+//!
+//! type AesOfb = Ofb<Aes128>;
+//!
+//! let key = b"very secret key.";
+//! let iv = b"unique init vect";
+//! let mut buffer = [0u8; 100];
+//!
+//! let mut cipher = AesOfb::new(key.into(), iv.into());
+//! cipher.apply_keystream(&mut buffer[..42]);
+//! let checkpoint = cipher.state();
+//! cipher.apply_keystream(&mut buffer[42..]);
+//!
+//! let mut buffer2 = buffer;
+//! let aes = Aes128::new(key.into());
+//! let mut cipher = AesOfb::from_state(aes, checkpoint);
+//! assert_eq!(cipher.current_pos::<u64>(), 42);
+//! cipher.apply_keystream(&mut buffer2[42..]);
+//! assert!(buffer2[42..].iter().all(|&b| b == 0));
+//! ```
+//!
+//! The `state` feature provides `to_bytes` and `from_bytes` methods which
+//! serialize such a snapshot, optionally together with the key.
+//!
+//! The `io` feature re-exports the `StreamReader` and `StreamWriter` adapters
+//! of the [`cipher-io`](https://docs.rs/cipher-io) crate, which apply the
+//! keystream to the data read from a `std::io::Read` or written to a
+//! `std::io::Write` implementation.
+//!
 //! [1]: https://en.wikipedia.org/wiki/Block_cipher_mode_of_operation#OFB
 //! [2]: https://en.wikipedia.org/wiki/Stream_cipher#Synchronous_stream_ciphers
 
@@ -56,64 +96,248 @@
 pub use cipher;
 
 use cipher::{
-    errors::LoopError,
+    errors::{LoopError, OverflowError},
     generic_array::{typenum::Unsigned, GenericArray},
-    Block, BlockCipher, BlockEncrypt, FromBlockCipher, StreamCipher,
+    Block, BlockCipher, BlockEncrypt, FromBlockCipher, SeekNum, StreamCipher, StreamCipherSeek,
 };
 
+#[cfg(feature = "io")]
+pub use cipher_io::{StreamReader, StreamWriter};
+
+#[cfg(feature = "state")]
+pub use cipher_state::StateError;
+
+#[cfg(feature = "state")]
+use cipher::{BlockCipherKey, NewBlockCipher};
+#[cfg(feature = "state")]
+use cipher_state::State;
+#[cfg(feature = "state")]
+use core::convert::TryInto;
+
 /// OFB self-synchronizing stream cipher instance.
 pub struct Ofb<C: BlockCipher> {
     cipher: C,
+    state: OfbState<C>,
+}
+
+/// Snapshot of the keystream position of an [`Ofb`] instance.
+///
+/// It does not include the block cipher, so restoring it with
+/// [`Ofb::from_state`] requires a block cipher initialized with the same key.
+pub struct OfbState<C: BlockCipher> {
+    /// First block of keystream, used for seeking backwards
+    first: Block<C>,
+    /// Current block of keystream
     block: Block<C>,
+    /// Index of `block` in the keystream
+    block_idx: u64,
+    /// Position in `block`
     pos: usize,
 }
 
-impl<C> FromBlockCipher for Ofb<C>
-where
-    C: BlockCipher + BlockEncrypt,
-{
-    type BlockCipher = C;
-    type NonceSize = C::BlockSize;
+impl<C: BlockCipher> Clone for OfbState<C> {
+    fn clone(&self) -> Self {
+        Self {
+            first: self.first.clone(),
+            block: self.block.clone(),
+            block_idx: self.block_idx,
+            pos: self.pos,
+        }
+    }
+}
 
-    fn from_block_cipher(cipher: C, iv: &GenericArray<u8, Self::NonceSize>) -> Self {
-        let mut block = iv.clone();
-        cipher.encrypt_block(&mut block);
+impl<C: BlockCipher + Clone> Clone for Ofb<C> {
+    fn clone(&self) -> Self {
         Self {
-            cipher,
-            block,
-            pos: 0,
+            cipher: self.cipher.clone(),
+            state: self.state.clone(),
         }
     }
 }
 
-impl<C: BlockCipher + BlockEncrypt> StreamCipher for Ofb<C> {
-    fn try_apply_keystream(&mut self, mut data: &mut [u8]) -> Result<(), LoopError> {
+impl<C: BlockCipher + BlockEncrypt> Ofb<C> {
+    /// Get a snapshot of the current keystream position.
+    pub fn state(&self) -> OfbState<C> {
+        self.state.clone()
+    }
+
+    /// Create an instance which resumes the keystream at a snapshot taken
+    /// with [`Ofb::state`].
+    pub fn from_state(cipher: C, state: OfbState<C>) -> Self {
+        Self { cipher, state }
+    }
+
+    /// Write keystream to `out`, overwriting its contents.
+    ///
+    /// The output and the position of the cipher afterwards are the same as
+    /// for [`StreamCipher::apply_keystream`] on a zeroed buffer, without
+    /// paying for the XOR.
+    pub fn write_keystream(&mut self, out: &mut [u8]) {
+        self.process(out, copy);
+    }
+
+    /// Combine `data` with the keystream using `op`, e.g. XOR.
+    fn process(&mut self, mut data: &mut [u8], op: impl Fn(&mut [u8], &[u8])) {
         let bs = C::BlockSize::to_usize();
         let n = data.len();
+        let state = &mut self.state;
 
-        if n < bs - self.pos {
-            xor(data, &self.block[self.pos..self.pos + n]);
-            self.pos += n;
-            return Ok(());
+        if n < bs - state.pos {
+            op(data, &state.block[state.pos..state.pos + n]);
+            state.pos += n;
+            return;
         }
 
-        let (left, right) = { data }.split_at_mut(bs - self.pos);
+        let (left, right) = { data }.split_at_mut(bs - state.pos);
         data = right;
-        let mut block = self.block.clone();
-        xor(left, &block[self.pos..]);
+        let mut block = state.block.clone();
+        op(left, &block[state.pos..]);
         self.cipher.encrypt_block(&mut block);
 
         let mut chunks = data.chunks_exact_mut(bs);
         for chunk in &mut chunks {
-            xor(chunk, &block);
+            op(chunk, &block);
             self.cipher.encrypt_block(&mut block);
         }
 
         let rem = chunks.into_remainder();
-        xor(rem, &block[..rem.len()]);
-        self.block = block;
-        self.pos = rem.len();
+        op(rem, &block[..rem.len()]);
+        state.block = block;
+        state.block_idx = state
+            .block_idx
+            .wrapping_add(1 + (n - left.len()) as u64 / bs as u64);
+        state.pos = rem.len();
+    }
+}
+
+#[cfg(feature = "state")]
+impl<C: BlockCipher + BlockEncrypt + NewBlockCipher> Ofb<C> {
+    /// Length of the state serialized by [`Ofb::to_bytes`], with or without
+    /// the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(C::KeySize::USIZE, Self::state_body_len(), with_key)
+    }
+
+    /// Serialize the keystream state into `buf`, returning the written
+    /// prefix of it.
+    ///
+    /// The block cipher does not expose its key, so it's included only if
+    /// passed as `key`, which must be the key the block cipher was
+    /// initialized with. Without the key the state still contains two blocks
+    /// of keystream, the first and the current one, which decrypt the
+    /// matching blocks of the message.
+    ///
+    /// The format is a version byte, a flags byte, the key if included, the
+    /// first and the current keystream block, then the index of the current
+    /// block as a little endian `u64` and the byte position within it.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&BlockCipherKey<C>>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let bs = C::BlockSize::USIZE;
+        let state = &self.state;
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write(buf, key, 0, Self::state_body_len(), |body| {
+            body[..bs].copy_from_slice(&state.first);
+            body[bs..2 * bs].copy_from_slice(&state.block);
+            body[2 * bs..2 * bs + 8].copy_from_slice(&state.block_idx.to_le_bytes());
+            body[2 * bs + 8] = state.pos as u8;
+            Ok(())
+        })
+    }
+
+    /// Restore an instance from a state serialized by [`Ofb::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    pub fn from_bytes(bytes: &[u8], key: Option<&BlockCipherKey<C>>) -> Result<Self, StateError> {
+        let bs = C::BlockSize::USIZE;
+        let state = State::read(bytes, C::KeySize::USIZE, Self::state_body_len(), 0)?;
+        let body = state.body();
+
+        let cipher =
+            C::new_from_slice(state.key(key.map(|k| k.as_slice()))?).map_err(|_| StateError)?;
+        let pos = body[2 * bs + 8] as usize;
+        if pos >= bs {
+            return Err(StateError);
+        }
+        let state = OfbState {
+            first: GenericArray::clone_from_slice(&body[..bs]),
+            block: GenericArray::clone_from_slice(&body[bs..2 * bs]),
+            block_idx: u64::from_le_bytes(body[2 * bs..2 * bs + 8].try_into().unwrap()),
+            pos,
+        };
+        Ok(Self { cipher, state })
+    }
+
+    /// Length of the serialized state following the key: the first and the
+    /// current keystream block, block index and byte position
+    fn state_body_len() -> usize {
+        2 * C::BlockSize::USIZE + 8 + 1
+    }
+}
+
+impl<C> FromBlockCipher for Ofb<C>
+where
+    C: BlockCipher + BlockEncrypt,
+{
+    type BlockCipher = C;
+    type NonceSize = C::BlockSize;
+
+    fn from_block_cipher(cipher: C, iv: &GenericArray<u8, Self::NonceSize>) -> Self {
+        let mut block = iv.clone();
+        cipher.encrypt_block(&mut block);
+        Self {
+            cipher,
+            state: OfbState {
+                first: block.clone(),
+                block,
+                block_idx: 0,
+                pos: 0,
+            },
+        }
+    }
+}
 
+impl<C: BlockCipher + BlockEncrypt> StreamCipher for Ofb<C> {
+    fn try_apply_keystream(&mut self, data: &mut [u8]) -> Result<(), LoopError> {
+        self.process(data, xor);
+        Ok(())
+    }
+}
+
+impl<C: BlockCipher + BlockEncrypt> StreamCipherSeek for Ofb<C> {
+    fn try_current_pos<T: SeekNum>(&self) -> Result<T, OverflowError> {
+        let bs = C::BlockSize::U8;
+        T::from_block_byte(self.state.block_idx, self.state.pos as u8, bs)
+    }
+
+    /// Seek to the given position in the keystream.
+    ///
+    /// OFB keystream blocks can only be computed one after another, so this
+    /// costs one block encryption per block skipped: seeking within the
+    /// current block is free, seeking forward encrypts the blocks from the
+    /// current one to the target, and seeking backwards starts over from the
+    /// first block, i.e. encrypts as many blocks as the target position is
+    /// from the start of the keystream. Seeking to a position gigabytes
+    /// away takes as long as encrypting that much data.
+    fn try_seek<T: SeekNum>(&mut self, pos: T) -> Result<(), LoopError> {
+        let (block_idx, pos): (u64, u8) = pos.to_block_byte(C::BlockSize::U8)?;
+        let state = &mut self.state;
+        if block_idx == state.block_idx {
+            state.pos = pos as usize;
+            return Ok(());
+        }
+        // Seek forward from the current block if possible, otherwise restart
+        if block_idx < state.block_idx {
+            state.block = state.first.clone();
+            state.block_idx = 0;
+        }
+        for _ in state.block_idx..block_idx {
+            self.cipher.encrypt_block(&mut state.block);
+        }
+        state.block_idx = block_idx;
+        state.pos = pos as usize;
         Ok(())
     }
 }
@@ -125,3 +349,8 @@ fn xor(buf1: &mut [u8], buf2: &[u8]) {
         *a ^= *b;
     }
 }
+
+#[inline(always)]
+fn copy(buf1: &mut [u8], buf2: &[u8]) {
+    buf1.copy_from_slice(buf2);
+}
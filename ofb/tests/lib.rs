@@ -1 +1,87 @@
 cipher::stream_cipher_test!(ofb_aes128, ofb::Ofb<aes::Aes128>, "aes128");
+cipher::stream_cipher_seek_test!(ofb_aes128_seek, ofb::Ofb<aes::Aes128>);
+
+#[cfg(feature = "state")]
+cipher_state::stream_state_test!(ofb_aes128_state, ofb::Ofb<aes::Aes128>);
+
+/// Seeking backwards after restoring a state starts over from the first
+/// block, which the state keeps
+#[cfg(feature = "state")]
+#[test]
+fn ofb_aes128_state_seek_backwards() {
+    use aes::Aes128;
+    use ofb::cipher::{NewCipher, StreamCipher, StreamCipherSeek};
+    use ofb::Ofb;
+
+    let key = [0x42; 16].into();
+    let iv = [0x24; 16].into();
+    let mut expected = [0u8; 100];
+    Ofb::<Aes128>::new(&key, &iv).apply_keystream(&mut expected);
+
+    let mut cipher = Ofb::<Aes128>::new(&key, &iv);
+    cipher.apply_keystream(&mut [0u8; 42]);
+    let mut buf = [0u8; 64];
+    let state = cipher.to_bytes(None, &mut buf).unwrap();
+    let mut restored = Ofb::<Aes128>::from_bytes(state, Some(&key)).unwrap();
+    assert_eq!(restored.current_pos::<usize>(), 42);
+
+    let mut out = [0u8; 100];
+    restored.seek(0u8);
+    restored.apply_keystream(&mut out);
+    assert_eq!(&out[..], &expected[..]);
+}
+
+#[test]
+fn ofb_aes128_write_keystream() {
+    use aes::Aes128;
+    use ofb::cipher::{NewCipher, StreamCipher, StreamCipherSeek};
+    use ofb::Ofb;
+
+    let new = || Ofb::<Aes128>::new(&[0x42; 16].into(), &[0x24; 16].into());
+    let mut expected = [0u8; 100];
+    new().apply_keystream(&mut expected);
+
+    let mut cipher = new();
+    let mut buf = [0xffu8; 100];
+    let mut pos = 0;
+    for (i, &len) in [1, 15, 16, 17, 7, 44].iter().enumerate() {
+        let chunk = &mut buf[pos..pos + len];
+        if i % 2 == 0 {
+            cipher.write_keystream(chunk);
+        } else {
+            chunk.iter_mut().for_each(|b| *b = 0);
+            cipher.apply_keystream(chunk);
+        }
+        pos += len;
+        assert_eq!(cipher.current_pos::<usize>(), pos);
+    }
+    assert_eq!(&buf[..], &expected[..]);
+}
+
+#[cfg(feature = "state")]
+#[test]
+fn ofb_aes128_seek_forward_from_current_block() {
+    use aes::Aes128;
+    use ofb::cipher::{NewCipher, StreamCipher, StreamCipherSeek};
+    use ofb::Ofb;
+
+    let key = [0x42; 16].into();
+    let iv = [0x24; 16].into();
+    let mut expected = [0u8; 100];
+    Ofb::<Aes128>::new(&key, &iv).apply_keystream(&mut expected);
+
+    let mut cipher = Ofb::<Aes128>::new(&key, &iv);
+    cipher.apply_keystream(&mut [0u8; 20]);
+    let mut buf = [0u8; 64];
+    let len = cipher.to_bytes(None, &mut buf).unwrap().len();
+    // clobber the first keystream block, which only backward seeks use
+    buf[2..18].iter_mut().for_each(|b| *b = 0);
+
+    for &pos in &[20usize, 31, 32, 57] {
+        let mut restored = Ofb::<Aes128>::from_bytes(&buf[..len], Some(&key)).unwrap();
+        restored.seek(pos);
+        let mut out = [0u8; 100];
+        restored.apply_keystream(&mut out[pos..]);
+        assert_eq!(&out[pos..], &expected[pos..]);
+    }
+}
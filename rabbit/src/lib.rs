@@ -1,5 +1,18 @@
 //! This crate implements the Rabbit Stream Cipher Algorithm as described in [RFC 4503][1]
 //!
+//! Seeking ([`cipher::StreamCipherSeek`]) is supported, but the state can
+//! only be advanced one 16-byte block at a time, so a seek is O(n) in the
+//! distance covered, and seeking backwards starts over from the beginning
+//! of the keystream.
+//!
+//! The `state` feature provides `to_bytes` and `from_bytes` methods which
+//! save and restore the IV and keystream position of a cipher.
+//!
+//! The `io` feature re-exports the `StreamReader` and `StreamWriter` adapters
+//! of the [`cipher-io`](https://docs.rs/cipher-io) crate, which apply the
+//! keystream to the data read from a `std::io::Read` or written to a
+//! `std::io::Write` implementation.
+//!
 //! [1]: https://tools.ietf.org/html/rfc4503#section-2.3
 
 #![no_std]
@@ -10,14 +23,25 @@ pub use cipher;
 
 use cipher::{
     consts::{U16, U8},
-    errors::LoopError,
-    NewCipher, StreamCipher,
+    errors::{LoopError, OverflowError},
+    NewCipher, SeekNum, StreamCipher, StreamCipherSeek,
 };
 #[cfg(feature = "zeroize")]
 use zeroize::Zeroize;
 
 use core::{cmp::min, fmt, mem::replace};
 
+#[cfg(feature = "io")]
+pub use cipher_io::{StreamReader, StreamWriter};
+
+#[cfg(feature = "state")]
+pub use cipher_state::StateError;
+
+#[cfg(feature = "state")]
+use cipher_state::POSITION_LEN;
+#[cfg(feature = "state")]
+use core::convert::TryInto;
+
 /// RFC 4503. 2.3.  Key Setup Scheme (page 2).
 pub const KEY_BYTE_LEN: usize = 16;
 /// RFC 4503. 2.4.  IV Setup Scheme (page 2-3).
@@ -213,10 +237,22 @@ fn extract(state: &State) -> [u8; 16] {
 }
 
 /// Rabbit stream cipher state.
+///
+/// Besides the state derived from the key and IV, it keeps a copy of the IV
+/// the keystream was derived from, which is only needed by `to_bytes`
+/// (`state` feature). Keep this in mind if the IV is treated as secret:
+/// it stays in memory for as long as the cipher does, and is wiped along with
+/// the rest of the state under the `zeroize` feature.
 #[cfg_attr(feature = "zeroize", derive(Zeroize))]
 #[cfg_attr(feature = "zeroize", zeroize(drop))]
 pub struct Rabbit {
     master_state: State,
+    /// State after the IV setup (or the master state if no IV is used),
+    /// i.e. the origin of the keystream.
+    iv_state: State,
+    /// IV the keystream was derived from, if any
+    #[cfg_attr(not(feature = "state"), allow(dead_code))]
+    iv: Option<[u8; IV_BYTE_LEN]>,
     state: State,
     block: [u8; 16],
     block_idx: usize,
@@ -240,10 +276,13 @@ impl Rabbit {
         #[cfg(feature = "zeroize")]
         key.zeroize();
 
+        let iv_state = master_state.clone();
         let mut state = master_state.clone();
         next_state(&mut state);
         Rabbit {
             master_state,
+            iv_state,
+            iv: None,
             block: extract(&state),
             state,
             block_idx: 0,
@@ -255,11 +294,13 @@ impl Rabbit {
     #[allow(unused_mut)]
     pub fn setup(key: [u8; KEY_BYTE_LEN], mut iv: [u8; IV_BYTE_LEN]) -> Rabbit {
         let mut this = Self::setup_without_iv(key);
-        this.state = this.master_state.clone();
-        setup_iv(&mut this.state, iv);
+        this.iv_state = this.master_state.clone();
+        setup_iv(&mut this.iv_state, iv);
+        this.iv = Some(iv);
         #[cfg(feature = "zeroize")]
         iv.zeroize();
 
+        this.state = this.iv_state.clone();
         next_state(&mut this.state);
         this.block = extract(&this.state);
         this
@@ -267,6 +308,8 @@ impl Rabbit {
 
     /// Restores master state (iv will be lost).
     pub fn reset(&mut self) {
+        self.iv_state = self.master_state.clone();
+        self.iv = None;
         self.state = self.master_state.clone();
         next_state(&mut self.state);
         self.block = extract(&self.state);
@@ -277,11 +320,13 @@ impl Rabbit {
     /// Restores master state, than setups initialization vector `iv` on it.
     #[allow(unused_mut)]
     pub fn reinit(&mut self, mut iv: [u8; IV_BYTE_LEN]) {
-        self.state = self.master_state.clone();
-        setup_iv(&mut self.state, iv);
+        self.iv_state = self.master_state.clone();
+        setup_iv(&mut self.iv_state, iv);
+        self.iv = Some(iv);
         #[cfg(feature = "zeroize")]
         iv.zeroize();
 
+        self.state = self.iv_state.clone();
         next_state(&mut self.state);
         self.block = extract(&self.state);
         self.block_idx = 0;
@@ -300,7 +345,7 @@ impl Rabbit {
         }
 
         let prefix_len = min(
-            (MESSAGE_BLOCK_BYTE_LEN - (self.block_idx as usize)) % MESSAGE_BLOCK_BYTE_LEN,
+            (MESSAGE_BLOCK_BYTE_LEN - self.block_idx) % MESSAGE_BLOCK_BYTE_LEN,
             data.len(),
         );
         let num_blocks = (data.len() - prefix_len) / MESSAGE_BLOCK_BYTE_LEN;
@@ -322,7 +367,7 @@ impl Rabbit {
 
             rhs ^= lhs;
 
-            (&mut data[i..i + MESSAGE_BLOCK_BYTE_LEN]).copy_from_slice(&rhs.to_le_bytes());
+            data[i..i + MESSAGE_BLOCK_BYTE_LEN].copy_from_slice(&rhs.to_le_bytes());
 
             i += MESSAGE_BLOCK_BYTE_LEN;
         }
@@ -338,6 +383,49 @@ impl Rabbit {
         true
     }
 
+    /// Writes keystream to `out`, overwriting its contents.
+    ///
+    /// The output and the position of the keystream afterwards are the same
+    /// as for [`Rabbit::encrypt_inplace`] on a zeroed buffer, without paying
+    /// for the XOR.
+    ///
+    /// # Panics
+    ///
+    /// If max message length (16 * 2⁶⁴ bytes) is exceeded.
+    pub fn write_keystream(&mut self, out: &mut [u8]) {
+        self.try_write_keystream(out).unwrap();
+    }
+
+    /// Writes keystream to `out` (see [`Rabbit::write_keystream`]).
+    ///
+    /// Returns [`LoopError`] and leaves `out` unaffected if max message
+    /// length (16 * 2⁶⁴ bytes) is exceeded.
+    pub fn try_write_keystream(&mut self, out: &mut [u8]) -> Result<(), LoopError> {
+        if !self.check_keystream_len(out.len()) {
+            return Err(LoopError);
+        }
+
+        let prefix_len = min(
+            (MESSAGE_BLOCK_BYTE_LEN - self.block_idx) % MESSAGE_BLOCK_BYTE_LEN,
+            out.len(),
+        );
+        let (prefix, rest) = out.split_at_mut(prefix_len);
+        for b in prefix {
+            *b = self.get_s_byte();
+        }
+
+        let mut blocks = rest.chunks_exact_mut(MESSAGE_BLOCK_BYTE_LEN);
+        for block in &mut blocks {
+            block.copy_from_slice(&self.get_s_block());
+        }
+
+        for b in blocks.into_remainder() {
+            *b = self.get_s_byte();
+        }
+
+        Ok(())
+    }
+
     /// Decrypts bytes of `data` inplace (see [`Rabbit::encrypt_inplace`]).
     #[inline(always)]
     pub fn decrypt_inplace(&mut self, data: &mut [u8]) -> bool {
@@ -369,7 +457,7 @@ impl Rabbit {
     /// Make sure to call this only if there is enough bytes in the keystream
     /// (see [`Rabbit::check_keystream_len`], RFC 4503 3.1. Message Length (page 5)).
     fn get_s_byte(&mut self) -> u8 {
-        let byte = self.block[self.block_idx as usize];
+        let byte = self.block[self.block_idx];
 
         self.block_idx = (self.block_idx + 1) % MESSAGE_BLOCK_BYTE_LEN;
         if self.block_idx == 0 {
@@ -399,6 +487,111 @@ impl Rabbit {
         self.block_num += 1;
         replace(&mut self.block, extract(&self.state))
     }
+
+    /// Moves the keystream to the start of block `block_num`, then to byte
+    /// `block_idx` within it.
+    ///
+    /// The counter system is a carry-chained addition feeding the non-linear
+    /// next-state function, so there is no shortcut to an arbitrary block:
+    /// the state is advanced one block at a time. Seeking backwards restarts
+    /// from the IV-initialized state.
+    fn seek_block(&mut self, block_num: u64, block_idx: usize) {
+        debug_assert!(block_idx < MESSAGE_BLOCK_BYTE_LEN);
+
+        if block_num != self.block_num {
+            let start = if block_num < self.block_num {
+                self.state = self.iv_state.clone();
+                next_state(&mut self.state);
+                0
+            } else {
+                self.block_num
+            };
+
+            for _ in start..block_num {
+                next_state(&mut self.state);
+            }
+
+            #[cfg(feature = "zeroize")]
+            self.block.zeroize();
+            self.block = extract(&self.state);
+            self.block_num = block_num;
+        }
+
+        self.block_idx = block_idx;
+    }
+}
+
+/// State flag: the keystream was derived from an IV
+#[cfg(feature = "state")]
+const STATE_IV: u8 = 2;
+
+/// Length of the serialized state following the key: IV, block number and
+/// byte position
+#[cfg(feature = "state")]
+const STATE_BODY_LEN: usize = IV_BYTE_LEN + POSITION_LEN;
+
+#[cfg(feature = "state")]
+impl Rabbit {
+    /// Length of the state serialized by [`Rabbit::to_bytes`], with or
+    /// without the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(KEY_BYTE_LEN, STATE_BODY_LEN, with_key)
+    }
+
+    /// Serialize the IV and keystream position into `buf`, returning the
+    /// written prefix of it.
+    ///
+    /// The cipher does not retain its key, so it's included only if passed
+    /// as `key`, which must be the key the cipher was set up with. A state
+    /// including the key recreates the keystream of every IV used with that
+    /// key, not only the current one.
+    ///
+    /// The format is a version byte, a flags byte, the key if included, the
+    /// IV (all zeros if none was set up), then the block number as a little
+    /// endian `u64` and the byte position within that block.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&Key>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let flags = if self.iv.is_some() { STATE_IV } else { 0 };
+        let pos = self.block_num as u128 * MESSAGE_BLOCK_BYTE_LEN as u128 + self.block_idx as u128;
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write(buf, key, flags, STATE_BODY_LEN, |body| {
+            body[..IV_BYTE_LEN].copy_from_slice(&self.iv.unwrap_or_default());
+            cipher_state::write_position(
+                &mut body[IV_BYTE_LEN..],
+                pos,
+                MESSAGE_BLOCK_BYTE_LEN as u8,
+            )
+        })
+    }
+
+    /// Restore a cipher from a state serialized by [`Rabbit::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    ///
+    /// Rabbit has no random access to its keystream, so the keystream up to
+    /// the saved position is regenerated, as with seeking.
+    pub fn from_bytes(bytes: &[u8], key: Option<&Key>) -> Result<Self, StateError> {
+        let state = cipher_state::State::read(bytes, KEY_BYTE_LEN, STATE_BODY_LEN, STATE_IV)?;
+        let key: [u8; KEY_BYTE_LEN] = state
+            .key(key.map(|k| k.as_slice()))?
+            .try_into()
+            .map_err(|_| StateError)?;
+        let (iv, pos) = state.body().split_at(IV_BYTE_LEN);
+        let pos = cipher_state::read_position(pos, MESSAGE_BLOCK_BYTE_LEN as u8)?;
+        let block_num = (pos / MESSAGE_BLOCK_BYTE_LEN as u128) as u64;
+        let block_idx = (pos % MESSAGE_BLOCK_BYTE_LEN as u128) as usize;
+
+        let mut cipher = if state.flags() & STATE_IV != 0 {
+            Self::setup(key, iv.try_into().unwrap())
+        } else {
+            Self::setup_without_iv(key)
+        };
+        cipher.seek_block(block_num, block_idx);
+        Ok(cipher)
+    }
 }
 
 impl NewCipher for Rabbit {
@@ -420,6 +613,32 @@ impl StreamCipher for Rabbit {
     }
 }
 
+impl StreamCipherSeek for Rabbit {
+    fn try_current_pos<T: SeekNum>(&self) -> Result<T, OverflowError> {
+        T::from_block_byte(
+            self.block_num,
+            self.block_idx as u8,
+            MESSAGE_BLOCK_BYTE_LEN as u8,
+        )
+    }
+
+    /// Seek to the given position in the keystream.
+    ///
+    /// Rabbit has no shortcut to an arbitrary block, so this runs the
+    /// next-state function once per block skipped: seeking within the
+    /// current block is free, seeking forward advances from the current
+    /// block to the target, and seeking backwards starts over from the first
+    /// block, i.e. costs as many steps as the target is blocks away from the
+    /// start of the keystream.
+    fn try_seek<T: SeekNum>(&mut self, pos: T) -> Result<(), LoopError> {
+        let (block_num, block_idx): (u64, u8) = pos
+            .to_block_byte(MESSAGE_BLOCK_BYTE_LEN as u8)
+            .map_err(|_| LoopError)?;
+        self.seek_block(block_num, block_idx as usize);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -650,4 +869,105 @@ mod test {
         S[1] = [0x96,0xC8,0xF2,0x79,0x47,0xF4,0x2C,0x5B,0xAE,0xAE,0x67,0xC6,0xAC,0xC3,0x5B,0x03]
         S[2] = [0x9F,0xCB,0xFC,0x89,0x5F,0xA7,0x1C,0x17,0x31,0x3D,0xF0,0x34,0xF0,0x15,0x51,0xCB]
     }
+
+    cipher::stream_cipher_seek_test!(rabbit_seek, Rabbit);
+
+    fn keystream(len: usize) -> [u8; 1024] {
+        let mut buf = [0u8; 1024];
+        let mut cipher = Rabbit::setup([0x42; KEY_BYTE_LEN], [0x24; IV_BYTE_LEN]);
+        cipher.encrypt_inplace(&mut buf[..len]);
+        buf
+    }
+
+    #[test]
+    fn seek_forward_and_backward() {
+        let expected = keystream(1024);
+        let mut cipher = Rabbit::setup([0x42; KEY_BYTE_LEN], [0x24; IV_BYTE_LEN]);
+
+        for &pos in &[700usize, 3, 16, 1000, 999, 0, 512, 17, 1008] {
+            cipher.seek(pos);
+            assert_eq!(cipher.current_pos::<usize>(), pos);
+
+            let mut buf = [0u8; 16];
+            let len = core::cmp::min(buf.len(), 1024 - pos);
+            cipher.encrypt_inplace(&mut buf[..len]);
+            assert_eq!(&buf[..len], &expected[pos..pos + len]);
+            assert_eq!(cipher.current_pos::<usize>(), pos + len);
+        }
+    }
+
+    #[test]
+    fn seek_after_reinit() {
+        let mut cipher = Rabbit::setup([0x42; KEY_BYTE_LEN], [0; IV_BYTE_LEN]);
+        cipher.reinit([0x24; IV_BYTE_LEN]);
+
+        let mut buf = [0u8; 100];
+        cipher.encrypt_inplace(&mut buf);
+        assert_eq!(&buf[..], &keystream(100)[..100]);
+
+        let mut buf = [0u8; 63];
+        cipher.seek(37u64);
+        cipher.encrypt_inplace(&mut buf);
+        assert_eq!(&buf[..], &keystream(100)[37..100]);
+    }
+
+    #[test]
+    fn seek_without_iv() {
+        let mut expected = [0u8; 64];
+        Rabbit::setup_without_iv([0x42; KEY_BYTE_LEN]).encrypt_inplace(&mut expected);
+
+        let mut cipher = Rabbit::setup([0x42; KEY_BYTE_LEN], [0x24; IV_BYTE_LEN]);
+        cipher.reset();
+        cipher.seek(40u64);
+        let mut buf = [0u8; 24];
+        cipher.encrypt_inplace(&mut buf);
+        cipher.seek(5u64);
+        let mut buf2 = [0u8; 10];
+        cipher.encrypt_inplace(&mut buf2);
+
+        assert_eq!(&buf[..], &expected[40..]);
+        assert_eq!(&buf2[..], &expected[5..15]);
+    }
+
+    #[test]
+    fn write_keystream() {
+        let expected = keystream(1024);
+        let mut cipher = Rabbit::setup([0x42; KEY_BYTE_LEN], [0x24; IV_BYTE_LEN]);
+        let mut buf = [0xffu8; 1024];
+
+        let mut pos = 0;
+        for (i, &len) in [1usize, 15, 16, 17, 7, 500, 3, 465].iter().enumerate() {
+            let chunk = &mut buf[pos..pos + len];
+            if i % 2 == 0 {
+                cipher.write_keystream(chunk);
+            } else {
+                chunk.iter_mut().for_each(|b| *b = 0);
+                cipher.encrypt_inplace(chunk);
+            }
+            pos += len;
+            assert_eq!(cipher.current_pos::<usize>(), pos);
+        }
+        assert_eq!(&buf[..], &expected[..]);
+    }
+
+    #[cfg(feature = "state")]
+    cipher_state::stream_state_test!(state_round_trip, Rabbit);
+
+    #[cfg(feature = "state")]
+    #[test]
+    fn state_round_trip_without_iv() {
+        let mut expected = [0u8; 64];
+        Rabbit::setup_without_iv([0x42; KEY_BYTE_LEN]).encrypt_inplace(&mut expected);
+
+        let mut cipher = Rabbit::setup_without_iv([0x42; KEY_BYTE_LEN]);
+        cipher.encrypt_inplace(&mut [0u8; 21]);
+
+        let key = Key::from([0x42; KEY_BYTE_LEN]);
+        let mut buf = [0u8; 64];
+        let state = cipher.to_bytes(Some(&key), &mut buf).unwrap();
+        let mut restored = Rabbit::from_bytes(state, None).unwrap();
+        let mut out = [0u8; 43];
+        restored.encrypt_inplace(&mut out);
+        assert_eq!(&out[..], &expected[21..]);
+    }
 }
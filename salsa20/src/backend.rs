@@ -0,0 +1,31 @@
+//! Backends providing the Salsa20 core function.
+//!
+//! Defined in the Salsa20 specification:
+//! <https://cr.yp.to/snuffle/spec.pdf>
+
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "sse2",
+        not(feature = "force-soft")
+    ))] {
+        #[allow(unsafe_code)]
+        pub(crate) mod autodetect;
+        #[allow(unsafe_code)]
+        pub(crate) mod avx2;
+        #[allow(unsafe_code)]
+        pub(crate) mod sse2;
+
+        pub(crate) use self::autodetect::BUFFER_SIZE;
+        pub use self::autodetect::Core;
+
+        #[cfg(any(feature = "expose-core", feature = "xsalsa20", test))]
+        pub(crate) mod soft;
+    } else {
+        pub(crate) mod soft;
+        pub(crate) use self::soft::BUFFER_SIZE;
+        pub use self::soft::Core;
+    }
+}
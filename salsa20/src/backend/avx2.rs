@@ -0,0 +1,261 @@
+//! The Salsa20 core function.
+//!
+//! AVX2-optimized implementation for x86/x86-64 CPUs.
+//!
+//! Uses the same "diagonal" state layout as the SSE2 backend (see
+//! `sse2.rs`), with each 256-bit vector holding the diagonals of two
+//! consecutive blocks: the first in the low 128-bit lane, and the second in
+//! the high 128-bit lane.
+
+use super::autodetect::BUFFER_SIZE;
+use crate::{rounds::Rounds, Key, Nonce, BLOCK_SIZE, CONSTANTS};
+use core::{convert::TryInto, marker::PhantomData};
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+/// The Salsa20 core function (AVX2 accelerated implementation for x86/x86_64)
+#[derive(Clone)]
+pub(crate) struct Core<R: Rounds> {
+    v0: __m256i,
+    v1: __m256i,
+    v2: __m256i,
+    v3: __m256i,
+    rounds: PhantomData<R>,
+}
+
+impl<R: Rounds> Core<R> {
+    /// Initialize core function with the given key and IV
+    #[inline]
+    pub fn new(key: &Key, iv: &Nonce) -> Self {
+        let mut k = [0i32; 8];
+        for (word, chunk) in k.iter_mut().zip(key.chunks_exact(4)) {
+            *word = i32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        let n = [
+            i32::from_le_bytes(iv[..4].try_into().unwrap()),
+            i32::from_le_bytes(iv[4..].try_into().unwrap()),
+        ];
+
+        let (v0, v1, v2, v3) = unsafe { state_setup(k, n) };
+
+        Self {
+            v0,
+            v1,
+            v2,
+            v3,
+            rounds: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn generate(&self, counter: u64, output: &mut [u8]) {
+        debug_assert_eq!(output.len(), BUFFER_SIZE);
+
+        unsafe {
+            let (v0, v1, v2, v3) = self.blocks(counter);
+            store(v0, v1, v2, v3, output);
+        }
+    }
+
+    #[inline]
+    #[allow(clippy::cast_ptr_alignment)] // loadu/storeu support unaligned loads/stores
+    pub fn apply_keystream(&self, counter: u64, output: &mut [u8]) {
+        debug_assert_eq!(output.len(), BUFFER_SIZE);
+
+        unsafe {
+            let (v0, v1, v2, v3) = self.blocks(counter);
+            let rows = rows(v0, v1, v2, v3);
+
+            for (chunk, a) in output[..BLOCK_SIZE].chunks_mut(0x10).zip(&rows) {
+                let b = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+                let out = _mm_xor_si128(_mm256_castsi256_si128(*a), b);
+                _mm_storeu_si128(chunk.as_mut_ptr() as *mut __m128i, out);
+            }
+
+            for (chunk, a) in output[BLOCK_SIZE..].chunks_mut(0x10).zip(&rows) {
+                let b = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+                let out = _mm_xor_si128(_mm256_extractf128_si256(*a, 1), b);
+                _mm_storeu_si128(chunk.as_mut_ptr() as *mut __m128i, out);
+            }
+        }
+    }
+
+    /// Compute the (diagonalized) output of the core function for the blocks
+    /// at `counter` and `counter + 1`
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn blocks(&self, counter: u64) -> (__m256i, __m256i, __m256i, __m256i) {
+        let (v2_orig, v3_orig) = counter_setup(self.v2, self.v3, counter);
+        let (mut v0, mut v1, mut v2, mut v3) = (self.v0, self.v1, v2_orig, v3_orig);
+
+        for _ in 0..(R::COUNT / 2) {
+            double_round(&mut v0, &mut v1, &mut v2, &mut v3);
+        }
+
+        (
+            _mm256_add_epi32(v0, self.v0),
+            _mm256_add_epi32(v1, self.v1),
+            _mm256_add_epi32(v2, v2_orig),
+            _mm256_add_epi32(v3, v3_orig),
+        )
+    }
+}
+
+/// Load the key and nonce words into diagonalized form, leaving the counter
+/// words set to zero
+#[inline]
+#[target_feature(enable = "avx2")]
+#[allow(clippy::cast_ptr_alignment)] // loadu supports unaligned loads
+unsafe fn state_setup(k: [i32; 8], n: [i32; 2]) -> (__m256i, __m256i, __m256i, __m256i) {
+    let v0 = _mm_loadu_si128(CONSTANTS.as_ptr() as *const __m128i);
+    let v1 = _mm_set_epi32(k[4], n[0], k[0], k[5]);
+    let v2 = _mm_set_epi32(n[1], k[1], k[6], 0);
+    let v3 = _mm_set_epi32(k[2], k[7], 0, k[3]);
+
+    (
+        _mm256_broadcastsi128_si256(v0),
+        _mm256_broadcastsi128_si256(v1),
+        _mm256_broadcastsi128_si256(v2),
+        _mm256_broadcastsi128_si256(v3),
+    )
+}
+
+/// Insert the 64-bit block counters for two consecutive blocks into words 8
+/// and 9 of the state
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn counter_setup(v2: __m256i, v3: __m256i, counter: u64) -> (__m256i, __m256i) {
+    let next = counter.wrapping_add(1);
+
+    (
+        _mm256_or_si256(
+            v2,
+            _mm256_set_epi32(
+                0,
+                0,
+                0,
+                (next & 0xffff_ffff) as i32,
+                0,
+                0,
+                0,
+                (counter & 0xffff_ffff) as i32,
+            ),
+        ),
+        _mm256_or_si256(
+            v3,
+            _mm256_set_epi32(
+                0,
+                0,
+                ((next >> 32) & 0xffff_ffff) as i32,
+                0,
+                0,
+                0,
+                ((counter >> 32) & 0xffff_ffff) as i32,
+                0,
+            ),
+        ),
+    )
+}
+
+/// Convert the diagonalized state back into the four rows of the Salsa20
+/// matrix (for both blocks)
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn rows(v0: __m256i, v1: __m256i, v2: __m256i, v3: __m256i) -> [__m256i; 4] {
+    [
+        select(v0, v1, v2, v3),
+        select(v3, v0, v1, v2),
+        select(v2, v3, v0, v1),
+        select(v1, v2, v3, v0),
+    ]
+}
+
+/// Build a vector from lane 0 of `a`, lane 1 of `b`, lane 2 of `c`, and
+/// lane 3 of `d` (within each 128-bit half)
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn select(a: __m256i, b: __m256i, c: __m256i, d: __m256i) -> __m256i {
+    let t = _mm256_blend_epi32(a, b, 0b0010_0010);
+    let t = _mm256_blend_epi32(t, c, 0b0100_0100);
+    _mm256_blend_epi32(t, d, 0b1000_1000)
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+#[allow(clippy::cast_ptr_alignment)] // storeu supports unaligned stores
+unsafe fn store(v0: __m256i, v1: __m256i, v2: __m256i, v3: __m256i, output: &mut [u8]) {
+    debug_assert_eq!(output.len(), BUFFER_SIZE);
+    let rows = rows(v0, v1, v2, v3);
+
+    for (chunk, v) in output[..BLOCK_SIZE].chunks_mut(0x10).zip(&rows) {
+        _mm_storeu_si128(
+            chunk.as_mut_ptr() as *mut __m128i,
+            _mm256_castsi256_si128(*v),
+        );
+    }
+
+    for (chunk, v) in output[BLOCK_SIZE..].chunks_mut(0x10).zip(&rows) {
+        _mm_storeu_si128(
+            chunk.as_mut_ptr() as *mut __m128i,
+            _mm256_extractf128_si256(*v, 1),
+        );
+    }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn double_round(v0: &mut __m256i, v1: &mut __m256i, v2: &mut __m256i, v3: &mut __m256i) {
+    // column round
+    add_xor_rot(v0, v1, v2, v3);
+    cols_to_rows(v0, v1, v2, v3);
+
+    // row round: operates on the same diagonals, with the roles of v1 and v3 swapped
+    add_xor_rot(v0, v3, v2, v1);
+    rows_to_cols(v0, v1, v2, v3);
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn cols_to_rows(_v0: &mut __m256i, v1: &mut __m256i, v2: &mut __m256i, v3: &mut __m256i) {
+    // v1 >>>= 32; v2 >>>= 64; v3 >>>= 96;
+    *v1 = _mm256_shuffle_epi32(*v1, 0b_00_11_10_01); // _MM_SHUFFLE(0, 3, 2, 1)
+    *v2 = _mm256_shuffle_epi32(*v2, 0b_01_00_11_10); // _MM_SHUFFLE(1, 0, 3, 2)
+    *v3 = _mm256_shuffle_epi32(*v3, 0b_10_01_00_11); // _MM_SHUFFLE(2, 1, 0, 3)
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn rows_to_cols(_v0: &mut __m256i, v1: &mut __m256i, v2: &mut __m256i, v3: &mut __m256i) {
+    // v1 <<<= 32; v2 <<<= 64; v3 <<<= 96;
+    *v1 = _mm256_shuffle_epi32(*v1, 0b_10_01_00_11); // _MM_SHUFFLE(2, 1, 0, 3)
+    *v2 = _mm256_shuffle_epi32(*v2, 0b_01_00_11_10); // _MM_SHUFFLE(1, 0, 3, 2)
+    *v3 = _mm256_shuffle_epi32(*v3, 0b_00_11_10_01); // _MM_SHUFFLE(0, 3, 2, 1)
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn add_xor_rot(v0: &mut __m256i, v1: &mut __m256i, v2: &mut __m256i, v3: &mut __m256i) {
+    // v3 ^= (v0 + v1) <<< 7;
+    let t = _mm256_add_epi32(*v0, *v1);
+    *v3 = _mm256_xor_si256(*v3, _mm256_slli_epi32(t, 7));
+    *v3 = _mm256_xor_si256(*v3, _mm256_srli_epi32(t, 25));
+
+    // v2 ^= (v3 + v0) <<< 9;
+    let t = _mm256_add_epi32(*v3, *v0);
+    *v2 = _mm256_xor_si256(*v2, _mm256_slli_epi32(t, 9));
+    *v2 = _mm256_xor_si256(*v2, _mm256_srli_epi32(t, 23));
+
+    // v1 ^= (v2 + v3) <<< 13;
+    let t = _mm256_add_epi32(*v2, *v3);
+    *v1 = _mm256_xor_si256(*v1, _mm256_slli_epi32(t, 13));
+    *v1 = _mm256_xor_si256(*v1, _mm256_srli_epi32(t, 19));
+
+    // v0 ^= (v1 + v2) <<< 18;
+    let t = _mm256_add_epi32(*v1, *v2);
+    *v0 = _mm256_xor_si256(*v0, _mm256_slli_epi32(t, 18));
+    *v0 = _mm256_xor_si256(*v0, _mm256_srli_epi32(t, 14));
+}
@@ -0,0 +1,155 @@
+//! The Salsa20 core function.
+//!
+//! Portable implementation which does not rely on architecture-specific
+//! intrinsics.
+
+use crate::{rounds::Rounds, Key, Nonce, BLOCK_SIZE, CONSTANTS};
+use core::{convert::TryInto, marker::PhantomData};
+
+/// Size of buffers passed to `generate` and `apply_keystream` for this backend
+#[allow(dead_code)]
+pub(crate) const BUFFER_SIZE: usize = BLOCK_SIZE;
+
+/// Number of 32-bit words in the Salsa20 state
+pub(crate) const STATE_WORDS: usize = 16;
+
+/// The Salsa20 core function.
+// TODO(tarcieri): zeroize support
+#[derive(Clone)]
+#[allow(dead_code)]
+pub struct Core<R: Rounds> {
+    /// Internal state of the core function
+    state: [u32; STATE_WORDS],
+
+    /// Number of rounds to perform
+    rounds: PhantomData<R>,
+}
+
+#[allow(dead_code)]
+impl<R: Rounds> Core<R> {
+    /// Initialize core function with the given key and IV
+    pub fn new(key: &Key, iv: &Nonce) -> Self {
+        let mut state = [0u32; STATE_WORDS];
+        state[0] = CONSTANTS[0];
+
+        for (i, chunk) in key[..16].chunks(4).enumerate() {
+            state[1 + i] = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        state[5] = CONSTANTS[1];
+
+        for (i, chunk) in iv.chunks(4).enumerate() {
+            state[6 + i] = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        state[8] = 0;
+        state[9] = 0;
+        state[10] = CONSTANTS[2];
+
+        for (i, chunk) in key[16..].chunks(4).enumerate() {
+            state[11 + i] = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        state[15] = CONSTANTS[3];
+
+        Self {
+            state,
+            rounds: PhantomData,
+        }
+    }
+
+    /// Generate output, overwriting data already in the buffer
+    #[inline]
+    pub fn generate(&self, counter: u64, output: &mut [u8]) {
+        debug_assert_eq!(output.len(), BUFFER_SIZE);
+
+        let mut state = self.counter_setup(counter);
+        self.rounds(&mut state);
+
+        for (i, chunk) in output.chunks_mut(4).enumerate() {
+            chunk.copy_from_slice(&state[i].to_le_bytes());
+        }
+    }
+
+    /// Apply generated keystream to the output buffer
+    #[inline]
+    pub fn apply_keystream(&self, counter: u64, output: &mut [u8]) {
+        debug_assert_eq!(output.len(), BUFFER_SIZE);
+
+        let mut state = self.counter_setup(counter);
+        self.rounds(&mut state);
+
+        for (i, chunk) in output.chunks_mut(4).enumerate() {
+            for (a, b) in chunk.iter_mut().zip(&state[i].to_le_bytes()) {
+                *a ^= *b;
+            }
+        }
+    }
+
+    /// Get a copy of the initial state with the given block counter
+    #[inline]
+    fn counter_setup(&self, counter: u64) -> [u32; STATE_WORDS] {
+        let mut state = self.state;
+        state[8] = (counter & 0xffff_ffff) as u32;
+        state[9] = ((counter >> 32) & 0xffff_ffff) as u32;
+        state
+    }
+
+    /// Run the 20 rounds (i.e. 10 double rounds) of Salsa20
+    #[inline]
+    fn rounds(&self, state: &mut [u32; STATE_WORDS]) {
+        let input = *state;
+
+        for _ in 0..(R::COUNT / 2) {
+            // column rounds
+            quarter_round(0, 4, 8, 12, state);
+            quarter_round(5, 9, 13, 1, state);
+            quarter_round(10, 14, 2, 6, state);
+            quarter_round(15, 3, 7, 11, state);
+
+            // diagonal rounds
+            quarter_round(0, 1, 2, 3, state);
+            quarter_round(5, 6, 7, 4, state);
+            quarter_round(10, 11, 8, 9, state);
+            quarter_round(15, 12, 13, 14, state);
+        }
+
+        for (s1, s0) in state.iter_mut().zip(&input) {
+            *s1 = s1.wrapping_add(*s0);
+        }
+    }
+}
+
+impl<R: Rounds> From<[u32; STATE_WORDS]> for Core<R> {
+    fn from(state: [u32; STATE_WORDS]) -> Core<R> {
+        Self {
+            state,
+            rounds: PhantomData,
+        }
+    }
+}
+
+/// The Salsa20 quarter round function
+#[inline]
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn quarter_round(
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize,
+    state: &mut [u32; STATE_WORDS],
+) {
+    let mut t: u32;
+
+    t = state[a].wrapping_add(state[d]);
+    state[b] ^= t.rotate_left(7);
+
+    t = state[b].wrapping_add(state[a]);
+    state[c] ^= t.rotate_left(9);
+
+    t = state[c].wrapping_add(state[b]);
+    state[d] ^= t.rotate_left(13);
+
+    t = state[d].wrapping_add(state[c]);
+    state[a] ^= t.rotate_left(18);
+}
@@ -0,0 +1,289 @@
+//! The Salsa20 core function.
+//!
+//! SSE2-optimized implementation for x86/x86-64 CPUs.
+//!
+//! The state is kept in "diagonal" form: each of the four vectors holds one
+//! word from each column of the 4x4 Salsa20 matrix, arranged so that both the
+//! column and row rounds can be computed with lane-wise operations followed by
+//! a lane rotation:
+//!
+//! ```text
+//! v0 = (x0,  x5,  x10, x15)
+//! v1 = (x12, x1,  x6,  x11)
+//! v2 = (x8,  x13, x2,  x7)
+//! v3 = (x4,  x9,  x14, x3)
+//! ```
+
+use super::autodetect::BUFFER_SIZE;
+use crate::{rounds::Rounds, Key, Nonce, BLOCK_SIZE, CONSTANTS};
+use core::{convert::TryInto, marker::PhantomData};
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+/// Number of blocks computed in parallel to fill a buffer
+const PAR_BLOCKS: usize = BUFFER_SIZE / BLOCK_SIZE;
+
+/// The Salsa20 core function (SSE2 accelerated implementation for x86/x86_64)
+#[derive(Clone)]
+pub struct Core<R: Rounds> {
+    v0: __m128i,
+    v1: __m128i,
+    v2: __m128i,
+    v3: __m128i,
+    rounds: PhantomData<R>,
+}
+
+impl<R: Rounds> Core<R> {
+    /// Initialize core function with the given key and IV
+    #[inline]
+    pub fn new(key: &Key, iv: &Nonce) -> Self {
+        let mut k = [0i32; 8];
+        for (word, chunk) in k.iter_mut().zip(key.chunks_exact(4)) {
+            *word = i32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        let n = [
+            i32::from_le_bytes(iv[..4].try_into().unwrap()),
+            i32::from_le_bytes(iv[4..].try_into().unwrap()),
+        ];
+
+        let (v0, v1, v2, v3) = unsafe { state_setup(k, n) };
+
+        Self {
+            v0,
+            v1,
+            v2,
+            v3,
+            rounds: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn generate(&self, counter: u64, output: &mut [u8]) {
+        debug_assert_eq!(output.len(), BUFFER_SIZE);
+
+        unsafe {
+            let blocks = self.blocks(counter);
+            for (chunk, block) in output.chunks_exact_mut(BLOCK_SIZE).zip(&blocks) {
+                store(block, chunk);
+            }
+        }
+    }
+
+    #[inline]
+    #[allow(clippy::cast_ptr_alignment)] // loadu/storeu support unaligned loads/stores
+    pub fn apply_keystream(&self, counter: u64, output: &mut [u8]) {
+        debug_assert_eq!(output.len(), BUFFER_SIZE);
+
+        unsafe {
+            let blocks = self.blocks(counter);
+            for (chunk, block) in output.chunks_exact_mut(BLOCK_SIZE).zip(&blocks) {
+                for (ch, a) in chunk.chunks_exact_mut(0x10).zip(&rows(block)) {
+                    let b = _mm_loadu_si128(ch.as_ptr() as *const __m128i);
+                    let out = _mm_xor_si128(*a, b);
+                    _mm_storeu_si128(ch.as_mut_ptr() as *mut __m128i, out);
+                }
+            }
+        }
+    }
+
+    /// Compute the (diagonalized) output of the core function for all blocks
+    /// of the buffer, interleaving their rounds so they run in parallel
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn blocks(&self, counter: u64) -> [[__m128i; 4]; PAR_BLOCKS] {
+        let mut input = [[self.v0, self.v1, self.v2, self.v3]; PAR_BLOCKS];
+        for (i, block) in input.iter_mut().enumerate() {
+            let (v2, v3) = counter_setup(self.v2, self.v3, counter.wrapping_add(i as u64));
+            block[2] = v2;
+            block[3] = v3;
+        }
+
+        let mut state = input;
+        for _ in 0..(R::COUNT / 2) {
+            for block in state.iter_mut() {
+                let [v0, v1, v2, v3] = block;
+                double_round(v0, v1, v2, v3);
+            }
+        }
+
+        for (block, input) in state.iter_mut().zip(&input) {
+            for (v, i) in block.iter_mut().zip(input) {
+                *v = _mm_add_epi32(*v, *i);
+            }
+        }
+        state
+    }
+}
+
+/// Load the key and nonce words into diagonalized form, leaving the counter
+/// words set to zero
+#[inline]
+#[target_feature(enable = "sse2")]
+#[allow(clippy::cast_ptr_alignment)] // loadu supports unaligned loads
+unsafe fn state_setup(k: [i32; 8], n: [i32; 2]) -> (__m128i, __m128i, __m128i, __m128i) {
+    let v0 = _mm_loadu_si128(CONSTANTS.as_ptr() as *const __m128i);
+    let v1 = _mm_set_epi32(k[4], n[0], k[0], k[5]);
+    let v2 = _mm_set_epi32(n[1], k[1], k[6], 0);
+    let v3 = _mm_set_epi32(k[2], k[7], 0, k[3]);
+    (v0, v1, v2, v3)
+}
+
+/// Insert the 64-bit block counter into words 8 and 9 of the state
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn counter_setup(v2: __m128i, v3: __m128i, counter: u64) -> (__m128i, __m128i) {
+    (
+        _mm_or_si128(v2, _mm_set_epi32(0, 0, 0, (counter & 0xffff_ffff) as i32)),
+        _mm_or_si128(
+            v3,
+            _mm_set_epi32(0, 0, ((counter >> 32) & 0xffff_ffff) as i32, 0),
+        ),
+    )
+}
+
+/// Convert the diagonalized state back into the four rows of the Salsa20
+/// matrix, i.e. 16-byte chunks of output in the order they are serialized
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn rows(block: &[__m128i; 4]) -> [__m128i; 4] {
+    let [v0, v1, v2, v3] = *block;
+    [
+        select(v0, v1, v2, v3),
+        select(v3, v0, v1, v2),
+        select(v2, v3, v0, v1),
+        select(v1, v2, v3, v0),
+    ]
+}
+
+/// Build a vector from lane 0 of `a`, lane 1 of `b`, lane 2 of `c`, and
+/// lane 3 of `d`
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn select(a: __m128i, b: __m128i, c: __m128i, d: __m128i) -> __m128i {
+    let ab = _mm_or_si128(
+        _mm_and_si128(a, _mm_set_epi32(0, 0, 0, -1)),
+        _mm_and_si128(b, _mm_set_epi32(0, 0, -1, 0)),
+    );
+    let cd = _mm_or_si128(
+        _mm_and_si128(c, _mm_set_epi32(0, -1, 0, 0)),
+        _mm_and_si128(d, _mm_set_epi32(-1, 0, 0, 0)),
+    );
+    _mm_or_si128(ab, cd)
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+#[allow(clippy::cast_ptr_alignment)] // storeu supports unaligned stores
+unsafe fn store(block: &[__m128i; 4], output: &mut [u8]) {
+    debug_assert_eq!(output.len(), BLOCK_SIZE);
+
+    for (chunk, v) in output.chunks_exact_mut(0x10).zip(&rows(block)) {
+        _mm_storeu_si128(chunk.as_mut_ptr() as *mut __m128i, *v);
+    }
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn double_round(v0: &mut __m128i, v1: &mut __m128i, v2: &mut __m128i, v3: &mut __m128i) {
+    // column round
+    add_xor_rot(v0, v1, v2, v3);
+    cols_to_rows(v0, v1, v2, v3);
+
+    // row round: operates on the same diagonals, with the roles of v1 and v3 swapped
+    add_xor_rot(v0, v3, v2, v1);
+    rows_to_cols(v0, v1, v2, v3);
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn cols_to_rows(_v0: &mut __m128i, v1: &mut __m128i, v2: &mut __m128i, v3: &mut __m128i) {
+    // v1 >>>= 32; v2 >>>= 64; v3 >>>= 96;
+    *v1 = _mm_shuffle_epi32(*v1, 0b_00_11_10_01); // _MM_SHUFFLE(0, 3, 2, 1)
+    *v2 = _mm_shuffle_epi32(*v2, 0b_01_00_11_10); // _MM_SHUFFLE(1, 0, 3, 2)
+    *v3 = _mm_shuffle_epi32(*v3, 0b_10_01_00_11); // _MM_SHUFFLE(2, 1, 0, 3)
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn rows_to_cols(_v0: &mut __m128i, v1: &mut __m128i, v2: &mut __m128i, v3: &mut __m128i) {
+    // v1 <<<= 32; v2 <<<= 64; v3 <<<= 96;
+    *v1 = _mm_shuffle_epi32(*v1, 0b_10_01_00_11); // _MM_SHUFFLE(2, 1, 0, 3)
+    *v2 = _mm_shuffle_epi32(*v2, 0b_01_00_11_10); // _MM_SHUFFLE(1, 0, 3, 2)
+    *v3 = _mm_shuffle_epi32(*v3, 0b_00_11_10_01); // _MM_SHUFFLE(0, 3, 2, 1)
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn add_xor_rot(v0: &mut __m128i, v1: &mut __m128i, v2: &mut __m128i, v3: &mut __m128i) {
+    // v3 ^= (v0 + v1) <<< 7;
+    let t = _mm_add_epi32(*v0, *v1);
+    *v3 = _mm_xor_si128(*v3, _mm_slli_epi32(t, 7));
+    *v3 = _mm_xor_si128(*v3, _mm_srli_epi32(t, 25));
+
+    // v2 ^= (v3 + v0) <<< 9;
+    let t = _mm_add_epi32(*v3, *v0);
+    *v2 = _mm_xor_si128(*v2, _mm_slli_epi32(t, 9));
+    *v2 = _mm_xor_si128(*v2, _mm_srli_epi32(t, 23));
+
+    // v1 ^= (v2 + v3) <<< 13;
+    let t = _mm_add_epi32(*v2, *v3);
+    *v1 = _mm_xor_si128(*v1, _mm_slli_epi32(t, 13));
+    *v1 = _mm_xor_si128(*v1, _mm_srli_epi32(t, 19));
+
+    // v0 ^= (v1 + v2) <<< 18;
+    let t = _mm_add_epi32(*v1, *v2);
+    *v0 = _mm_xor_si128(*v0, _mm_slli_epi32(t, 18));
+    *v0 = _mm_xor_si128(*v0, _mm_srli_epi32(t, 14));
+}
+
+#[cfg(all(test, target_feature = "sse2"))]
+mod tests {
+    use super::*;
+    use crate::backend::soft;
+    use crate::rounds::{R12, R20, R8};
+
+    // random inputs for testing
+    const R_CNT: u64 = 0x9fe625b6d23a8fa8u64;
+    const R_IV: [u8; 8] = [0x2f, 0x96, 0xa8, 0x4a, 0xf8, 0x92, 0xbc, 0x94];
+    const R_KEY: [u8; 32] = [
+        0x11, 0xf2, 0x72, 0x99, 0xe1, 0x79, 0x6d, 0xef, 0xb, 0xdc, 0x6a, 0x58, 0x1f, 0x1, 0x58,
+        0x94, 0x92, 0x19, 0x69, 0x3f, 0xe9, 0x35, 0x16, 0x72, 0x63, 0xd1, 0xd, 0x94, 0x6d, 0x31,
+        0x34, 0x11,
+    ];
+
+    fn generate_vs_scalar_impl<R: Rounds>(counter: u64) {
+        let (key, iv) = (Key::from(R_KEY), Nonce::from(R_IV));
+        let mut soft_result = [0u8; BUFFER_SIZE];
+        for (i, chunk) in soft_result.chunks_exact_mut(soft::BUFFER_SIZE).enumerate() {
+            soft::Core::<R>::new(&key, &iv).generate(counter.wrapping_add(i as u64), chunk);
+        }
+
+        let mut simd_result = [0u8; BUFFER_SIZE];
+        Core::<R>::new(&key, &iv).generate(counter, &mut simd_result);
+        assert_eq!(&soft_result[..], &simd_result[..]);
+
+        let mut simd_result = [0u8; BUFFER_SIZE];
+        Core::<R>::new(&key, &iv).apply_keystream(counter, &mut simd_result);
+        assert_eq!(&soft_result[..], &simd_result[..]);
+    }
+
+    #[test]
+    fn generate_vs_scalar_impl_r8() {
+        generate_vs_scalar_impl::<R8>(R_CNT);
+    }
+
+    #[test]
+    fn generate_vs_scalar_impl_r12() {
+        generate_vs_scalar_impl::<R12>(R_CNT);
+    }
+
+    #[test]
+    fn generate_vs_scalar_impl_r20() {
+        generate_vs_scalar_impl::<R20>(R_CNT);
+        generate_vs_scalar_impl::<R20>(u64::MAX);
+    }
+}
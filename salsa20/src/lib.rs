@@ -10,6 +10,18 @@
 //!
 //! USE AT YOUR OWN RISK!
 //!
+//! The `secretbox` feature additionally provides [`XSalsa20Poly1305`]
+//! authenticated encryption, compatible with NaCl's `crypto_secretbox`, the
+//! `rayon` feature provides a `par_apply_keystream` method which generates
+//! the keystream for large inputs on multiple threads, and the `state`
+//! feature provides `to_bytes` and `from_bytes` methods which save and
+//! restore the nonce and keystream position of a cipher.
+//!
+//! The `io` feature re-exports the `StreamReader` and `StreamWriter` adapters
+//! of the [`cipher-io`](https://docs.rs/cipher-io) crate, which apply the
+//! keystream to the data read from a `std::io::Read` or written to a
+//! `std::io::Write` implementation.
+//!
 //! # Diagram
 //!
 //! This diagram illustrates the Salsa quarter round function.
@@ -58,14 +70,32 @@
 
 pub use cipher;
 
+#[cfg(feature = "std")]
+extern crate std;
+
+mod backend;
+#[cfg(feature = "expose-core")]
 mod core;
+#[cfg(feature = "rng")]
+mod rng;
 mod rounds;
 mod salsa;
+#[cfg(feature = "secretbox")]
+#[cfg_attr(docsrs, doc(cfg(feature = "secretbox")))]
+pub mod secretbox;
 #[cfg(feature = "xsalsa20")]
 mod xsalsa;
 
 pub use crate::salsa::{Key, Nonce, Salsa, Salsa12, Salsa20, Salsa8};
 
+#[cfg(feature = "io")]
+#[cfg_attr(docsrs, doc(cfg(feature = "io")))]
+pub use cipher_io::{StreamReader, StreamWriter};
+
+#[cfg(feature = "state")]
+#[cfg_attr(docsrs, doc(cfg(feature = "state")))]
+pub use cipher_state::StateError;
+
 #[cfg(feature = "expose-core")]
 pub use crate::{
     core::Core,
@@ -75,6 +105,14 @@ pub use crate::{
 #[cfg(feature = "hsalsa20")]
 pub use crate::xsalsa::hsalsa20;
 
+#[cfg(feature = "rng")]
+pub use crate::rng::{
+    Salsa12Rng, Salsa12RngCore, Salsa20Rng, Salsa20RngCore, Salsa8Rng, Salsa8RngCore,
+};
+
+#[cfg(feature = "secretbox")]
+pub use crate::secretbox::XSalsa20Poly1305;
+
 #[cfg(feature = "xsalsa20")]
 pub use crate::xsalsa::{XNonce, XSalsa20};
 
@@ -84,8 +122,5 @@ pub const BLOCK_SIZE: usize = 64;
 /// Size of a Salsa20 key in bytes
 pub const KEY_SIZE: usize = 32;
 
-/// Number of 32-bit words in the Salsa20 state
-const STATE_WORDS: usize = 16;
-
 /// State initialization constant ("expand 32-byte k")
 const CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
@@ -0,0 +1,281 @@
+//! Block RNG based on rand_core::BlockRng
+
+use rand_core::{
+    block::{BlockRng, BlockRngCore},
+    CryptoRng, Error, RngCore, SeedableRng,
+};
+
+use crate::{
+    backend::{Core, BUFFER_SIZE},
+    rounds::{R12, R20, R8},
+    BLOCK_SIZE, KEY_SIZE,
+};
+use core::convert::TryInto;
+
+/// Number of 32-bit words per Salsa20 block (fixed by algorithm definition).
+const BLOCK_WORDS: u8 = (BLOCK_SIZE / 4) as u8;
+
+/// Number of Salsa20 blocks produced by each call to `BlockRngCore::generate`.
+///
+/// This depends on the backend in use (e.g. the AVX2 backend computes two
+/// blocks in parallel).
+const BUF_BLOCKS: u8 = (BUFFER_SIZE / BLOCK_SIZE) as u8;
+
+macro_rules! impl_salsa_rng {
+    ($name:ident, $core:ident, $rounds:ident, $doc:expr) => {
+        #[doc = $doc]
+        #[cfg_attr(docsrs, doc(cfg(feature = "rng")))]
+        pub struct $name(BlockRng<$core>);
+
+        impl SeedableRng for $name {
+            type Seed = [u8; KEY_SIZE];
+
+            #[inline]
+            fn from_seed(seed: Self::Seed) -> Self {
+                let core = $core::from_seed(seed);
+                Self(BlockRng::new(core))
+            }
+        }
+
+        impl RngCore for $name {
+            #[inline]
+            fn next_u32(&mut self) -> u32 {
+                self.0.next_u32()
+            }
+
+            #[inline]
+            fn next_u64(&mut self) -> u64 {
+                self.0.next_u64()
+            }
+
+            #[inline]
+            fn fill_bytes(&mut self, bytes: &mut [u8]) {
+                self.0.fill_bytes(bytes)
+            }
+
+            #[inline]
+            fn try_fill_bytes(&mut self, bytes: &mut [u8]) -> Result<(), Error> {
+                self.0.try_fill_bytes(bytes)
+            }
+        }
+
+        impl CryptoRng for $name {}
+
+        impl $name {
+            /// Get the offset from the start of the stream, in 32-bit words.
+            ///
+            /// Since the generated blocks are 16 words (2<sup>4</sup>) long and the
+            /// counter is 64-bits, the offset is a 68-bit number. Sub-word offsets are
+            /// not supported, hence the result can simply be multiplied by 4 to get a
+            /// byte-offset.
+            #[inline]
+            pub fn get_word_pos(&self) -> u128 {
+                // `counter` points at the block following the buffered output
+                let buf_start_block = self.0.core.counter.wrapping_sub(BUF_BLOCKS.into());
+                let buf_offset_words = self.0.index() as u64;
+                let pos_block =
+                    buf_start_block.wrapping_add(buf_offset_words / u64::from(BLOCK_WORDS));
+                let block_offset_words = buf_offset_words % u64::from(BLOCK_WORDS);
+                u128::from(pos_block) * u128::from(BLOCK_WORDS) + u128::from(block_offset_words)
+            }
+
+            /// Set the offset from the start of the stream, in 32-bit words.
+            ///
+            /// As with `get_word_pos`, we use a 68-bit number. Since the generator
+            /// simply cycles at the end of its period (1 ZiB), we ignore the upper
+            /// 60 bits.
+            #[inline]
+            pub fn set_word_pos(&mut self, word_offset: u128) {
+                let block = (word_offset / u128::from(BLOCK_WORDS)) as u64;
+                self.0.core.counter = block;
+                self.0
+                    .generate_and_set((word_offset % u128::from(BLOCK_WORDS)) as usize);
+            }
+
+            /// Set the stream number.
+            ///
+            /// This is initialized to zero; 2<sup>64</sup> unique streams of output
+            /// are available per seed/key. The stream number occupies the 64-bit
+            /// nonce of Salsa20.
+            ///
+            /// Note that in order to reproduce Salsa20 output with a specific 64-bit
+            /// nonce, one can convert that nonce to a `u64` in little-endian fashion
+            /// and pass to this function. The word position is retained, so output
+            /// continues from the same offset within the new stream.
+            #[inline]
+            pub fn set_stream(&mut self, stream: u64) {
+                self.0.core.set_stream(stream);
+
+                if self.0.index() != BUFFER_SIZE / 4 {
+                    // regenerate buffer with the new stream, preserving position
+                    let wp = self.get_word_pos();
+                    self.set_word_pos(wp);
+                }
+            }
+
+            /// Get the stream number.
+            #[inline]
+            pub fn get_stream(&self) -> u64 {
+                self.0.core.stream
+            }
+
+            /// Get the seed.
+            #[inline]
+            pub fn get_seed(&self) -> [u8; KEY_SIZE] {
+                self.0.core.seed
+            }
+        }
+
+        #[doc = "Core random number generator, for use with [`rand_core::block::BlockRng`]"]
+        #[cfg_attr(docsrs, doc(cfg(feature = "rng")))]
+        pub struct $core {
+            block: Core<$rounds>,
+            seed: [u8; KEY_SIZE],
+            stream: u64,
+            counter: u64,
+        }
+
+        impl $core {
+            /// Re-key the core function to output the given stream.
+            fn set_stream(&mut self, stream: u64) {
+                self.block = Core::new(&self.seed.into(), &stream.to_le_bytes().into());
+                self.stream = stream;
+            }
+        }
+
+        impl SeedableRng for $core {
+            type Seed = [u8; KEY_SIZE];
+
+            #[inline]
+            fn from_seed(seed: Self::Seed) -> Self {
+                let block = Core::new(&seed.into(), &Default::default());
+                Self {
+                    block,
+                    seed,
+                    stream: 0,
+                    counter: 0,
+                }
+            }
+        }
+
+        impl BlockRngCore for $core {
+            type Item = u32;
+            type Results = [u32; BUFFER_SIZE / 4];
+
+            /// Generate the next `BUF_BLOCKS` blocks of output.
+            ///
+            /// The block counter is 64-bits wide, so each stream provides
+            /// 2<sup>64</sup> blocks (1 ZiB) of output, after which the counter
+            /// wraps around and the stream repeats from the beginning.
+            fn generate(&mut self, results: &mut Self::Results) {
+                let mut buffer = [0u8; BUFFER_SIZE];
+                self.block.generate(self.counter, &mut buffer);
+
+                for (n, chunk) in results.iter_mut().zip(buffer.chunks_exact(4)) {
+                    *n = u32::from_le_bytes(chunk.try_into().unwrap());
+                }
+
+                self.counter = self.counter.wrapping_add(BUF_BLOCKS.into());
+            }
+        }
+
+        impl CryptoRng for $core {}
+    };
+}
+
+impl_salsa_rng!(
+    Salsa8Rng,
+    Salsa8RngCore,
+    R8,
+    "Random number generator over the Salsa20/8 stream cipher."
+);
+
+impl_salsa_rng!(
+    Salsa12Rng,
+    Salsa12RngCore,
+    R12,
+    "Random number generator over the Salsa20/12 stream cipher."
+);
+
+impl_salsa_rng!(
+    Salsa20Rng,
+    Salsa20RngCore,
+    R20,
+    "Random number generator over the Salsa20 stream cipher."
+);
+
+#[cfg(test)]
+mod tests {
+    use super::Salsa20Rng;
+    use crate::KEY_SIZE;
+    use rand_core::{RngCore, SeedableRng};
+
+    const KEY: [u8; KEY_SIZE] = [
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    ];
+
+    #[test]
+    fn test_salsa_true_values() {
+        // Set 1, vector 0 from the eSTREAM Salsa20 test vectors
+        let mut seed = [0u8; KEY_SIZE];
+        seed[0] = 0x80;
+        let expected = [
+            0xdd8fbee3, 0xe3a2ec8b, 0x47f98eea, 0xe7a6295b, 0xe1513900, 0x385c7a09, 0x5f7a3bd2,
+            0x44689fad, 0x55972cb2, 0xc723279e, 0xe43fbdcb, 0x079a8dfc, 0x832a6544, 0x469c2ae7,
+            0x4daf7618, 0x17a1f17e,
+        ];
+
+        let mut rng = Salsa20Rng::from_seed(seed);
+        let mut results = [0u32; 16];
+        for i in results.iter_mut() {
+            *i = rng.next_u32();
+        }
+        assert_eq!(results, expected);
+        assert_eq!(rng.get_word_pos(), 16);
+    }
+
+    #[test]
+    fn test_salsa_nonce() {
+        // All-zero key with an IV of `80 00 00 00 00 00 00 00`, interpreted
+        // in little-endian order (see `EXPECTED_KEY0_IV1` in `tests/lib.rs`)
+        let mut rng = Salsa20Rng::from_seed([0u8; KEY_SIZE]);
+        rng.set_stream(0x80);
+        assert_eq!(rng.get_stream(), 0x80);
+
+        let mut results = [0u32; 16];
+        for i in results.iter_mut() {
+            *i = rng.next_u32();
+        }
+        let expected = [
+            0xc43dba2a, 0x0047495b, 0x51c8147b, 0x564469cd, 0x59ad03b3, 0x286665a4, 0x05670003,
+            0x3e6c3d67, 0x51d3f129, 0x0504fc0d, 0x41033c46, 0xe3070e4e, 0x81f1f159, 0x43b2686c,
+            0xeed3194a, 0x734846e0,
+        ];
+        assert_eq!(results, expected);
+    }
+
+    /// Output at any stream and word position is the Salsa20 keystream for
+    /// the stream number as nonce, at four times the word position
+    #[test]
+    fn test_salsa_matches_cipher() {
+        use crate::Salsa20;
+        use cipher::{NewCipher, StreamCipher, StreamCipherSeek};
+
+        let mut rng = Salsa20Rng::from_seed(KEY);
+        rng.set_stream(0x0123_4567_89ab_cdef);
+        let mut cipher = Salsa20::new(&KEY.into(), &0x0123_4567_89ab_cdef_u64.to_le_bytes().into());
+
+        for &pos in &[0u64, 7, 16, 35, 127, 1 << 40] {
+            rng.set_word_pos(pos.into());
+            let mut actual = [0u8; 200];
+            rng.fill_bytes(&mut actual);
+            assert_eq!(rng.get_word_pos(), u128::from(pos) + 50);
+
+            let mut expected = [0u8; 200];
+            cipher.seek(pos * 4);
+            cipher.apply_keystream(&mut expected);
+            assert_eq!(&actual[..], &expected[..]);
+        }
+    }
+}
@@ -5,7 +5,7 @@
 // TODO(tarcieri): figure out how to unify this with the `ctr` crate (see #95)
 
 use crate::{
-    core::Core,
+    backend::{Core, BUFFER_SIZE},
     rounds::{Rounds, R12, R20, R8},
     BLOCK_SIZE,
 };
@@ -19,6 +19,14 @@ use core::fmt;
 #[cfg(docsrs)]
 use cipher::generic_array::GenericArray;
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+#[cfg(feature = "state")]
+use crate::{StateError, KEY_SIZE};
+#[cfg(feature = "state")]
+use cipher_state::{SeekableState, POSITION_LEN};
+
 /// Key type.
 ///
 /// Implemented as an alias for [`GenericArray`].
@@ -44,7 +52,16 @@ pub type Salsa12 = Salsa<R12>;
 pub type Salsa20 = Salsa<R20>;
 
 /// Internal buffer
-type Buffer = [u8; BLOCK_SIZE];
+type Buffer = [u8; BUFFER_SIZE];
+
+/// How much to increment the counter by for each buffer we generate.
+/// Normally this is 1 but the SIMD backends use double-wide buffers.
+const COUNTER_INCR: u64 = (BUFFER_SIZE as u64) / (BLOCK_SIZE as u64);
+
+/// Size of the chunks of data processed by each task in
+/// [`Salsa::par_apply_keystream`], a multiple of `BUFFER_SIZE`.
+#[cfg(feature = "rayon")]
+const PAR_CHUNK_SIZE: usize = 1 << 16;
 
 /// The Salsa20 family of stream ciphers
 /// (implemented generically over a number of rounds).
@@ -62,6 +79,10 @@ pub struct Salsa<R: Rounds> {
 
     /// Current counter value relative to the start of the keystream
     counter: u64,
+
+    /// Nonce the cipher was initialized with, saved by `to_bytes`
+    #[cfg_attr(not(feature = "state"), allow(dead_code))]
+    nonce: Nonce,
 }
 
 impl<R: Rounds> NewCipher for Salsa<R> {
@@ -76,85 +97,219 @@ impl<R: Rounds> NewCipher for Salsa<R> {
 
         Self {
             block,
-            buffer: [0u8; BLOCK_SIZE],
+            buffer: [0u8; BUFFER_SIZE],
             buffer_pos: 0,
             counter: 0,
+            nonce: *nonce,
         }
     }
 }
 
 impl<R: Rounds> StreamCipher for Salsa<R> {
-    fn try_apply_keystream(&mut self, mut data: &mut [u8]) -> Result<(), LoopError> {
+    fn try_apply_keystream(&mut self, data: &mut [u8]) -> Result<(), LoopError> {
+        self.process(data, xor, |core, counter, buf| {
+            core.apply_keystream(counter, buf)
+        })
+    }
+}
+
+impl<R: Rounds> StreamCipherSeek for Salsa<R> {
+    fn try_current_pos<T: SeekNum>(&self) -> Result<T, OverflowError> {
+        let bs = BLOCK_SIZE as u8;
+        let counter = self
+            .counter
+            .checked_add(u64::from(self.buffer_pos / bs))
+            .ok_or(OverflowError)?;
+        T::from_block_byte(counter, self.buffer_pos % bs, bs)
+    }
+
+    fn try_seek<T: SeekNum>(&mut self, pos: T) -> Result<(), LoopError> {
+        let res: (u64, u8) = pos.to_block_byte(BUFFER_SIZE as u8)?;
+        self.counter = res.0.checked_mul(COUNTER_INCR).ok_or(LoopError)?;
+        self.buffer_pos = res.1;
+        if self.buffer_pos != 0 {
+            self.block.generate(self.counter, &mut self.buffer);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "state")]
+#[cfg_attr(docsrs, doc(cfg(feature = "state")))]
+impl<R: Rounds> Salsa<R> {
+    /// Length of the state serialized by [`Salsa::to_bytes`], with or
+    /// without the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(KEY_SIZE, 8 + POSITION_LEN, with_key)
+    }
+
+    /// Serialize the nonce and keystream position into `buf`, returning the
+    /// written prefix of it.
+    ///
+    /// The cipher does not retain its key, so it's included only if passed
+    /// as `key`, which must be the key the cipher was initialized with. A
+    /// state including the key can decrypt anything encrypted under that key,
+    /// with any nonce, so store it only where the key itself may be stored.
+    ///
+    /// The format is a version byte, a flags byte, the key if included, the
+    /// nonce, then the number of the current block as a little endian `u64`
+    /// and the byte position within that block.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&Key>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let pos = self.try_current_pos().map_err(|_| StateError)?;
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write_seekable(buf, key, &self.nonce, pos, BLOCK_SIZE as u8)
+    }
+
+    /// Restore a cipher from a state serialized by [`Salsa::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    pub fn from_bytes(bytes: &[u8], key: Option<&Key>) -> Result<Self, StateError> {
+        let state = SeekableState::read(bytes, KEY_SIZE, 8, BLOCK_SIZE as u8)?;
+        let key = Key::from_slice(state.key(key.map(|k| k.as_slice()))?);
+        let mut cipher = Self::new(key, Nonce::from_slice(state.nonce()));
+        cipher.try_seek(state.pos()).map_err(|_| StateError)?;
+        Ok(cipher)
+    }
+}
+
+impl<R: Rounds> Salsa<R> {
+    /// Write keystream to `out`, overwriting its contents.
+    ///
+    /// The output and the position of the cipher afterwards are the same as
+    /// for [`StreamCipher::apply_keystream`] on a zeroed buffer, without
+    /// paying for the XOR, e.g. to derive keys from the keystream.
+    ///
+    /// # Panics
+    ///
+    /// If the end of the keystream is reached with the given length.
+    pub fn write_keystream(&mut self, out: &mut [u8]) {
+        self.try_write_keystream(out).unwrap();
+    }
+
+    /// Write keystream to `out`, see [`Salsa::write_keystream`].
+    ///
+    /// Returns [`LoopError`] and leaves the cipher unchanged if the end of
+    /// the keystream is reached with the given length.
+    pub fn try_write_keystream(&mut self, out: &mut [u8]) -> Result<(), LoopError> {
+        self.process(out, copy, |core, counter, buf| core.generate(counter, buf))
+    }
+
+    /// Combine `data` with the keystream, using `op` for the bytes from the
+    /// internal buffer and `bulk` for whole buffers of keystream.
+    #[inline]
+    fn process(
+        &mut self,
+        mut data: &mut [u8],
+        op: impl Fn(&mut [u8], &[u8]),
+        mut bulk: impl FnMut(&Core<R>, u64, &mut [u8]),
+    ) -> Result<(), LoopError> {
         self.check_data_len(data)?;
         let pos = self.buffer_pos as usize;
-        debug_assert!(BLOCK_SIZE > pos);
+        debug_assert!(BUFFER_SIZE > pos);
 
         let mut counter = self.counter;
         // xor with leftover bytes from the last call if any
         if pos != 0 {
-            if data.len() < BLOCK_SIZE - pos {
+            if data.len() < BUFFER_SIZE - pos {
                 let n = pos + data.len();
-                xor(data, &self.buffer[pos..n]);
+                op(data, &self.buffer[pos..n]);
                 self.buffer_pos = n as u8;
                 return Ok(());
             } else {
-                let (l, r) = data.split_at_mut(BLOCK_SIZE - pos);
+                let (l, r) = data.split_at_mut(BUFFER_SIZE - pos);
                 data = r;
-                xor(l, &self.buffer[pos..]);
-                counter += 1;
+                op(l, &self.buffer[pos..]);
+                counter = counter.wrapping_add(COUNTER_INCR);
             }
         }
 
-        let mut chunks = data.chunks_exact_mut(BLOCK_SIZE);
+        let mut chunks = data.chunks_exact_mut(BUFFER_SIZE);
         for chunk in &mut chunks {
-            self.block.apply_keystream(counter, chunk);
-            counter += 1;
+            bulk(&self.block, counter, chunk);
+            counter = counter.wrapping_add(COUNTER_INCR);
         }
 
         let rem = chunks.into_remainder();
         self.buffer_pos = rem.len() as u8;
         self.counter = counter;
         if !rem.is_empty() {
-            self.block.counter_setup(counter);
-            self.block.generate(&mut self.buffer);
-            xor(rem, &self.buffer[..rem.len()]);
+            self.block.generate(counter, &mut self.buffer);
+            op(rem, &self.buffer[..rem.len()]);
         }
 
         Ok(())
     }
-}
-
-impl<R: Rounds> StreamCipherSeek for Salsa<R> {
-    fn try_current_pos<T: SeekNum>(&self) -> Result<T, OverflowError> {
-        T::from_block_byte(self.counter, self.buffer_pos, BLOCK_SIZE as u8)
-    }
 
-    fn try_seek<T: SeekNum>(&mut self, pos: T) -> Result<(), LoopError> {
-        let res = pos.to_block_byte(BLOCK_SIZE as u8)?;
-        self.counter = res.0;
-        self.buffer_pos = res.1;
-        if self.buffer_pos != 0 {
-            self.block.counter_setup(self.counter);
-            self.block.generate(&mut self.buffer);
-        }
-        Ok(())
-    }
-}
-
-impl<R: Rounds> Salsa<R> {
     fn check_data_len(&self, data: &[u8]) -> Result<(), LoopError> {
-        let leftover_bytes = BLOCK_SIZE - self.buffer_pos as usize;
+        let leftover_bytes = BUFFER_SIZE - self.buffer_pos as usize;
         if data.len() < leftover_bytes {
             return Ok(());
         }
-        let blocks = 1 + (data.len() - leftover_bytes) / BLOCK_SIZE;
-        self.counter
-            .checked_add(blocks as u64)
+        let buffers = 1 + (data.len() - leftover_bytes) / BUFFER_SIZE;
+        (buffers as u64)
+            .checked_mul(COUNTER_INCR)
+            .and_then(|blocks| self.counter.checked_add(blocks))
             .ok_or(LoopError)
             .map(|_| ())
     }
 }
 
+#[cfg(feature = "rayon")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+impl<R: Rounds + Sync> Salsa<R> {
+    /// Apply keystream to `data` using the [`rayon`] thread pool.
+    ///
+    /// The output and the position of the cipher afterwards are the same as
+    /// for [`StreamCipher::apply_keystream`]. Only the whole buffers of
+    /// keystream following the current position are generated in parallel,
+    /// so this pays off for large inputs.
+    ///
+    /// # Panics
+    ///
+    /// If the end of the keystream is reached with the given data length.
+    pub fn par_apply_keystream(&mut self, data: &mut [u8]) {
+        self.try_par_apply_keystream(data).unwrap();
+    }
+
+    /// Apply keystream to `data` using the [`rayon`] thread pool, see
+    /// [`Salsa::par_apply_keystream`].
+    ///
+    /// Returns [`LoopError`] and leaves the cipher unchanged if the end of
+    /// the keystream is reached with the given data length.
+    pub fn try_par_apply_keystream(&mut self, data: &mut [u8]) -> Result<(), LoopError> {
+        self.check_data_len(data)?;
+
+        // use up the leftover keystream first to get to a buffer boundary
+        let head = match self.buffer_pos as usize {
+            0 => 0,
+            pos => core::cmp::min(BUFFER_SIZE - pos, data.len()),
+        };
+        let (head, data) = data.split_at_mut(head);
+        self.try_apply_keystream(head)?;
+
+        let body_len = data.len() - data.len() % BUFFER_SIZE;
+        let (body, tail) = data.split_at_mut(body_len);
+        let counter = self.counter;
+        let block = &self.block;
+        body.par_chunks_mut(PAR_CHUNK_SIZE)
+            .enumerate()
+            .for_each(|(i, chunk)| {
+                let mut counter = counter + (i * PAR_CHUNK_SIZE / BLOCK_SIZE) as u64;
+                for buf in chunk.chunks_exact_mut(BUFFER_SIZE) {
+                    block.apply_keystream(counter, buf);
+                    counter = counter.wrapping_add(COUNTER_INCR);
+                }
+            });
+        self.counter += (body_len / BLOCK_SIZE) as u64;
+
+        self.try_apply_keystream(tail)
+    }
+}
+
 impl<R: Rounds> fmt::Debug for Salsa<R> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         write!(f, "Cipher {{ .. }}")
@@ -168,3 +323,8 @@ fn xor(buf: &mut [u8], key: &[u8]) {
         *a ^= *b;
     }
 }
+
+#[inline(always)]
+fn copy(buf: &mut [u8], key: &[u8]) {
+    buf.copy_from_slice(key);
+}
@@ -0,0 +1,183 @@
+//! XSalsa20Poly1305 authenticated encryption, compatible with NaCl's and
+//! libsodium's `crypto_secretbox`.
+//!
+//! The Poly1305 one-time key is the first 32 bytes of the XSalsa20 keystream,
+//! and the message is encrypted with the keystream starting at byte 32. The
+//! sealed box consists of the 16-byte MAC followed by the ciphertext, i.e.
+//! the same wire format as libsodium's `crypto_secretbox_easy`.
+//!
+//! # Usage
+//!
+//! ```
+//! use salsa20::{Key, XNonce, XSalsa20Poly1305};
+//!
+//! let key = Key::from_slice(b"an example very very secret key.");
+//! let nonce = XNonce::from_slice(b"extra long unique nonce!");
+//! let secretbox = XSalsa20Poly1305::new(key);
+//!
+//! // the first `MAC_SIZE` bytes of the buffer are reserved for the MAC
+//! let mut buffer = *b"\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0plaintext message";
+//! secretbox.seal_in_place(nonce, &mut buffer).unwrap();
+//!
+//! let plaintext = secretbox.open_in_place(nonce, &mut buffer).unwrap();
+//! assert_eq!(plaintext, b"plaintext message");
+//! ```
+
+use crate::{Key, XNonce, XSalsa20};
+use cipher::{consts::U16, generic_array::GenericArray, NewCipher, StreamCipher};
+use core::fmt;
+use poly1305::{universal_hash::NewUniversalHash, Poly1305};
+use subtle::ConstantTimeEq;
+
+#[cfg(feature = "std")]
+use std::vec::Vec;
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Size of the Poly1305 MAC which prefixes a sealed box in bytes
+/// (`crypto_secretbox_MACBYTES`)
+pub const MAC_SIZE: usize = 16;
+
+/// Poly1305 authentication tag
+pub type Tag = GenericArray<u8, U16>;
+
+/// Error type returned when a box fails to open, either because it's too
+/// short to contain a MAC or because the MAC doesn't match.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Error;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("secretbox::Error")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// XSalsa20Poly1305 (a.k.a. NaCl `crypto_secretbox`) authenticated
+/// encryption.
+#[derive(Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "secretbox")))]
+pub struct XSalsa20Poly1305 {
+    key: Key,
+}
+
+impl XSalsa20Poly1305 {
+    /// Create a new XSalsa20Poly1305 instance with the given key.
+    pub fn new(key: &Key) -> Self {
+        Self { key: *key }
+    }
+
+    /// Seal a box in-place.
+    ///
+    /// The first [`MAC_SIZE`] bytes of `buffer` are overwritten with the MAC,
+    /// and the remainder of the buffer is encrypted. Returns an error if
+    /// `buffer` is shorter than [`MAC_SIZE`].
+    pub fn seal_in_place(&self, nonce: &XNonce, buffer: &mut [u8]) -> Result<(), Error> {
+        if buffer.len() < MAC_SIZE {
+            return Err(Error);
+        }
+
+        let (mac, message) = buffer.split_at_mut(MAC_SIZE);
+        let tag = self.seal_in_place_detached(nonce, message);
+        mac.copy_from_slice(&tag);
+        Ok(())
+    }
+
+    /// Open a box in-place, returning the decrypted message.
+    ///
+    /// `buffer` consists of the MAC followed by the ciphertext. The buffer is
+    /// left unmodified if authentication fails.
+    pub fn open_in_place<'a>(
+        &self,
+        nonce: &XNonce,
+        buffer: &'a mut [u8],
+    ) -> Result<&'a mut [u8], Error> {
+        if buffer.len() < MAC_SIZE {
+            return Err(Error);
+        }
+
+        let (mac, message) = buffer.split_at_mut(MAC_SIZE);
+        self.open_in_place_detached(nonce, message, Tag::from_slice(mac))?;
+        Ok(message)
+    }
+
+    /// Encrypt the given buffer in-place, returning the MAC.
+    pub fn seal_in_place_detached(&self, nonce: &XNonce, buffer: &mut [u8]) -> Tag {
+        let (mut cipher, mac) = self.init(nonce);
+        cipher.apply_keystream(buffer);
+        mac.compute_unpadded(buffer).into_bytes()
+    }
+
+    /// Authenticate the given buffer against `tag` and, if it matches,
+    /// decrypt it in-place.
+    ///
+    /// The buffer is left unmodified if authentication fails.
+    pub fn open_in_place_detached(
+        &self,
+        nonce: &XNonce,
+        buffer: &mut [u8],
+        tag: &Tag,
+    ) -> Result<(), Error> {
+        let (mut cipher, mac) = self.init(nonce);
+        let expected = mac.compute_unpadded(buffer).into_bytes();
+
+        if bool::from(expected.as_slice().ct_eq(tag.as_slice())) {
+            cipher.apply_keystream(buffer);
+            Ok(())
+        } else {
+            Err(Error)
+        }
+    }
+
+    /// Seal a box containing `plaintext`, returning the MAC followed by the
+    /// ciphertext.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn seal(&self, nonce: &XNonce, plaintext: &[u8]) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(MAC_SIZE + plaintext.len());
+        buffer.extend_from_slice(&[0u8; MAC_SIZE]);
+        buffer.extend_from_slice(plaintext);
+        self.seal_in_place(nonce, &mut buffer)
+            .expect("buffer contains space for the MAC");
+        buffer
+    }
+
+    /// Open a box consisting of the MAC followed by the ciphertext, returning
+    /// the decrypted message.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn open(&self, nonce: &XNonce, sealed: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut buffer = sealed.to_vec();
+        self.open_in_place(nonce, &mut buffer)?;
+        buffer.drain(..MAC_SIZE);
+        Ok(buffer)
+    }
+
+    /// Derive the Poly1305 key from the first 32 bytes of keystream, leaving
+    /// the cipher positioned at byte 32
+    fn init(&self, nonce: &XNonce) -> (XSalsa20, Poly1305) {
+        let mut cipher = XSalsa20::new(&self.key, nonce);
+        let mut mac_key = poly1305::Key::default();
+        cipher.apply_keystream(&mut mac_key);
+        (cipher, Poly1305::new(&mac_key))
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl Zeroize for XSalsa20Poly1305 {
+    fn zeroize(&mut self) {
+        self.key.as_mut_slice().zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl Drop for XSalsa20Poly1305 {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
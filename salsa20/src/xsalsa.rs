@@ -1,6 +1,6 @@
 //! XSalsa20 is an extended nonce variant of Salsa20
 
-use crate::{core::quarter_round, Key, Nonce, Salsa20, CONSTANTS};
+use crate::{backend::soft::quarter_round, Key, Nonce, Salsa20, CONSTANTS};
 use cipher::{
     consts::{U16, U24, U32},
     errors::{LoopError, OverflowError},
@@ -9,6 +9,11 @@ use cipher::{
 };
 use core::convert::TryInto;
 
+#[cfg(feature = "state")]
+use crate::{StateError, BLOCK_SIZE, KEY_SIZE};
+#[cfg(feature = "state")]
+use cipher_state::{SeekableState, POSITION_LEN};
+
 /// EXtended Salsa20 nonce (192-bit/24-byte)
 #[cfg_attr(docsrs, doc(cfg(feature = "xsalsa20")))]
 pub type XNonce = cipher::Nonce<XSalsa20>;
@@ -22,7 +27,10 @@ pub type XNonce = cipher::Nonce<XSalsa20>;
 /// The `xsalsa20` Cargo feature must be enabled in order to use this
 /// (which it is by default).
 #[cfg_attr(docsrs, doc(cfg(feature = "xsalsa20")))]
-pub struct XSalsa20(Salsa20);
+pub struct XSalsa20(
+    Salsa20,
+    #[cfg_attr(not(feature = "state"), allow(dead_code))] XNonce,
+);
 
 impl NewCipher for XSalsa20 {
     /// Key size in bytes
@@ -37,7 +45,7 @@ impl NewCipher for XSalsa20 {
         let mut padded_nonce = Nonce::default();
         padded_nonce.copy_from_slice(&nonce[16..]);
 
-        let mut result = XSalsa20(Salsa20::new(&subkey, &padded_nonce));
+        let mut result = XSalsa20(Salsa20::new(&subkey, &padded_nonce), *nonce);
 
         #[cfg(feature = "zeroize")]
         {
@@ -65,6 +73,61 @@ impl StreamCipherSeek for XSalsa20 {
     }
 }
 
+#[cfg(feature = "state")]
+#[cfg_attr(docsrs, doc(cfg(feature = "state")))]
+impl XSalsa20 {
+    /// Length of the state serialized by [`XSalsa20::to_bytes`], with or
+    /// without the key.
+    pub fn state_len(with_key: bool) -> usize {
+        cipher_state::state_len(KEY_SIZE, 24 + POSITION_LEN, with_key)
+    }
+
+    /// Serialize the nonce and keystream position into `buf`, returning the
+    /// written prefix of it.
+    ///
+    /// The cipher does not retain its key, so it's included only if passed
+    /// as `key`, which must be the key the cipher was initialized with (not
+    /// the HSalsa20 subkey). The state stores the 24-byte nonce rather than
+    /// the subkey, so without the key it holds nothing secret.
+    ///
+    /// The format is the same as for [`Salsa::to_bytes`][crate::Salsa::to_bytes],
+    /// with the extended nonce.
+    pub fn to_bytes<'a>(
+        &self,
+        key: Option<&Key>,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], StateError> {
+        let pos = self.try_current_pos().map_err(|_| StateError)?;
+        let key = key.map(|k| k.as_slice());
+        cipher_state::write_seekable(buf, key, &self.1, pos, BLOCK_SIZE as u8)
+    }
+
+    /// Restore a cipher from a state serialized by [`XSalsa20::to_bytes`].
+    ///
+    /// `key` is used if given, otherwise the state must include the key.
+    pub fn from_bytes(bytes: &[u8], key: Option<&Key>) -> Result<Self, StateError> {
+        let state = SeekableState::read(bytes, KEY_SIZE, 24, BLOCK_SIZE as u8)?;
+        let key = Key::from_slice(state.key(key.map(|k| k.as_slice()))?);
+        let mut cipher = Self::new(key, XNonce::from_slice(state.nonce()));
+        cipher.try_seek(state.pos()).map_err(|_| StateError)?;
+        Ok(cipher)
+    }
+}
+
+impl XSalsa20 {
+    /// Write keystream to `out`, overwriting its contents, see
+    /// [`Salsa::write_keystream`][crate::Salsa::write_keystream].
+    pub fn write_keystream(&mut self, out: &mut [u8]) {
+        self.0.write_keystream(out);
+    }
+
+    /// Write keystream to `out`, see
+    /// [`Salsa::try_write_keystream`][crate::Salsa::try_write_keystream].
+    pub fn try_write_keystream(&mut self, out: &mut [u8]) -> Result<(), LoopError> {
+        self.0.try_write_keystream(out)
+    }
+}
+
 /// The HSalsa20 function defined in the paper "Extending the Salsa20 nonce"
 ///
 /// <https://cr.yp.to/snuffle/xsalsa-20110204.pdf>
@@ -164,6 +164,25 @@ fn salsa20_long() {
     }
 }
 
+#[cfg(feature = "expose-core")]
+#[test]
+fn salsa20_expose_core() {
+    use salsa20::{Core, R20};
+
+    let mut core = Core::<R20>::new(&GenericArray::from(KEY_LONG), &GenericArray::from(IV_LONG));
+    let mut buf = [0u8; 64];
+    core.generate(&mut buf);
+    assert_eq!(&buf[..], &EXPECTED_LONG[..64]);
+
+    let mut buf = [0u8; 64];
+    core.apply_keystream(2, &mut buf);
+    assert_eq!(&buf[..], &EXPECTED_LONG[128..192]);
+
+    // `generate` uses the counter last passed to `apply_keystream`
+    core.generate(&mut buf);
+    assert_eq!(&buf[..], &EXPECTED_LONG[128..192]);
+}
+
 #[test]
 #[ignore]
 fn salsa20_offsets() {
@@ -213,3 +232,201 @@ fn xsalsa20_encrypt_hello_world() {
 
     assert_eq!(buf, EXPECTED_XSALSA20_HELLO_WORLD);
 }
+
+/// Serialized states must resume the keystream where it was saved
+#[cfg(feature = "state")]
+mod state {
+    use salsa20::Salsa20;
+    cipher_state::stream_state_test!(salsa20, Salsa20);
+    #[cfg(feature = "xsalsa20")]
+    cipher_state::stream_state_test!(xsalsa20, salsa20::XSalsa20);
+}
+
+#[test]
+fn salsa20_write_keystream() {
+    let new = || Salsa20::new(&GenericArray::from(KEY_LONG), &GenericArray::from(IV_LONG));
+
+    let mut cipher = new();
+    let mut buf = [0xffu8; 256];
+    let mut pos = 0;
+    for (i, &len) in [1, 63, 64, 65, 7, 56].iter().enumerate() {
+        let chunk = &mut buf[pos..pos + len];
+        if i % 2 == 0 {
+            cipher.write_keystream(chunk);
+        } else {
+            chunk.iter_mut().for_each(|b| *b = 0);
+            cipher.apply_keystream(chunk);
+        }
+        pos += len;
+        assert_eq!(cipher.current_pos::<usize>(), pos);
+    }
+    assert_eq!(&buf[..], &EXPECTED_LONG[..]);
+
+    let mut cipher = new();
+    cipher.seek(100u8);
+    cipher.write_keystream(&mut buf[..50]);
+    assert_eq!(&buf[..50], &EXPECTED_LONG[100..150]);
+}
+
+#[cfg(feature = "xsalsa20")]
+#[test]
+fn xsalsa20_write_keystream() {
+    let mut cipher = XSalsa20::new(
+        &GenericArray::from(KEY_XSALSA20),
+        &GenericArray::from(IV_XSALSA20),
+    );
+    let mut buf = [0xffu8; 64];
+    cipher.write_keystream(&mut buf[..3]);
+    cipher.write_keystream(&mut buf[3..]);
+    assert_eq!(&buf[..], &EXPECTED_XSALSA20_ZEROS[..]);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn salsa20_par_apply_keystream() {
+    const LEN: usize = 3 * (1 << 16) + 1000;
+    let new = || Salsa20::new(&GenericArray::from(KEY_LONG), &GenericArray::from(IV_LONG));
+    let mut expected = vec![0u8; LEN + 100];
+    new().apply_keystream(&mut expected);
+
+    for &(start, len) in &[
+        (0, 0),
+        (5, 100),
+        (0, LEN),
+        (1, LEN - 1),
+        (64, 1 << 16),
+        (130, LEN - 130),
+    ] {
+        let mut cipher = new();
+        cipher.seek(start as u64);
+        let mut buf = vec![0u8; len + 100];
+        cipher.par_apply_keystream(&mut buf[..len]);
+        assert_eq!(cipher.current_pos::<usize>(), start + len);
+        cipher.apply_keystream(&mut buf[len..]);
+        assert!(buf[..] == expected[start..start + len + 100]);
+    }
+}
+
+/// XSalsa20Poly1305 (`crypto_secretbox`) test vectors.
+///
+/// Adapted from NaCl's `tests/secretbox.c` and `tests/secretbox.out`
+#[cfg(all(feature = "secretbox", feature = "std"))]
+mod secretbox {
+    use salsa20::{secretbox::MAC_SIZE, Key, XNonce, XSalsa20Poly1305};
+
+    const KEY: &[u8; 32] = &[
+        0x1b, 0x27, 0x55, 0x64, 0x73, 0xe9, 0x85, 0xd4, 0x62, 0xcd, 0x51, 0x19, 0x7a, 0x9a, 0x46,
+        0xc7, 0x60, 0x09, 0x54, 0x9e, 0xac, 0x64, 0x74, 0xf2, 0x06, 0xc4, 0xee, 0x08, 0x44, 0xf6,
+        0x83, 0x89,
+    ];
+
+    const NONCE: &[u8; 24] = &[
+        0x69, 0x69, 0x6e, 0xe9, 0x55, 0xb6, 0x2b, 0x73, 0xcd, 0x62, 0xbd, 0xa8, 0x75, 0xfc, 0x73,
+        0xd6, 0x82, 0x19, 0xe0, 0x03, 0x6b, 0x7a, 0x0b, 0x37,
+    ];
+
+    const PLAINTEXT: &[u8] = &[
+        0xbe, 0x07, 0x5f, 0xc5, 0x3c, 0x81, 0xf2, 0xd5, 0xcf, 0x14, 0x13, 0x16, 0xeb, 0xeb, 0x0c,
+        0x7b, 0x52, 0x28, 0xc5, 0x2a, 0x4c, 0x62, 0xcb, 0xd4, 0x4b, 0x66, 0x84, 0x9b, 0x64, 0x24,
+        0x4f, 0xfc, 0xe5, 0xec, 0xba, 0xaf, 0x33, 0xbd, 0x75, 0x1a, 0x1a, 0xc7, 0x28, 0xd4, 0x5e,
+        0x6c, 0x61, 0x29, 0x6c, 0xdc, 0x3c, 0x01, 0x23, 0x35, 0x61, 0xf4, 0x1d, 0xb6, 0x6c, 0xce,
+        0x31, 0x4a, 0xdb, 0x31, 0x0e, 0x3b, 0xe8, 0x25, 0x0c, 0x46, 0xf0, 0x6d, 0xce, 0xea, 0x3a,
+        0x7f, 0xa1, 0x34, 0x80, 0x57, 0xe2, 0xf6, 0x55, 0x6a, 0xd6, 0xb1, 0x31, 0x8a, 0x02, 0x4a,
+        0x83, 0x8f, 0x21, 0xaf, 0x1f, 0xde, 0x04, 0x89, 0x77, 0xeb, 0x48, 0xf5, 0x9f, 0xfd, 0x49,
+        0x24, 0xca, 0x1c, 0x60, 0x90, 0x2e, 0x52, 0xf0, 0xa0, 0x89, 0xbc, 0x76, 0x89, 0x70, 0x40,
+        0xe0, 0x82, 0xf9, 0x37, 0x76, 0x38, 0x48, 0x64, 0x5e, 0x07, 0x05,
+    ];
+
+    const CIPHERTEXT: &[u8] = &[
+        0xf3, 0xff, 0xc7, 0x70, 0x3f, 0x94, 0x00, 0xe5, 0x2a, 0x7d, 0xfb, 0x4b, 0x3d, 0x33, 0x05,
+        0xd9, 0x8e, 0x99, 0x3b, 0x9f, 0x48, 0x68, 0x12, 0x73, 0xc2, 0x96, 0x50, 0xba, 0x32, 0xfc,
+        0x76, 0xce, 0x48, 0x33, 0x2e, 0xa7, 0x16, 0x4d, 0x96, 0xa4, 0x47, 0x6f, 0xb8, 0xc5, 0x31,
+        0xa1, 0x18, 0x6a, 0xc0, 0xdf, 0xc1, 0x7c, 0x98, 0xdc, 0xe8, 0x7b, 0x4d, 0xa7, 0xf0, 0x11,
+        0xec, 0x48, 0xc9, 0x72, 0x71, 0xd2, 0xc2, 0x0f, 0x9b, 0x92, 0x8f, 0xe2, 0x27, 0x0d, 0x6f,
+        0xb8, 0x63, 0xd5, 0x17, 0x38, 0xb4, 0x8e, 0xee, 0xe3, 0x14, 0xa7, 0xcc, 0x8a, 0xb9, 0x32,
+        0x16, 0x45, 0x48, 0xe5, 0x26, 0xae, 0x90, 0x22, 0x43, 0x68, 0x51, 0x7a, 0xcf, 0xea, 0xbd,
+        0x6b, 0xb3, 0x73, 0x2b, 0xc0, 0xe9, 0xda, 0x99, 0x83, 0x2b, 0x61, 0xca, 0x01, 0xb6, 0xde,
+        0x56, 0x24, 0x4a, 0x9e, 0x88, 0xd5, 0xf9, 0xb3, 0x79, 0x73, 0xf6, 0x22, 0xa4, 0x3d, 0x14,
+        0xa6, 0x59, 0x9b, 0x1f, 0x65, 0x4c, 0xb4, 0x5a, 0x74, 0xe3, 0x55, 0xa5,
+    ];
+
+    #[test]
+    fn seal() {
+        let secretbox = XSalsa20Poly1305::new(Key::from_slice(KEY));
+        let sealed = secretbox.seal(XNonce::from_slice(NONCE), PLAINTEXT);
+        assert_eq!(sealed.as_slice(), CIPHERTEXT);
+    }
+
+    #[test]
+    fn open() {
+        let secretbox = XSalsa20Poly1305::new(Key::from_slice(KEY));
+        let plaintext = secretbox
+            .open(XNonce::from_slice(NONCE), CIPHERTEXT)
+            .unwrap();
+        assert_eq!(plaintext.as_slice(), PLAINTEXT);
+    }
+
+    #[test]
+    fn seal_open_in_place() {
+        let secretbox = XSalsa20Poly1305::new(Key::from_slice(KEY));
+        let nonce = XNonce::from_slice(NONCE);
+
+        let mut buffer = vec![0u8; MAC_SIZE];
+        buffer.extend_from_slice(PLAINTEXT);
+        secretbox.seal_in_place(nonce, &mut buffer).unwrap();
+        assert_eq!(buffer.as_slice(), CIPHERTEXT);
+
+        let plaintext = secretbox.open_in_place(nonce, &mut buffer).unwrap();
+        assert_eq!(plaintext, PLAINTEXT);
+    }
+
+    #[test]
+    fn open_modified() {
+        let secretbox = XSalsa20Poly1305::new(Key::from_slice(KEY));
+        let nonce = XNonce::from_slice(NONCE);
+
+        // tweak the MAC, then the ciphertext
+        for &i in &[0, MAC_SIZE, CIPHERTEXT.len() - 1] {
+            let mut buffer = CIPHERTEXT.to_vec();
+            buffer[i] ^= 0xaa;
+            assert!(secretbox.open_in_place(nonce, &mut buffer).is_err());
+
+            // the buffer must be left untouched on failure
+            buffer[i] ^= 0xaa;
+            assert_eq!(buffer.as_slice(), CIPHERTEXT);
+        }
+    }
+
+    #[test]
+    fn open_truncated() {
+        let secretbox = XSalsa20Poly1305::new(Key::from_slice(KEY));
+        let nonce = XNonce::from_slice(NONCE);
+
+        assert!(secretbox.open(nonce, &CIPHERTEXT[..MAC_SIZE - 1]).is_err());
+        assert!(secretbox.open(nonce, &[]).is_err());
+    }
+
+    #[test]
+    fn seal_empty() {
+        let secretbox = XSalsa20Poly1305::new(Key::from_slice(KEY));
+        let nonce = XNonce::from_slice(NONCE);
+
+        let sealed = secretbox.seal(nonce, &[]);
+        assert_eq!(sealed.len(), MAC_SIZE);
+        assert!(secretbox.open(nonce, &sealed).unwrap().is_empty());
+    }
+
+    /// A wiped instance seals like one with an all-zero key
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize() {
+        use zeroize::Zeroize;
+
+        let mut secretbox = XSalsa20Poly1305::new(Key::from_slice(KEY));
+        secretbox.zeroize();
+
+        let nonce = XNonce::from_slice(NONCE);
+        let expected = XSalsa20Poly1305::new(&Key::default()).seal(nonce, PLAINTEXT);
+        assert_eq!(secretbox.seal(nonce, PLAINTEXT), expected);
+    }
+}